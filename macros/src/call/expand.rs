@@ -23,6 +23,9 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is the index of each call, used to identify it when it is encoded.
+	let call_index = (0..methods.len()).map(|i| i as u8).collect::<Vec<_>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
 	// and the `Dispatch` trait logic to route a `caller` to access those functions.
 	let dispatch_impl = quote! {
@@ -30,6 +33,7 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq)]
 		pub enum Call<T: Config> {
 			#(
 				#fn_name { #( #args_name: #args_type),* },
@@ -57,8 +61,64 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 				Ok(())
 			}
 		}
+
+		impl<T: Config> Call<T> {
+			// The names of the calls exposed by this pallet, in the order of their call index.
+			pub const NAMES: &'static [&'static str] = &[ #( stringify!(#fn_name) ),* ];
+
+			// The name of this call, as listed in `NAMES`.
+			pub fn name(&self) -> &'static str {
+				match self {
+					#(
+						Call::#fn_name { .. } => stringify!(#fn_name),
+					)*
+				}
+			}
+		}
+	};
+
+	// This quote block implements `Encode` and `Decode` for the `enum Call`. A call is encoded as
+	// its index, followed by each of its arguments.
+	let codec_impl = quote! {
+		impl<T: Config> crate::support::Encode for Call<T>
+		where
+			#( #( #args_type: crate::support::Encode, )* )*
+		{
+			fn encode(&self) -> Vec<u8> {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							#[allow(unused_mut)]
+							let mut bytes = vec![#call_index];
+							#( bytes.extend(crate::support::Encode::encode(#args_name)); )*
+							bytes
+						},
+					)*
+				}
+			}
+		}
+
+		impl<T: Config> crate::support::Decode for Call<T>
+		where
+			#( #( #args_type: crate::support::Decode, )* )*
+		{
+			fn decode(input: &mut &[u8]) -> Result<Self, String> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					#(
+						#call_index => Ok(Call::#fn_name {
+							#( #args_name: crate::support::Decode::decode(input)? ),*
+						}),
+					)*
+					index => Err(format!("Invalid call index {}", index)),
+				}
+			}
+		}
 	};
 
 	// Return the generated code.
-	dispatch_impl.into()
+	quote! {
+		#dispatch_impl
+		#codec_impl
+	}
+	.into()
 }
@@ -16,15 +16,35 @@ pub fn call(
 ///   included in the runtime.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number. The runtime must implement `support::Hooks`, which is called while
+///   executing the block, and `support::Fees`, which charges a fee for each extrinsic. With the
+///   `logging` feature, the outcome of each extrinsic is logged through `log_dispatch` of the
+///   system pallet.
+/// - `fn pre_validate_block()` - runs all the structural checks of a block (block number, parent
+///   hash, empty blocks, size, nonces and weight) without executing it. `execute_block` calls it
+///   first.
+/// - `fn execute_block_with_mode()` - the same as `execute_block`, but lets the caller decide what
+///   happens to blocks which exceed the maximum block weight, see `support::OverweightMode`.
+/// - `fn supported_calls()` - lists the names of all the calls, as `pallet.call`, from the `NAMES`
+///   of the `Call` of each pallet.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
-/// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
+///   all pallets, including the system pallet. Its `name()` gives the names of the pallet and of
+///   the call, from the `name()` of the `Call` of each pallet.
 /// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   logic like incrementing the nonce of the user is included in the generated code. Calls are
+///   first checked by the `support::CallFilter` the runtime must implement.
+/// - implements the traits `support::Encode` and `support::Decode` for `RuntimeCall`, where each
+///   call is prefixed by the index of its pallet.
+/// - implements the trait `support::GetWeight` for `RuntimeCall`, by using the weight of the
+///   underlying pallet call. Each pallet must implement `support::GetWeight` for its `Call`.
+/// - `enum RuntimeEvent` - an "outer"-enum representing the accumulation of all possible events
+///   deposited in the system pallet, through `support::DepositEvent`. Each pallet must have an
+///   `enum Event`, usually buffered in a `support::EventBuffer`, and a `take_events()` function
+///   which is used to collect its events after dispatching one of its calls.
+/// - implements the trait `support::DepositEvent` for the runtime, for the events of any pallet,
+///   so that the runtime owns the collector of the events of all its pallets.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
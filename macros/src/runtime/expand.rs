@@ -10,6 +10,10 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	// This is a vector of all the pallet types, not including system.
 	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
 
+	// This is the index of each pallet, used to identify its calls when they are encoded. The
+	// system pallet always has the index 0.
+	let pallet_index = (1..=pallets.len()).map(|i| i as u8).collect::<Vec<_>>();
+
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
@@ -24,22 +28,243 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
+			// Check that the header of `block` can be executed next: it must have the next block
+			// number, reference the hash of the current block as its parent, and have an author
+			// with a valid account id, so that the fees of the block can be paid to them.
+			fn validate_header(&self, header: &types::Header) -> crate::support::DispatchResult {
+				if header.block_number != self.system.block_number() + 1 {
 					return Err(&"block number does not match what is expected")
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
+				if header.parent_hash != self.system.parent_hash() {
+					return Err(&"Parent hash mismatch")
+				}
+				<Self as system::Config>::validate_account(&header.author)?;
+				Ok(())
+			}
+
+			// Run all the structural checks of `block` without executing any of its extrinsics.
+			//
+			// The chain must not be halted. On top of the header checks, the block must not be
+			// empty unless `ALLOW_EMPTY_BLOCKS`, its encoded extrinsics must fit within
+			// `MAX_BLOCK_BYTES`, and it must fit within the maximum block weight. The nonces are
+			// checked in the order the extrinsics are executed, see
+			// `system::Pallet::ensure_valid_nonces`. Duplicate extrinsics are ignored if
+			// `DEDUPLICATE_EXTRINSICS`, except for the size of the block.
+			fn pre_validate_block(&self, block: &types::Block) -> crate::support::DispatchResult {
+				self.system.ensure_not_halted()?;
+				self.validate_header(&block.header)?;
+				<system::Pallet<Self>>::ensure_block_not_empty(block.extrinsics.len())?;
+				<system::Pallet<Self>>::ensure_block_size(Self::extrinsics_size(&block.extrinsics))?;
+
+				// Only copy the extrinsics when some of them must be skipped.
+				let extrinsics = if <Self as system::Config>::DEDUPLICATE_EXTRINSICS {
+					let mut extrinsics = block.extrinsics.clone();
+					crate::support::deduplicate(&mut extrinsics);
+					std::borrow::Cow::Owned(extrinsics)
+				} else {
+					std::borrow::Cow::Borrowed(&block.extrinsics)
+				};
+				self.system.ensure_valid_nonces(&extrinsics)?;
+
+				let total_weight: crate::support::Weight = extrinsics
+					.iter()
+					.map(|extrinsic| crate::support::GetWeight::weight(&extrinsic.call))
+					.sum();
+				if total_weight > <Self as system::Config>::MAX_BLOCK_WEIGHT {
+					return Err(&"Block weight limit exceeded")
+				}
+				Ok(())
+			}
+
+			// The total size of the encodings of `extrinsics`, in bytes.
+			fn extrinsics_size(extrinsics: &[types::Extrinsic]) -> usize {
+				extrinsics
+					.iter()
+					.map(|extrinsic| crate::support::Encode::encode(extrinsic).len())
+					.sum()
+			}
+
+			// Execute a block of extrinsics, rejecting blocks which fail `pre_validate_block`.
+			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+				self.pre_validate_block(&block)?;
+				self.execute_block_with_mode(block, crate::support::OverweightMode::Reject)?;
+				Ok(())
+			}
+
+			// Execute a block of extrinsics. Increments the block number, calls the
+			// `support::Hooks` of the runtime, charges the `support::Fees` of each extrinsic, and
+			// stores the hash of the block so the next block
+			// can reference it as its parent.
+			//
+			// Blocks are rejected while the chain is halted, or if their encoded extrinsics do not
+			// fit within `MAX_BLOCK_BYTES`.
+			// Duplicate extrinsics are then skipped if `DEDUPLICATE_EXTRINSICS`, and the rest are
+			// ordered following the configured `ORDERING_POLICY`, in which order their nonces are
+			// checked, whatever the `mode`. Then the `mode` decides what happens when the
+			// extrinsics weigh more than the maximum block weight. The outcome of each extrinsic,
+			// which fails if its fee cannot be settled, is recorded in the dispatch log of the
+			// system pallet, and passed to its log handler as a JSON line with the `logging`
+			// feature, if one is set. Returns the number of extrinsics which were applied.
+			fn execute_block_with_mode(
+				&mut self,
+				mut block: types::Block,
+				mode: crate::support::OverweightMode,
+			) -> Result<usize, &'static str> {
+				self.system.ensure_not_halted()?;
+				self.validate_header(&block.header)?;
+				<system::Pallet<Self>>::ensure_block_not_empty(block.extrinsics.len())?;
+				<system::Pallet<Self>>::ensure_block_size(Self::extrinsics_size(&block.extrinsics))?;
+
+				if <Self as system::Config>::DEDUPLICATE_EXTRINSICS {
+					for i in crate::support::deduplicate(&mut block.extrinsics) {
 						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
+							"Duplicate Extrinsic Skipped\n\tBlock Number: {}\n\tExtrinsic Number: {}",
+							block.header.block_number, i
+						);
+					}
+				}
+				<Self as system::Config>::ORDERING_POLICY.sort(&mut block.extrinsics);
+				// The nonces are checked once the extrinsics are in their execution order.
+				self.system.ensure_sequential_nonces(
+					block.extrinsics.iter().map(|extrinsic| (&extrinsic.caller, extrinsic.nonce)),
+				)?;
+
+				// Find how many extrinsics fit within the maximum block weight.
+				let max_weight = <Self as system::Config>::MAX_BLOCK_WEIGHT;
+				let mut total_weight: crate::support::Weight = 0;
+				let fitting = block
+					.extrinsics
+					.iter()
+					.take_while(|extrinsic| {
+						total_weight += crate::support::GetWeight::weight(&extrinsic.call);
+						total_weight <= max_weight
+					})
+					.count();
+				if fitting < block.extrinsics.len() {
+					match mode {
+						crate::support::OverweightMode::Reject =>
+							return Err(&"Block weight limit exceeded"),
+						crate::support::OverweightMode::Partial => block.extrinsics.truncate(fitting),
+					}
+				}
+				let applied = block.extrinsics.len();
+
+				self.system.inc_block_number();
+				let block_hash =
+					<system::Pallet<Self>>::hash(&crate::support::Encode::encode(&block.header));
+				crate::support::Hooks::on_initialize(self, &block.header);
+				for (i, extrinsic) in block.extrinsics.into_iter().enumerate() {
+					let is_valid = extrinsic.is_valid_at(&block.header.block_number);
+					let support::Extrinsic { caller, call, .. } = extrinsic;
+					#[cfg(feature = "logging")]
+					let call_name = call.name();
+					self.system.inc_nonce(caller.clone());
+					let index = i as u32;
+					// Extrinsics outside of their validity window are skipped, without charging
+					// any fee.
+					let result = if !is_valid {
+						Err("Extrinsic is outside of its validity window")
+					} else {
+						match crate::support::Fees::charge_fee(self, &caller, &call) {
+							Ok(fee) => {
+								let result = self.dispatch(caller.clone(), call);
+								let settled = crate::support::Fees::settle_fee(
+									self,
+									&block.header,
+									&caller,
+									fee,
+									&result,
+								);
+								result.and(settled)
+							},
+							Err(error) => Err(error),
+						}
+					};
+					match result {
+						Ok(()) => crate::support::DepositEvent::deposit_event(
+							&mut self.system,
+							system::Event::ExtrinsicSuccess { index },
+						),
+						Err(error) => {
+							eprintln!(
+								"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
+								block.header.block_number, i, error
+							);
+							crate::support::DepositEvent::deposit_event(
+								&mut self.system,
+								system::Event::ExtrinsicFailed { index, error },
+							);
+						},
+					}
+					#[cfg(feature = "logging")]
+					self.system.log_dispatch(index, &caller, call_name, &result);
+					self.system.record_dispatch(system::DispatchRecord {
+						block: block.header.block_number,
+						extrinsic_index: index,
+						caller,
+						result,
 					});
 				}
-				Ok(())
+				crate::support::Hooks::on_finalize(self, &block.header);
+				self.system.set_block_hash(block.header.block_number, block_hash);
+				self.system.on_finalize();
+				Ok(applied)
+			}
+
+			// Get the names of all the calls which can be dispatched, as `pallet.call`, ordered
+			// by pallet and then by call index.
+			fn supported_calls() -> Vec<&'static str> {
+				static NAMES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+				let names = NAMES.get_or_init(|| {
+					let mut names: Vec<String> = <system::Call<Self>>::NAMES
+						.iter()
+						.map(|call| format!("system.{}", call))
+						.collect();
+					#(
+						names.extend(
+							<#pallet_names::Call<Self>>::NAMES
+								.iter()
+								.map(|call| format!("{}.{}", stringify!(#pallet_names), call)),
+						);
+					)*
+					names
+				});
+				names.iter().map(String::as_str).collect()
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum.
+	let event_impl = quote! {
+		// These are all the events which can be emitted by the runtime.
+		// Note that it is just an accumulation of the events emitted by each pallet.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq)]
+		pub enum RuntimeEvent {
+			system(system::Event<#runtime_struct>),
+			#( #pallet_names(#pallet_names::Event<#runtime_struct>) ),*
+		}
+
+		impl From<system::Event<#runtime_struct>> for RuntimeEvent {
+			fn from(event: system::Event<#runtime_struct>) -> Self {
+				RuntimeEvent::system(event)
+			}
+		}
+
+		#(
+			impl From<#pallet_names::Event<#runtime_struct>> for RuntimeEvent {
+				fn from(event: #pallet_names::Event<#runtime_struct>) -> Self {
+					RuntimeEvent::#pallet_names(event)
+				}
+			}
+		)*
+
+		// The runtime owns the collector shared by all the pallets, so that the events of any
+		// pallet can be deposited in it. The collected events are kept in the system pallet, for
+		// the current block.
+		impl<E: Into<RuntimeEvent>> crate::support::DepositEvent<E> for #runtime_struct {
+			fn deposit_event(&mut self, event: E) {
+				crate::support::DepositEvent::deposit_event(&mut self.system, event.into());
 			}
 		}
 	};
@@ -51,14 +276,71 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq)]
 		pub enum RuntimeCall {
+			system(system::Call<#runtime_struct>),
 			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
 		}
 
+		impl RuntimeCall {
+			// The names of the pallet and of the call, which `supported_calls` lists as
+			// `pallet.call`.
+			pub fn name(&self) -> (&'static str, &'static str) {
+				match self {
+					RuntimeCall::system(call) => ("system", call.name()),
+					#(
+						RuntimeCall::#pallet_names(call) =>
+							(stringify!(#pallet_names), call.name()),
+					)*
+				}
+			}
+		}
+
+		impl crate::support::GetWeight for RuntimeCall {
+			fn weight(&self) -> crate::support::Weight {
+				match self {
+					RuntimeCall::system(call) => call.weight(),
+					#(
+						RuntimeCall::#pallet_names(call) => call.weight(),
+					)*
+				}
+			}
+		}
+
+		// A runtime call is encoded as the index of its pallet, followed by the pallet call.
+		impl crate::support::Encode for RuntimeCall {
+			fn encode(&self) -> Vec<u8> {
+				let (index, call) = match self {
+					RuntimeCall::system(call) => (0u8, crate::support::Encode::encode(call)),
+					#(
+						RuntimeCall::#pallet_names(call) =>
+							(#pallet_index, crate::support::Encode::encode(call)),
+					)*
+				};
+				let mut bytes = vec![index];
+				bytes.extend(call);
+				bytes
+			}
+		}
+
+		impl crate::support::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, String> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					0 => Ok(RuntimeCall::system(crate::support::Decode::decode(input)?)),
+					#(
+						#pallet_index =>
+							Ok(RuntimeCall::#pallet_names(crate::support::Decode::decode(input)?)),
+					)*
+					index => Err(format!("Invalid pallet index {}", index)),
+				}
+			}
+		}
+
 		impl crate::support::Dispatch for #runtime_struct {
 			type Caller = <Runtime as system::Config>::AccountId;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of a caller, unless they are blacklisted in the system
+			// pallet, and if it passes the `support::CallFilter` of the runtime.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
 			// Note that we extract the `caller` from the extrinsic, and use that information
@@ -69,21 +351,35 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				runtime_call: Self::Call,
 			) -> crate::support::DispatchResult {
 				// This match statement will allow us to correctly route `RuntimeCall`s
-				// to the appropriate pallet level call.
+				// to the appropriate pallet level call. The events deposited by the pallet are
+				// then collected by the runtime, but only if the call was successful. The system
+				// pallet deposits its events directly. Successful calls are then passed to the
+				// `on_dispatched` hook of the runtime.
+				self.system.ensure_not_blacklisted(&caller)?;
+				crate::support::CallFilter::filter_call(self, &caller, &runtime_call)?;
 				match runtime_call {
+					RuntimeCall::system(call) => {
+						self.system.dispatch(caller, call)?;
+					}
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							let result = self.#pallet_names.dispatch(caller, call);
+							let events = self.#pallet_names.take_events();
+							result?;
+							for event in events {
+								crate::support::DepositEvent::deposit_event(self, event);
+							}
 						}
 					),*
 				}
-				Ok(())
+				<Self as crate::support::Hooks<types::Header>>::on_dispatched(self)
 			}
 		}
 	};
 
 	// We combine and return all the generated code.
 	quote! {
+		#event_impl
 		#dispatch_impl
 		#runtime_impl
 	}
@@ -1,76 +1,865 @@
-use num::{CheckedAdd, CheckedSub, Zero};
-use std::collections::BTreeMap;
+use num::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Saturating, Zero};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt::Display,
+	ops::Sub,
+};
+
+use crate::support::{DepositEvent, Encode, EventBuffer};
 
 pub trait Config: crate::system::Config {
-	type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+	type Balance: Zero
+		+ CheckedSub
+		+ CheckedAdd
+		+ CheckedMul
+		+ CheckedDiv
+		+ Saturating
+		+ Copy
+		+ PartialOrd;
+	/// Custom logic to run after every successful transfer, `()` to do nothing.
+	type OnTransfer: OnTransfer<Self::AccountId, Self::Balance>;
+	/// Custom logic to run after an account is reaped, `()` to do nothing. For example, to clean
+	/// up what other pallets store for that account.
+	type OnReap: OnReap<Self::AccountId>;
+	/// The number of decimals of the token, used when displaying balances to humans.
+	const DECIMALS: u32;
+	/// The maximum free balance any single account is allowed to hold.
+	const MAX_BALANCE: Self::Balance;
+	/// The maximum amount a single transfer can move, whatever the balance of the sender. By
+	/// default, this is the maximum balance.
+	const MAX_TRANSFER_AMOUNT: Self::Balance = Self::MAX_BALANCE;
+	/// The free balance an account must keep after a transfer or a withdrawal. An account left
+	/// with less, and without any reserved balance, is reaped: it is removed, and its remaining
+	/// free balance is burned. The `OnReap` hook is then called, so that the runtime can clean up
+	/// what other pallets store for the account. Accounts are never reaped if this is `None`, the
+	/// default. This cannot be set along with `DEFAULT_BALANCE`, since reaped accounts would get
+	/// their default balance back.
+	const EXISTENTIAL_DEPOSIT: Option<Self::Balance> = None;
+	/// What to do when crediting an account would overflow its balance.
+	const OVERFLOW_STRATEGY: OverflowStrategy = OverflowStrategy::Checked;
+	/// The free balance of accounts which have never been touched, or `None`, the default, for
+	/// them to have nothing. It only becomes part of the total issuance once the account is
+	/// stored.
+	const DEFAULT_BALANCE: Option<Self::Balance> = None;
+	/// Whether to record the free balance of each account at every block it changed in, see
+	/// `balance_history`. The history is never pruned, so only enable this when needed.
+	const TRACK_BALANCE_HISTORY: bool = false;
+}
+
+/// A hook called by the balances pallet after every successful transfer, for example to keep
+/// another pallet in sync.
+pub trait OnTransfer<AccountId, Balance> {
+	fn on_transfer(from: &AccountId, to: &AccountId, amount: Balance);
+}
+
+impl<AccountId, Balance> OnTransfer<AccountId, Balance> for () {
+	fn on_transfer(_from: &AccountId, _to: &AccountId, _amount: Balance) {}
+}
+
+/// A hook called by the balances pallet after an account is reaped, see `EXISTENTIAL_DEPOSIT`,
+/// or removed as dust, see `Pallet::redistribute_dust`. Unlike `OnTransfer`, the hook is part of
+/// the state of the pallet, so that it can record the accounts for the runtime, which then
+/// cleans up what other pallets store for them.
+pub trait OnReap<AccountId>: Default {
+	fn on_reap(&mut self, who: &AccountId);
+}
+
+impl<AccountId> OnReap<AccountId> for () {
+	fn on_reap(&mut self, _who: &AccountId) {}
+}
+
+/// The strategy used when crediting an account would take it over the maximum balance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowStrategy {
+	/// Reject the operation with an error.
+	Checked,
+	/// Clamp the balance of the account at the maximum balance. Any excess is not minted, or stays
+	/// with the sender of a transfer.
+	Saturating,
+}
+
+/// Statistics about the free balance of an account over its balance history, see
+/// `Pallet::account_stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountStats<Balance> {
+	/// The lowest recorded balance.
+	pub min: Balance,
+	/// The highest recorded balance.
+	pub max: Balance,
+	/// The average of the recorded balances, each block counting once.
+	pub average: f64,
+	/// The number of blocks the balance changed in.
+	pub changes: usize,
+}
+
+/// The events emitted by the Balances Module.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T: Config> {
+	/// `amount` was transferred from `from` to `to`.
+	Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// `who` was reaped, and the free balance they had left, `burned`, was burned.
+	Reaped { who: T::AccountId, burned: T::Balance },
 }
 
 /// This is the Balances Module.
 /// It is a simple module which keeps track of how much balance each account has in this state
 /// machine.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pallet<T: Config> {
 	// A simple storage mapping from accounts (`String`) to their balances (`u128`).
 	balances: BTreeMap<T::AccountId, T::Balance>,
+	/// A storage mapping from accounts to the part of their balance which is reserved, and thus
+	/// cannot be transferred.
+	reserved: BTreeMap<T::AccountId, T::Balance>,
+	/// The part of the reserved balance of each account which is staked, for example by the
+	/// validators. Accounts without a stake are not stored.
+	stakes: BTreeMap<T::AccountId, T::Balance>,
+	/// The total amount of tokens in existence, free and reserved.
+	total_issuance: T::Balance,
+	/// The vesting schedules of each account, as a list of `(until, amount)` entries: `amount` of
+	/// the free balance cannot be transferred before block `until`.
+	#[allow(clippy::type_complexity)]
+	vesting: BTreeMap<T::AccountId, Vec<(T::BlockNumber, T::Balance)>>,
+	/// The free balance of each account after every block it changed in, ordered by block number.
+	/// This is only recorded when `TRACK_BALANCE_HISTORY` is set.
+	#[allow(clippy::type_complexity)]
+	history: BTreeMap<T::AccountId, Vec<(T::BlockNumber, T::Balance)>>,
+	/// The total issuance at the end of each block.
+	issuance_snapshots: BTreeMap<T::BlockNumber, T::Balance>,
+	/// The transaction fees collected in each block.
+	fees_collected: BTreeMap<T::BlockNumber, T::Balance>,
+	/// The current block number, used to know which funds have vested.
+	block_number: T::BlockNumber,
+	/// The hook called with each reaped account.
+	on_reap: T::OnReap,
+	/// The events deposited by this pallet which have not been collected by the runtime yet.
+	events: EventBuffer<Event<T>>,
 }
 
 impl<T: Config> Pallet<T> {
 	/// Create a new instance of the balances module.
 	pub fn new() -> Self {
-		Self { balances: BTreeMap::new() }
+		const {
+			assert!(
+				T::EXISTENTIAL_DEPOSIT.is_none() || T::DEFAULT_BALANCE.is_none(),
+				"`EXISTENTIAL_DEPOSIT` and `DEFAULT_BALANCE` cannot both be set"
+			)
+		};
+		Self {
+			balances: BTreeMap::new(),
+			reserved: BTreeMap::new(),
+			stakes: BTreeMap::new(),
+			total_issuance: T::Balance::zero(),
+			vesting: BTreeMap::new(),
+			history: BTreeMap::new(),
+			fees_collected: BTreeMap::new(),
+			issuance_snapshots: BTreeMap::new(),
+			block_number: T::BlockNumber::zero(),
+			on_reap: T::OnReap::default(),
+			events: EventBuffer::new(),
+		}
+	}
+
+	/// Called by the runtime at the start of every block, so that we know the current block.
+	pub fn on_initialize(&mut self, now: T::BlockNumber) {
+		self.block_number = now;
+	}
+
+	/// Clear all the balances, reserves, stakes, vesting schedules and balance history, and reset
+	/// the total issuance to zero. The current block number is kept.
+	pub fn clear(&mut self) {
+		self.balances.clear();
+		self.reserved.clear();
+		self.stakes.clear();
+		self.vesting.clear();
+		self.history.clear();
+		self.on_reap = T::OnReap::default();
+		self.total_issuance = T::Balance::zero();
+	}
+
+	/// Called by the runtime at the end of every block, to snapshot the total issuance.
+	pub fn on_finalize(&mut self) {
+		self.issuance_snapshots.insert(self.block_number, self.total_issuance);
+	}
+
+	/// Get the total issuance at the end of block `block`, from the latest snapshot up to that
+	/// block. Returns `None` if there is no such snapshot.
+	pub fn issuance_at(&self, block: T::BlockNumber) -> Option<T::Balance> {
+		self.issuance_snapshots
+			.range(..=block)
+			.next_back()
+			.map(|(_, issuance)| *issuance)
+	}
+
+	/// Record that a transaction fee of `amount` was collected in the current block.
+	pub fn note_fee(&mut self, amount: T::Balance) {
+		let collected = self.fees_collected.entry(self.block_number).or_insert(T::Balance::zero());
+		*collected = collected.saturating_add(amount);
+	}
+
+	/// Get the transaction fees collected in each block which collected any.
+	pub fn fees_collected(&self) -> impl Iterator<Item = (&T::BlockNumber, &T::Balance)> {
+		self.fees_collected.iter()
+	}
+
+	/// Take all the events deposited by this pallet since they were last taken.
+	pub fn take_events(&mut self) -> Vec<Event<T>> {
+		self.events.take()
+	}
+
+	/// Get the hook called with each reaped account, for example to take the accounts it
+	/// recorded.
+	pub fn on_reap_mut(&mut self) -> &mut T::OnReap {
+		&mut self.on_reap
 	}
 
 	/// Set the balance of an account `who` to some `amount`.
-	pub fn set_balance(&mut self, who: T::AccountId, amount: T::Balance) {
-		self.balances.insert(who, amount);
+	/// The total issuance is updated to reflect the new balance.
+	/// This fails if `who` is not a valid account id.
+	pub fn set_balance(
+		&mut self,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		T::validate_account(&who)?;
+		let old = self.store_balance(&who, amount).unwrap_or(T::Balance::zero());
+		self.total_issuance = self.total_issuance.saturating_sub(old).saturating_add(amount);
+		Ok(())
+	}
+
+	/// Set the balances of many accounts at once, like calling `set_balance` for each of the
+	/// `entries`, but updating the total issuance in a single pass.
+	/// This is all or nothing: it fails without changing anything if an account id is invalid,
+	/// or if the same account appears more than once.
+	pub fn set_balances(
+		&mut self,
+		entries: Vec<(T::AccountId, T::Balance)>,
+	) -> crate::support::DispatchResult {
+		let mut seen = BTreeSet::new();
+		for (who, _) in &entries {
+			T::validate_account(who)?;
+			if !seen.insert(who) {
+				return Err("Duplicate account");
+			}
+		}
+		let (mut removed, mut added) = (T::Balance::zero(), T::Balance::zero());
+		for (who, amount) in entries {
+			let old = self.store_balance(&who, amount).unwrap_or(T::Balance::zero());
+			removed = removed.saturating_add(old);
+			added = added.saturating_add(amount);
+		}
+		self.total_issuance = self.total_issuance.saturating_sub(removed).saturating_add(added);
+		Ok(())
+	}
+
+	/// Store the free balance of `who`, recording it in the balance history if enabled.
+	/// Returns the previously stored balance, if any.
+	fn store_balance(&mut self, who: &T::AccountId, amount: T::Balance) -> Option<T::Balance> {
+		if T::TRACK_BALANCE_HISTORY {
+			let history = self.history.entry(who.clone()).or_default();
+			// Only keep the balance at the end of each block.
+			if history.last().is_some_and(|(block, _)| *block == self.block_number) {
+				history.pop();
+			}
+			history.push((self.block_number, amount));
+		}
+		self.balances.insert(who.clone(), amount)
+	}
+
+	/// Get the free balance of `who` after every block it changed in, ordered by block number.
+	/// This is always empty if `TRACK_BALANCE_HISTORY` is not set.
+	pub fn balance_history(&self, who: &T::AccountId) -> &[(T::BlockNumber, T::Balance)] {
+		self.history.get(who).map_or(&[], |history| history.as_slice())
+	}
+
+	/// Get statistics about the free balance of `who` at the end of every block it changed in.
+	/// Returns `None` if no balance was recorded, which is always the case if
+	/// `TRACK_BALANCE_HISTORY` is not set.
+	pub fn account_stats(&self, who: &T::AccountId) -> Option<AccountStats<T::Balance>>
+	where
+		T::Balance: Into<u128>,
+	{
+		let history = self.balance_history(who);
+		let (_, first) = *history.first()?;
+		let (mut min, mut max, mut total) = (first, first, 0u128);
+		for (_, balance) in history {
+			if *balance < min {
+				min = *balance;
+			}
+			if *balance > max {
+				max = *balance;
+			}
+			total = total.saturating_add((*balance).into());
+		}
+		let average = total as f64 / history.len() as f64;
+		Some(AccountStats { min, max, average, changes: history.len() })
+	}
+
+	/// Store `who`, with the default balance, if they do not have a stored balance yet. The default
+	/// balance is minted when an account is stored, so the total issuance stays accurate.
+	/// This fails if `who` is not stored yet and is not a valid account id.
+	fn touch(&mut self, who: &T::AccountId) -> crate::support::DispatchResult {
+		if !self.balances.contains_key(who) {
+			T::validate_account(who)?;
+			let default = Self::default_balance();
+			self.store_balance(who, default);
+			self.total_issuance = self.total_issuance.saturating_add(default);
+		}
+		Ok(())
+	}
+
+	/// Reap `who` if their free balance is below `EXISTENTIAL_DEPOSIT` and they have nothing
+	/// reserved, burning what is left of their free balance. The `OnReap` hook is called, and a
+	/// `Reaped` event is deposited.
+	fn reap_if_dead(&mut self, who: &T::AccountId) {
+		let balance = self.balance(who);
+		let Some(existential_deposit) = T::EXISTENTIAL_DEPOSIT else {
+			return;
+		};
+		if balance >= existential_deposit || !self.reserved_balance(who).is_zero() {
+			return;
+		}
+		// Record that the account was emptied before removing it.
+		self.store_balance(who, T::Balance::zero());
+		self.balances.remove(who);
+		self.total_issuance = self.total_issuance.saturating_sub(balance);
+		self.on_reap.on_reap(who);
+		self.events.deposit_event(Event::Reaped { who: who.clone(), burned: balance });
+	}
+
+	/// Compute the new balance of an account with `balance` after being credited `amount`,
+	/// following the configured `OverflowStrategy`.
+	fn credit(balance: T::Balance, amount: T::Balance) -> Result<T::Balance, &'static str> {
+		match T::OVERFLOW_STRATEGY {
+			OverflowStrategy::Checked => {
+				let new_balance = balance.checked_add(&amount).ok_or("Overflow")?;
+				if new_balance > T::MAX_BALANCE {
+					return Err("Exceeds max balance");
+				}
+				Ok(new_balance)
+			},
+			OverflowStrategy::Saturating => {
+				let new_balance = balance.saturating_add(amount);
+				Ok(if new_balance > T::MAX_BALANCE { T::MAX_BALANCE } else { new_balance })
+			},
+		}
+	}
+
+	/// Create `amount` new tokens in the free balance of `who`, increasing the total issuance.
+	/// If the new balance would exceed the maximum balance, this follows the configured
+	/// `OverflowStrategy`: either return an error, or only mint up to the maximum balance.
+	/// This fails if `who` is a new account with an invalid account id.
+	pub fn mint(
+		&mut self,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		// Store the account first, so that its default balance is already part of the total
+		// issuance.
+		self.touch(who)?;
+		let balance = self.balance(who);
+		let new_balance = Self::credit(balance, amount)?;
+		let minted = new_balance - balance;
+		let new_total_issuance = self.total_issuance.checked_add(&minted).ok_or("Overflow")?;
+
+		self.store_balance(who, new_balance);
+		self.total_issuance = new_total_issuance;
+		Ok(())
+	}
+
+	/// Remove `amount` from the spendable balance of `who`, decreasing the total issuance.
+	/// This reaps `who` if they are left with less than `EXISTENTIAL_DEPOSIT`.
+	pub fn withdraw(
+		&mut self,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		if amount > self.spendable_balance(who, self.block_number) {
+			return Err("Not enough funds!");
+		}
+		self.touch(who)?;
+		self.store_balance(who, self.balance(who) - amount);
+		self.total_issuance = self.total_issuance - amount;
+		self.reap_if_dead(who);
+		Ok(())
+	}
+
+	/// Get the total amount of tokens in existence.
+	pub fn total_issuance(&self) -> T::Balance {
+		self.total_issuance
+	}
+
+	/// Get the reserved balance of an account `who`.
+	/// If the account has no reserved balance, we return zero.
+	pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		*self.reserved.get(who).unwrap_or(&T::Balance::zero())
+	}
+
+	/// Get the total reserved balance across all accounts.
+	pub fn reserved_total(&self) -> T::Balance {
+		self.reserved
+			.values()
+			.fold(T::Balance::zero(), |total, reserved| total.saturating_add(*reserved))
+	}
+
+	/// Get every account with a reserved balance which is not zero, along with that balance,
+	/// ordered by account. This is meant for auditing, for example to find reserves which are
+	/// not backed by anything.
+	pub fn find_reserves(&self) -> Vec<(&T::AccountId, T::Balance)> {
+		self.reserved
+			.iter()
+			.filter(|(_, reserved)| !reserved.is_zero())
+			.map(|(who, reserved)| (who, *reserved))
+			.collect()
+	}
+
+	/// Move `amount` from the free balance of `who` to their reserved balance.
+	pub fn reserve(
+		&mut self,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		let new_free = self.balance(who).checked_sub(&amount).ok_or("Not enough funds!")?;
+		let new_reserved = self.reserved_balance(who).checked_add(&amount).ok_or("Overflow")?;
+
+		self.touch(who)?;
+		self.store_balance(who, new_free);
+		self.reserved.insert(who.clone(), new_reserved);
+		Ok(())
+	}
+
+	/// Get the stake of `who`, which is part of their reserved balance.
+	/// If the account has no stake, we return zero.
+	pub fn stake_of(&self, who: &T::AccountId) -> T::Balance {
+		*self.stakes.get(who).unwrap_or(&T::Balance::zero())
+	}
+
+	/// Reserve `amount` from the free balance of `who`, and add it to their stake. Staked funds
+	/// are never unreserved.
+	pub fn stake(
+		&mut self,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		self.reserve(who, amount)?;
+		// The stake is part of the reserved balance, which did not overflow.
+		let stake = self.stake_of(who).saturating_add(amount);
+		self.stakes.insert(who.clone(), stake);
+		Ok(())
+	}
+
+	/// Move up to `amount` from the reserved balance of `who` back to their free balance, except
+	/// their stake, which stays reserved. Returns the amount which was actually unreserved.
+	pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let reserved = self.reserved_balance(who);
+		let unstaked = reserved.saturating_sub(self.stake_of(who));
+		let actual = if amount < unstaked { amount } else { unstaked };
+		// Accounts with a reserve are never reaped, so they are always stored already.
+		if actual.is_zero() {
+			return actual;
+		}
+
+		self.reserved.insert(who.clone(), reserved - actual);
+		self.store_balance(who, self.balance(who).saturating_add(actual));
+		actual
+	}
+
+	/// Slash up to `amount` from the reserved balance of `who`, removing it from the total
+	/// issuance. Returns the amount which was actually slashed, which may be less than `amount` if
+	/// the account does not have enough reserved.
+	/// The part of the reserved balance which is not staked is slashed first, and the stake of
+	/// `who` is only lowered by what is left to slash, so that it never exceeds their reserve.
+	pub fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let reserved = self.reserved_balance(who);
+		let actual = if amount < reserved { amount } else { reserved };
+		let stake = self.stake_of(who);
+		let unstaked = reserved - stake;
+		if actual > unstaked {
+			let new_stake = stake - (actual - unstaked);
+			if new_stake.is_zero() {
+				self.stakes.remove(who);
+			} else {
+				self.stakes.insert(who.clone(), new_stake);
+			}
+		}
+
+		self.reserved.insert(who.clone(), reserved - actual);
+		self.total_issuance = self.total_issuance.saturating_sub(actual);
+		actual
+	}
+
+	/// Exchange the free balances of `a` and `b`. The total issuance is unchanged, apart from
+	/// the default balance of accounts which were not stored yet. Swapping an account with itself
+	/// does nothing. This fails if `a` or `b` is a new account with an invalid account id.
+	pub fn swap(&mut self, a: &T::AccountId, b: &T::AccountId) -> crate::support::DispatchResult {
+		if a == b {
+			return Ok(());
+		}
+		T::validate_account(a)?;
+		T::validate_account(b)?;
+		self.touch(a)?;
+		self.touch(b)?;
+		let a_balance = self.balance(a);
+		let b_balance = self.balance(b);
+		self.store_balance(a, b_balance);
+		self.store_balance(b, a_balance);
+		Ok(())
+	}
+
+	/// Remove all the stored accounts whose free balance is below `threshold`, and share their
+	/// balance between the remaining accounts, proportionally to their free balance. What is left
+	/// after rounding down each share goes to the largest holder. Accounts with a reserved or
+	/// vesting balance are never removed, whatever their free balance. The `OnReap` hook is called
+	/// with each removed account. Returns the amount of dust which was redistributed.
+	///
+	/// Crediting the holders follows the configured `OverflowStrategy`. If it fails, or if there is
+	/// no holder left to receive the dust, nothing changes and this returns zero.
+	pub fn redistribute_dust(&mut self, threshold: T::Balance) -> T::Balance {
+		let (dust, holders): (Vec<_>, Vec<_>) = self.balances.iter().partition(|(who, balance)| {
+			**balance < threshold
+				&& self.reserved_balance(who).is_zero()
+				&& self.vesting_balance(who, self.block_number).is_zero()
+		});
+		let dust_total =
+			dust.iter().fold(T::Balance::zero(), |acc, (_, b)| acc.saturating_add(**b));
+		let holders_total =
+			holders.iter().fold(T::Balance::zero(), |acc, (_, b)| acc.saturating_add(**b));
+		if dust_total.is_zero() || holders_total.is_zero() {
+			return T::Balance::zero();
+		}
+
+		// Find the share of each holder, and whatever is left because of rounding.
+		let mut shares = Vec::new();
+		let mut remainder = dust_total;
+		let mut largest = 0;
+		for (i, (who, balance)) in holders.iter().enumerate() {
+			let Some(share) =
+				balance.checked_mul(&dust_total).and_then(|x| x.checked_div(&holders_total))
+			else {
+				return T::Balance::zero();
+			};
+			remainder = remainder - share;
+			if **balance > *holders[largest].1 {
+				largest = i;
+			}
+			shares.push(((*who).clone(), **balance, share));
+		}
+		shares[largest].2 = shares[largest].2 + remainder;
+
+		let mut new_balances = Vec::new();
+		let mut burned = T::Balance::zero();
+		for (who, balance, share) in shares {
+			let Ok(new_balance) = Self::credit(balance, share) else {
+				return T::Balance::zero();
+			};
+			burned = burned + (share - (new_balance - balance));
+			new_balances.push((who, new_balance));
+		}
+
+		let dust_accounts: Vec<_> = dust.into_iter().map(|(who, _)| who.clone()).collect();
+		for who in dust_accounts {
+			// Record that the dust account was emptied before removing it.
+			self.store_balance(&who, T::Balance::zero());
+			self.balances.remove(&who);
+			self.on_reap.on_reap(&who);
+		}
+		for (who, new_balance) in new_balances {
+			self.store_balance(&who, new_balance);
+		}
+		self.total_issuance = self.total_issuance - burned;
+		dust_total
+	}
+
+	/// Lock `amount` of the free balance of `who` until block `until`.
+	pub fn vest(&mut self, who: &T::AccountId, amount: T::Balance, until: T::BlockNumber) {
+		self.vesting.entry(who.clone()).or_default().push((until, amount));
+	}
+
+	/// Get the part of the free balance of `who` which is still vesting at block `now`.
+	pub fn vesting_balance(&self, who: &T::AccountId, now: T::BlockNumber) -> T::Balance {
+		self.vesting
+			.get(who)
+			.into_iter()
+			.flatten()
+			.filter(|(until, _)| *until > now)
+			.fold(T::Balance::zero(), |acc, (_, amount)| acc.saturating_add(*amount))
+	}
+
+	/// Get the number of blocks from `now` until all the vesting schedules of `who` complete, which
+	/// is zero at or after the end of the last one. Returns `None` if `who` never vested anything.
+	pub fn vesting_remaining(
+		&self,
+		who: &T::AccountId,
+		now: T::BlockNumber,
+	) -> Option<T::BlockNumber>
+	where
+		T::BlockNumber: Sub<Output = T::BlockNumber>,
+	{
+		let end = self.vesting.get(who)?.iter().map(|(until, _)| *until).max()?;
+		Some(if end > now { end - now } else { T::BlockNumber::zero() })
+	}
+
+	/// Get how much `who` can actually transfer at block `now`: their free balance, minus what is
+	/// still vesting. Reserved funds, including stakes, are never part of the free balance, so
+	/// they are already excluded. This pallet has no locks other than vesting schedules, and the
+	/// `EXISTENTIAL_DEPOSIT` is not withheld: spending below it is allowed, and reaps the account.
+	pub fn spendable_balance(&self, who: &T::AccountId, now: T::BlockNumber) -> T::Balance {
+		self.balance(who).saturating_sub(self.vesting_balance(who, now))
 	}
 
 	/// Get the balance of an account `who`.
-	/// If the account has no stored balance, we return zero.
+	/// If the account has no stored balance, we return the default balance.
 	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-		*self.balances.get(who).unwrap_or(&T::Balance::zero())
+		self.balances.get(who).copied().unwrap_or_else(Self::default_balance)
+	}
+
+	/// The balance of accounts which were never stored, see `DEFAULT_BALANCE`.
+	fn default_balance() -> T::Balance {
+		T::DEFAULT_BALANCE.unwrap_or(T::Balance::zero())
+	}
+
+	/// Get the number of accounts with a stored balance, including explicit zero balances.
+	pub fn account_count(&self) -> usize {
+		self.balances.len()
+	}
+
+	/// Get the number of accounts with a stored balance which is not zero.
+	pub fn nonzero_account_count(&self) -> usize {
+		self.balances.values().filter(|balance| !balance.is_zero()).count()
+	}
+
+	/// Iterate over all the accounts with a stored balance, ordered by account.
+	pub fn iter_balances(&self) -> impl Iterator<Item = (&T::AccountId, &T::Balance)> {
+		self.balances.iter()
+	}
+
+	/// The same as `transfer`, but returns the new free balances of `caller` and `to`.
+	pub fn transfer_with_result(
+		&mut self,
+		caller: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> Result<(T::Balance, T::Balance), &'static str> {
+		self.transfer(caller.clone(), to.clone(), amount)?;
+		Ok((self.balance(&caller), self.balance(&to)))
+	}
+}
+
+/// Functions which need the accounts, block numbers and balances to be encoded.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::BlockNumber: Encode,
+	T::Balance: Encode,
+{
+	/// Get the hash of the vesting schedules of every account, so that they can be covered by the
+	/// state root.
+	pub fn vesting_hash(&self) -> [u8; 32] {
+		crate::system::Pallet::<T>::hash(&self.vesting.encode())
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Display,
+	T::Balance: Display,
+{
+	/// Export the free balance of every stored account as CSV, with an `account,balance` header
+	/// and one row per account, ordered by account.
+	pub fn to_csv(&self) -> String {
+		let mut csv = String::from("account,balance\n");
+		for (who, balance) in &self.balances {
+			csv.push_str(&format!("{},{}\n", csv_field(who), balance));
+		}
+		csv
+	}
+
+	/// Like `to_csv`, with an additional `reserved` column for the reserved balance.
+	pub fn to_csv_with_reserved(&self) -> String {
+		let mut csv = String::from("account,balance,reserved\n");
+		for (who, balance) in &self.balances {
+			let reserved = self.reserved_balance(who);
+			csv.push_str(&format!("{},{},{}\n", csv_field(who), balance, reserved));
+		}
+		csv
+	}
+}
+
+/// Format `value` as a CSV field, quoting it if it contains a separator, a quote or a new line.
+fn csv_field(value: impl Display) -> String {
+	let value = value.to_string();
+	if value.contains([',', '"', '\n']) {
+		format!("\"{}\"", value.replace('"', "\"\""))
+	} else {
+		value
 	}
 }
 
 #[macros::call]
 impl<T: Config> Pallet<T> {
 	/// Transfer `amount` from one account to another.
-	/// This function verifies that `from` has at least `amount` balance to transfer,
-	/// and that no mathematical overflows occur.
+	/// This function verifies that `from` has at least `amount` spendable balance to transfer,
+	/// and follows the configured `OverflowStrategy` if `to` would exceed the maximum balance.
+	/// With `OverflowStrategy::Saturating`, only the amount which fits in `to` is transferred, and
+	/// the rest stays with the caller.
+	/// Transfers of more than `MAX_TRANSFER_AMOUNT` are always rejected, and the caller is reaped
+	/// if they are left with less than `EXISTENTIAL_DEPOSIT`.
 	pub fn transfer(
 		&mut self,
 		caller: T::AccountId,
 		to: T::AccountId,
 		amount: T::Balance,
 	) -> crate::support::DispatchResult {
+		T::validate_account(&to)?;
+		if amount > T::MAX_TRANSFER_AMOUNT {
+			return Err("Transfer exceeds maximum");
+		}
 		let from_balance = self.balance(&caller);
 		let to_balance = self.balance(&to);
 
-		let new_from_balance = from_balance.checked_sub(&amount).ok_or("Not enough funds!")?;
-		let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow")?;
+		if amount > self.spendable_balance(&caller, self.block_number) {
+			return Err("Not enough funds!");
+		}
+		// Transferring to yourself does not change any balance, but still requires enough funds.
+		if caller == to {
+			return Ok(());
+		}
+		let new_to_balance = Self::credit(to_balance, amount)?;
+		let amount = new_to_balance - to_balance;
+		let new_from_balance = from_balance - amount;
 
-		self.balances.insert(caller, new_from_balance);
-		self.balances.insert(to, new_to_balance);
+		self.touch(&caller)?;
+		self.touch(&to)?;
+		self.store_balance(&caller, new_from_balance);
+		self.store_balance(&to, new_to_balance);
+		T::OnTransfer::on_transfer(&caller, &to, amount);
+		self.events.deposit_event(Event::Transfer { from: caller.clone(), to, amount });
+		self.reap_if_dead(&caller);
 
 		Ok(())
 	}
 }
 
+impl<T: Config> crate::support::GetWeight for Call<T> {
+	fn weight(&self) -> crate::support::Weight {
+		match self {
+			Call::transfer { .. } => 10,
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
+	use std::cell::RefCell;
+
+	use crate::support::Balance;
 
-	struct TestConfig;
+	thread_local! {
+		/// The transfers seen by `RecordTransfers` on the current thread.
+		static TRANSFERS: RefCell<Vec<(String, String, u32)>> = const { RefCell::new(Vec::new()) };
+	}
+
+	/// A transfer hook which records every transfer in `TRANSFERS`.
+	struct RecordTransfers;
 
-	impl super::Config for TestConfig {
-		type Balance = u32;
+	impl super::OnTransfer<String, u32> for RecordTransfers {
+		fn on_transfer(from: &String, to: &String, amount: u32) {
+			TRANSFERS
+				.with(|transfers| transfers.borrow_mut().push((from.clone(), to.clone(), amount)));
+		}
 	}
 
-	impl crate::system::Config for TestConfig {
-		type AccountId = String;
+	/// Declare a test config called `$name`, with the system config shared by every test config
+	/// and the given balances config items. Without a balance type and hooks, they default to
+	/// `u32` and no hooks, with a `MAX_BALANCE` of 200.
+	macro_rules! test_config {
+		($(#[$attr:meta])* $name:ident { $($item:item)* }) => {
+			test_config! {
+				$(#[$attr])*
+				$name<u32, (), ()> {
+					const MAX_BALANCE: u32 = 200;
+					$($item)*
+				}
+			}
+		};
+		(
+			$(#[$attr:meta])*
+			$name:ident<$balance:ty, $on_transfer:ty, $on_reap:ty> { $($item:item)* }
+		) => {
+			$(#[$attr])*
+			struct $name;
 
-		type BlockNumber = u32;
+			impl super::Config for $name {
+				type Balance = $balance;
+				type OnTransfer = $on_transfer;
+				type OnReap = $on_reap;
+				const DECIMALS: u32 = 2;
+				$($item)*
+			}
 
-		type Nonce = u32;
+			impl crate::system::Config for $name {
+				type AccountId = String;
+				type BlockNumber = u32;
+				type Nonce = u32;
+				type Hashing = crate::support::Sha256;
+				type RuntimeEvent = crate::system::Event<Self>;
+				const BLOCK_HASH_COUNT: u32 = 256;
+				const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+			}
+		};
+	}
+
+	test_config! {
+		TestConfig<u32, RecordTransfers, ()> {
+			const MAX_BALANCE: u32 = 200;
+			const MAX_TRANSFER_AMOUNT: u32 = 100;
+			const TRACK_BALANCE_HISTORY: bool = true;
+		}
+	}
+
+	test_config! {
+		/// The same as `TestConfig`, but using saturating arithmetic.
+		SaturatingConfig {
+			const OVERFLOW_STRATEGY: super::OverflowStrategy = super::OverflowStrategy::Saturating;
+		}
+	}
+
+	test_config! {
+		/// The same as `TestConfig`, but untouched accounts start with a default balance.
+		FaucetConfig {
+			const DEFAULT_BALANCE: Option<u32> = Some(10);
+		}
+	}
+
+	test_config! {
+		/// The same as `TestConfig`, but using the `Balance` wrapper rather than a bare integer.
+		WrappedConfig<Balance, (), ()> {
+			const MAX_BALANCE: Balance = Balance(200);
+			const MAX_TRANSFER_AMOUNT: Balance = Balance(100);
+		}
+	}
+
+	/// A reap hook which records the reaped accounts, in order.
+	#[derive(Default)]
+	struct RecordReaped(Vec<String>);
+
+	impl super::OnReap<String> for RecordReaped {
+		fn on_reap(&mut self, who: &String) {
+			self.0.push(who.clone());
+		}
+	}
+
+	/// Take the accounts which `RecordReaped` recorded so far.
+	fn take_reaped(balances: &mut super::Pallet<ReapConfig>) -> Vec<String> {
+		core::mem::take(&mut balances.on_reap_mut().0)
+	}
+
+	test_config! {
+		/// The same as `TestConfig`, but accounts are reaped below an existential deposit.
+		ReapConfig<u32, (), RecordReaped> {
+			const MAX_BALANCE: u32 = 200;
+			const EXISTENTIAL_DEPOSIT: Option<u32> = Some(10);
+		}
 	}
 
 	#[test]
@@ -78,7 +867,7 @@ mod tests {
 		let mut balances = super::Pallet::<TestConfig>::new();
 
 		assert_eq!(balances.balance(&"Alice".to_string()), 0);
-		balances.set_balance("Alice".to_string(), 100);
+		balances.set_balance("Alice".to_string(), 100).unwrap();
 		assert_eq!(balances.balance(&"Alice".to_string()), 100);
 		assert_eq!(balances.balance(&"Bob".to_string()), 0);
 	}
@@ -86,7 +875,7 @@ mod tests {
 	#[test]
 	fn transfer_balance() {
 		let mut balances = super::Pallet::<TestConfig>::new();
-		balances.set_balance("Alice".to_string(), 100);
+		balances.set_balance("Alice".to_string(), 100).unwrap();
 
 		balances.transfer("Alice".to_string(), "Bob".to_string(), 50).unwrap();
 		assert_eq!(balances.balance(&"Alice".to_string()), 50);
@@ -96,5 +885,513 @@ mod tests {
 		assert_eq!(result, Err("Not enough funds!"));
 		assert_eq!(balances.balance(&"Alice".to_string()), 50);
 		assert_eq!(balances.balance(&"Bob".to_string()), 50);
+
+		balances.transfer("Alice".to_string(), "Alice".to_string(), 50).unwrap();
+		assert_eq!(balances.balance(&"Alice".to_string()), 50);
+		let result = balances.transfer("Alice".to_string(), "Alice".to_string(), 60);
+		assert_eq!(result, Err("Not enough funds!"));
+	}
+
+	#[test]
+	fn on_transfer_hook() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances.set_balance("Alice".to_string(), 100).unwrap();
+
+		balances.transfer("Alice".to_string(), "Bob".to_string(), 30).unwrap();
+		// Failed transfers and transfers to yourself do not call the hook.
+		balances.transfer("Alice".to_string(), "Bob".to_string(), 80).unwrap_err();
+		balances.transfer("Alice".to_string(), "Alice".to_string(), 10).unwrap();
+
+		let transfers = TRANSFERS.with(|transfers| transfers.take());
+		assert_eq!(transfers, vec![("Alice".to_string(), "Bob".to_string(), 30)]);
+	}
+
+	#[test]
+	fn set_balances() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let accounts = ["Alice", "Bob", "Charlie", "Dave"].map(String::from);
+		balances.set_balance(accounts[0].clone(), 100).unwrap();
+
+		let entries = accounts.iter().cloned().zip([10, 20, 30, 40]).collect();
+		assert_eq!(balances.set_balances(entries), Ok(()));
+		for (who, amount) in accounts.iter().zip([10, 20, 30, 40]) {
+			assert_eq!(balances.balance(who), amount);
+		}
+		// The previous balance of Alice was replaced.
+		assert_eq!(balances.total_issuance(), 100);
+
+		let entries = vec![(accounts[0].clone(), 50), (accounts[0].clone(), 60)];
+		assert_eq!(balances.set_balances(entries), Err("Duplicate account"));
+		assert_eq!(balances.balance(&accounts[0]), 10);
+		assert_eq!(balances.total_issuance(), 100);
+	}
+
+	#[test]
+	fn balance_history() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		balances.set_balance(alice.clone(), 100).unwrap();
+
+		balances.on_initialize(1);
+		balances.transfer(alice.clone(), bob.clone(), 30).unwrap();
+		balances.on_initialize(2);
+		// Only the balance at the end of the block is recorded.
+		balances.transfer(alice.clone(), bob.clone(), 10).unwrap();
+		balances.transfer(bob.clone(), alice.clone(), 5).unwrap();
+		balances.on_initialize(3);
+		balances.transfer(bob.clone(), alice.clone(), 100).unwrap_err();
+
+		assert_eq!(balances.balance_history(&alice), &[(0, 100), (1, 70), (2, 65)]);
+		assert_eq!(balances.balance_history(&bob), &[(1, 30), (2, 35)]);
+		assert_eq!(balances.balance_history(&"Charlie".to_string()), &[]);
+
+		let mut untracked = super::Pallet::<SaturatingConfig>::new();
+		untracked.set_balance(alice.clone(), 100).unwrap();
+		assert_eq!(untracked.balance_history(&alice), &[]);
+	}
+
+	#[test]
+	fn account_stats() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		assert_eq!(balances.account_stats(&alice), None);
+
+		balances.on_initialize(1);
+		balances.set_balance(alice.clone(), 100).unwrap();
+		balances.on_initialize(2);
+		balances.transfer(alice.clone(), bob.clone(), 50).unwrap();
+		balances.on_initialize(3);
+		balances.mint(&alice, 20).unwrap();
+		balances.mint(&alice, 80).unwrap();
+
+		let stats = super::AccountStats { min: 50, max: 150, average: 100.0, changes: 3 };
+		assert_eq!(balances.account_stats(&alice), Some(stats));
+		let stats = super::AccountStats { min: 50, max: 50, average: 50.0, changes: 1 };
+		assert_eq!(balances.account_stats(&bob), Some(stats));
+	}
+
+	#[test]
+	fn to_csv() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances
+			.set_balances(vec![
+				("Charlie".to_string(), 30),
+				("Alice".to_string(), 100),
+				("Bob, Jr.".to_string(), 5),
+			])
+			.unwrap();
+		balances.reserve(&"Alice".to_string(), 40).unwrap();
+
+		assert_eq!(balances.to_csv(), "account,balance\nAlice,60\n\"Bob, Jr.\",5\nCharlie,30\n");
+		assert_eq!(
+			balances.to_csv_with_reserved(),
+			"account,balance,reserved\nAlice,60,40\n\"Bob, Jr.\",5,0\nCharlie,30,0\n"
+		);
+		assert_eq!(super::Pallet::<TestConfig>::new().to_csv(), "account,balance\n");
+	}
+
+	#[test]
+	fn reserve_and_unreserve() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		balances.set_balance(alice.clone(), 100).unwrap();
+
+		assert_eq!(balances.reserve(&alice, 150), Err("Not enough funds!"));
+		assert_eq!(balances.reserve(&alice, 30), Ok(()));
+		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.reserved_balance(&alice), 30);
+		assert_eq!(balances.total_issuance(), 100);
+
+		assert_eq!(balances.unreserve(&alice, 50), 30);
+		assert_eq!(balances.balance(&alice), 100);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+
+		// Staked funds are reserved, but cannot be unreserved.
+		assert_eq!(balances.stake(&alice, 150), Err("Not enough funds!"));
+		assert_eq!(balances.stake(&alice, 40), Ok(()));
+		balances.reserve(&alice, 10).unwrap();
+		assert_eq!(balances.stake_of(&alice), 40);
+		assert_eq!(balances.reserved_balance(&alice), 50);
+		assert_eq!(balances.unreserve(&alice, 50), 10);
+		assert_eq!(balances.reserved_balance(&alice), 40);
+		assert_eq!(balances.balance(&alice), 60);
+	}
+
+	#[test]
+	fn slash_reserved() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		balances.set_balance(alice.clone(), 100).unwrap();
+		balances.reserve(&alice, 50).unwrap();
+
+		// Slashing less than the reserved balance.
+		assert_eq!(balances.slash_reserved(&alice, 20), 20);
+		assert_eq!(balances.reserved_balance(&alice), 30);
+		assert_eq!(balances.total_issuance(), 80);
+
+		// Slashing exactly the reserved balance.
+		assert_eq!(balances.slash_reserved(&alice, 30), 30);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(balances.total_issuance(), 50);
+
+		// Slashing more than the reserved balance only slashes what is there.
+		balances.reserve(&alice, 10).unwrap();
+		assert_eq!(balances.slash_reserved(&alice, 25), 10);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(balances.total_issuance(), 40);
+
+		// The free balance is never touched.
+		assert_eq!(balances.balance(&alice), 40);
+
+		// The reserve which is not staked is slashed before the stake.
+		balances.stake(&alice, 20).unwrap();
+		balances.reserve(&alice, 10).unwrap();
+		assert_eq!(balances.slash_reserved(&alice, 15), 15);
+		assert_eq!(balances.reserved_balance(&alice), 15);
+		assert_eq!(balances.stake_of(&alice), 15);
+		assert_eq!(balances.slash_reserved(&alice, 15), 15);
+		assert_eq!(balances.stake_of(&alice), 0);
+	}
+
+	#[test]
+	fn max_balance() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		balances.set_balances(vec![(alice.clone(), 200), (bob.clone(), 100)]).unwrap();
+
+		// Landing exactly at the cap is fine.
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 100), Ok(()));
+		assert_eq!(balances.balance(&bob), 200);
+
+		// Going over the cap is not.
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 1), Err("Exceeds max balance"));
+		assert_eq!(balances.balance(&alice), 100);
+		assert_eq!(balances.balance(&bob), 200);
+
+		assert_eq!(balances.mint(&alice, 100), Ok(()));
+		assert_eq!(balances.mint(&alice, 1), Err("Exceeds max balance"));
+		assert_eq!(balances.balance(&alice), 200);
+		assert_eq!(balances.total_issuance(), 400);
+	}
+
+	#[test]
+	fn overflow_strategies() {
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+
+		// `Checked` errors at the boundary without touching any balance.
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances.set_balances(vec![(alice.clone(), 100), (bob.clone(), 150)]).unwrap();
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 51), Err("Exceeds max balance"));
+		assert_eq!(balances.mint(&bob, 51), Err("Exceeds max balance"));
+		assert_eq!(balances.balance(&alice), 100);
+		assert_eq!(balances.balance(&bob), 150);
+		assert_eq!(balances.total_issuance(), 250);
+
+		// `Saturating` clamps at the maximum balance, and the sender keeps the excess.
+		let mut balances = super::Pallet::<SaturatingConfig>::new();
+		balances.set_balances(vec![(alice.clone(), 100), (bob.clone(), 150)]).unwrap();
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 60), Ok(()));
+		assert_eq!(balances.balance(&alice), 50);
+		assert_eq!(balances.balance(&bob), 200);
+		assert_eq!(balances.total_issuance(), 250);
+
+		// Minting only creates what fits.
+		assert_eq!(balances.mint(&alice, 500), Ok(()));
+		assert_eq!(balances.balance(&alice), 200);
+		assert_eq!(balances.total_issuance(), 400);
+	}
+
+	#[test]
+	fn default_balance() {
+		let mut balances = super::Pallet::<FaucetConfig>::new();
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+
+		// Untouched accounts report the default, but are not part of the issuance yet.
+		assert_eq!(balances.balance(&alice), 10);
+		assert_eq!(balances.total_issuance(), 0);
+
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 11), Err("Not enough funds!"));
+		assert_eq!(balances.total_issuance(), 0);
+
+		// Transferring from an untouched account to another one stores both of them.
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 4), Ok(()));
+		assert_eq!(balances.balance(&alice), 6);
+		assert_eq!(balances.balance(&bob), 14);
+		assert_eq!(balances.total_issuance(), 20);
+		assert_eq!(balances.iter_balances().count(), 2);
+
+		// Setting a balance replaces the default.
+		balances.set_balance("Charlie".to_string(), 5).unwrap();
+		assert_eq!(balances.total_issuance(), 25);
+
+		// Minting to an untouched account mints on top of the default.
+		let dave = "Dave".to_string();
+		assert_eq!(balances.mint(&dave, 5), Ok(()));
+		assert_eq!(balances.balance(&dave), 15);
+		assert_eq!(balances.total_issuance(), 40);
+	}
+
+	#[test]
+	fn swap() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		balances.set_balances(vec![(alice.clone(), 100), (bob.clone(), 30)]).unwrap();
+
+		balances.swap(&alice, &bob).unwrap();
+		assert_eq!(balances.balance(&alice), 30);
+		assert_eq!(balances.balance(&bob), 100);
+		assert_eq!(balances.total_issuance(), 130);
+
+		balances.swap(&alice, &alice).unwrap();
+		assert_eq!(balances.balance(&alice), 30);
+		assert_eq!(balances.total_issuance(), 130);
+	}
+
+	#[test]
+	fn redistribute_dust() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let account = |name: &str| name.to_string();
+		balances
+			.set_balances(vec![
+				(account("Alice"), 60),
+				(account("Bob"), 30),
+				(account("Charlie"), 30),
+				(account("Dave"), 3),
+				(account("Eve"), 4),
+			])
+			.unwrap();
+
+		// No account is below the threshold.
+		assert_eq!(balances.redistribute_dust(2), 0);
+		assert_eq!(balances.total_issuance(), 127);
+
+		// The 7 dust tokens are shared as 60 * 7 / 120 = 3 and 30 * 7 / 120 = 1 twice. The
+		// remaining 2 go to Alice, the largest holder.
+		assert_eq!(balances.redistribute_dust(5), 7);
+		assert_eq!(balances.balance(&account("Alice")), 65);
+		assert_eq!(balances.balance(&account("Bob")), 31);
+		assert_eq!(balances.balance(&account("Charlie")), 31);
+		assert_eq!(balances.balance(&account("Dave")), 0);
+		assert_eq!(balances.balance(&account("Eve")), 0);
+		assert_eq!(balances.iter_balances().count(), 3);
+		assert_eq!(balances.total_issuance(), 127);
+
+		// Accounts with reserved or vesting funds are not dust.
+		balances.set_balance(account("Dave"), 4).unwrap();
+		balances.reserve(&account("Dave"), 1).unwrap();
+		balances.set_balance(account("Eve"), 4).unwrap();
+		balances.vest(&account("Eve"), 2, 10);
+		assert_eq!(balances.redistribute_dust(5), 0);
+		assert_eq!(balances.balance(&account("Dave")), 3);
+		assert_eq!(balances.balance(&account("Eve")), 4);
+		assert_eq!(balances.iter_balances().count(), 5);
+	}
+
+	#[test]
+	fn spendable_balance() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		let bob = "Bob".to_string();
+		balances.set_balance(alice.clone(), 100).unwrap();
+		balances.reserve(&alice, 20).unwrap();
+		balances.vest(&alice, 30, 5);
+		balances.vest(&alice, 10, 10);
+
+		assert_eq!(balances.spendable_balance(&alice, 0), 40);
+		assert_eq!(balances.spendable_balance(&alice, 5), 70);
+		assert_eq!(balances.spendable_balance(&alice, 10), 80);
+
+		balances.on_initialize(5);
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 71), Err("Not enough funds!"));
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 70), Ok(()));
+		assert_eq!(balances.balance(&alice), 10);
+		assert_eq!(balances.spendable_balance(&alice, 5), 0);
+
+		// The existential deposit is spendable, and spending it reaps the account.
+		let mut balances = super::Pallet::<ReapConfig>::new();
+		balances.set_balance(alice.clone(), 50).unwrap();
+		assert_eq!(balances.spendable_balance(&alice, 0), 50);
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 45), Ok(()));
+		assert_eq!(take_reaped(&mut balances), vec![alice]);
+	}
+
+	#[test]
+	fn withdraw() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		balances.set_balance(alice.clone(), 100).unwrap();
+		balances.vest(&alice, 50, 1);
+
+		assert_eq!(balances.withdraw(&alice, 51), Err("Not enough funds!"));
+		assert_eq!(balances.withdraw(&alice, 30), Ok(()));
+		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.total_issuance(), 70);
+	}
+
+	#[test]
+	fn account_counts() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		assert_eq!(balances.account_count(), 0);
+		assert_eq!(balances.nonzero_account_count(), 0);
+
+		balances
+			.set_balances(vec![
+				("Alice".to_string(), 100),
+				("Bob".to_string(), 0),
+				("Charlie".to_string(), 1),
+			])
+			.unwrap();
+		assert_eq!(balances.account_count(), 3);
+		assert_eq!(balances.nonzero_account_count(), 2);
+	}
+
+	#[test]
+	fn clear() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "Alice".to_string();
+		balances.set_balance(alice.clone(), 100).unwrap();
+		balances.reserve(&alice, 20).unwrap();
+		balances.vest(&alice, 30, 5);
+
+		balances.clear();
+		assert_eq!(balances.account_count(), 0);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(balances.vesting_balance(&alice, 0), 0);
+		assert_eq!(balances.total_issuance(), 0);
+	}
+
+	#[test]
+	fn transfer_with_result() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		balances.set_balances(vec![(alice.clone(), 100), (bob.clone(), 10)]).unwrap();
+
+		assert_eq!(balances.transfer_with_result(alice.clone(), bob.clone(), 30), Ok((70, 40)));
+		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.balance(&bob), 40);
+
+		assert_eq!(balances.transfer_with_result(alice.clone(), alice.clone(), 30), Ok((70, 70)));
+		assert_eq!(
+			balances.transfer_with_result(bob.clone(), alice.clone(), 50),
+			Err("Not enough funds!")
+		);
+	}
+
+	#[test]
+	fn max_transfer_amount() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		balances.set_balance(alice.clone(), 200).unwrap();
+
+		assert_eq!(
+			balances.transfer(alice.clone(), bob.clone(), 101),
+			Err("Transfer exceeds maximum")
+		);
+		assert_eq!(balances.balance(&alice), 200);
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), 100), Ok(()));
+		assert_eq!(balances.balance(&bob), 100);
+	}
+
+	#[test]
+	fn find_reserves() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let charlie = "charlie".to_string();
+		for who in [&alice, &bob, &charlie] {
+			balances.set_balance(who.clone(), 100).unwrap();
+		}
+		balances.reserve(&alice, 30).unwrap();
+		balances.reserve(&bob, 20).unwrap();
+		// Fully unreserved accounts are not listed.
+		balances.reserve(&charlie, 10).unwrap();
+		balances.unreserve(&charlie, 10);
+
+		assert_eq!(balances.find_reserves(), vec![(&alice, 30), (&bob, 20)]);
+		assert_eq!(balances.reserved_total(), 50);
+	}
+
+	#[test]
+	fn vesting_remaining() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+		assert_eq!(balances.vesting_remaining(&alice, 0), None);
+
+		balances.vest(&alice, 10, 5);
+		balances.vest(&alice, 20, 10);
+		assert_eq!(balances.vesting_remaining(&alice, 0), Some(10));
+		assert_eq!(balances.vesting_remaining(&alice, 5), Some(5));
+		assert_eq!(balances.vesting_remaining(&alice, 10), Some(0));
+		assert_eq!(balances.vesting_remaining(&alice, 15), Some(0));
+	}
+
+	#[test]
+	fn wrapped_balance() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut balances = super::Pallet::<WrappedConfig>::new();
+		balances.set_balance(alice.clone(), Balance(150)).unwrap();
+
+		assert_eq!(balances.transfer(alice.clone(), bob.clone(), Balance(60)), Ok(()));
+		assert_eq!(balances.balance(&alice), Balance(90));
+		assert_eq!(balances.balance(&bob), Balance(60));
+		assert_eq!(balances.total_issuance(), Balance(150));
+
+		assert_eq!(
+			balances.transfer(alice.clone(), bob.clone(), Balance(101)),
+			Err("Transfer exceeds maximum")
+		);
+		assert_eq!(balances.transfer(bob, alice, Balance(61)), Err("Not enough funds!"));
+	}
+
+	#[test]
+	fn reap_accounts() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut balances = super::Pallet::<ReapConfig>::new();
+		balances.set_balance(alice.clone(), 100).unwrap();
+
+		// Keeping exactly the existential deposit does not reap the account.
+		balances.transfer(alice.clone(), bob.clone(), 90).unwrap();
+		assert_eq!(balances.account_count(), 2);
+
+		assert!(take_reaped(&mut balances).is_empty());
+
+		// The remaining free balance of a reaped account is burned.
+		balances.take_events();
+		balances.transfer(alice.clone(), bob.clone(), 5).unwrap();
+		assert_eq!(balances.balance(&alice), 0);
+		assert_eq!(balances.account_count(), 1);
+		assert_eq!(balances.total_issuance(), 95);
+		assert_eq!(take_reaped(&mut balances), vec![alice.clone()]);
+		assert!(matches!(
+			balances.take_events().as_slice(),
+			[.., super::Event::Reaped { who, burned: 5 }] if who == &alice
+		));
+
+		// Withdrawing can also reap an account, unless it has a reserved balance.
+		balances.reserve(&bob, 5).unwrap();
+		balances.withdraw(&bob, 85).unwrap();
+		assert_eq!(balances.balance(&bob), 5);
+		balances.unreserve(&bob, 5);
+		balances.withdraw(&bob, 1).unwrap();
+		assert_eq!(balances.account_count(), 0);
+		assert_eq!(balances.total_issuance(), 0);
+		assert_eq!(take_reaped(&mut balances), vec![bob]);
+
+		// Dust accounts which are removed are reaped too.
+		balances
+			.set_balances(vec![(alice.clone(), 100), ("charlie".to_string(), 10)])
+			.unwrap();
+		assert_eq!(balances.redistribute_dust(20), 10);
+		assert_eq!(take_reaped(&mut balances), vec!["charlie".to_string()]);
 	}
 }
@@ -1,117 +1,3651 @@
-use support::Dispatch;
+// Many functions exposed by our pallets are only used by tests or by other users of the state
+// machine, so we don't want the compiler to complain about them in this binary.
+#![allow(dead_code)]
 
-use crate::types::Block;
+use num::{traits::SaturatingMul, Saturating, Zero};
+use std::{collections::BTreeMap, fmt, ops};
+
+use support::{DepositEvent, Dispatch};
 
 mod balances;
 mod proof_of_existence;
+mod recovery;
+mod scheduler;
 mod support;
 mod system;
+mod treasury;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 #[macros::runtime]
 pub struct Runtime {
 	system: system::Pallet<Self>,
 	balances: balances::Pallet<Self>,
 	proof_of_existence: proof_of_existence::Pallet<Self>,
+	scheduler: scheduler::Pallet<Self>,
+	recovery: recovery::Pallet<Self>,
+	treasury: treasury::Pallet<Self>,
 }
 
 impl system::Config for Runtime {
-	type BlockNumber = u32;
+	type BlockNumber = types::BlockNumber;
+
+	type AccountId = types::AccountId;
+
+	type Nonce = types::Nonce;
+
+	type Hashing = types::Hashing;
+
+	type RuntimeEvent = RuntimeEvent;
+
+	const BLOCK_HASH_COUNT: u32 = 256;
 
-	type AccountId = String;
+	const MAX_BLOCK_WEIGHT: support::Weight = 100;
 
-	type Nonce = u32;
+	const ORDERING_POLICY: support::OrderingPolicy = support::OrderingPolicy::ByTip;
+	const DISPATCH_LOG_CAPACITY: u32 = 8;
+	const DEDUPLICATE_EXTRINSICS: bool = true;
+	const MAX_BLOCK_BYTES: u32 = 16 * 1024;
+
+	/// Account ids must be non-empty, at most `MAX_ACCOUNT_ID_LENGTH` bytes long, and only contain
+	/// ASCII letters, digits, `-` and `_`.
+	fn validate_account(who: &types::AccountId) -> support::DispatchResult {
+		if who.is_empty() || who.len() > MAX_ACCOUNT_ID_LENGTH {
+			return Err("Invalid account id length");
+		}
+		if !who.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+			return Err("Invalid account id character");
+		}
+		Ok(())
+	}
+
+	fn is_sudo(who: &types::AccountId) -> bool {
+		who == SUDO
+	}
 }
 
 impl balances::Config for Runtime {
-	type Balance = u128;
+	type Balance = types::Balance;
+	type OnTransfer = ();
+	type OnReap = RevokeClaims;
+	const DECIMALS: u32 = 2;
+	const MAX_BALANCE: types::Balance = types::Balance::MAX;
+	const MAX_TRANSFER_AMOUNT: types::Balance = types::Balance(1_000_000);
+	/// Only accounts which are left with nothing are reaped.
+	const EXISTENTIAL_DEPOSIT: Option<types::Balance> = Some(types::Balance(1));
 }
 
 impl proof_of_existence::Config for Runtime {
-    type Content = &'static str;
+	type Content = types::Content;
+	const MAX_TOTAL_CLAIMS: u32 = 1_000;
+	const REQUIRE_TRANSFER_ACCEPTANCE: bool = true;
+	const CLAIM_CONFIRMATIONS: types::BlockNumber = 3;
+	/// Checked after paying the fee.
+	const MIN_BALANCE_TO_CLAIM: types::Balance = types::Balance(10);
+
+	/// The deposit grows with the length of the content, to discourage large claims.
+	fn deposit_for(len: usize) -> types::Balance {
+		CLAIM_DEPOSIT_PER_BYTE.saturating_mul(&types::Balance(len as u128))
+	}
+
+	fn claim_deposit(content: &types::Content) -> types::Balance {
+		Self::deposit_for(content.len())
+	}
+}
+
+impl scheduler::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+
+	const MAX_SCHEDULED_PER_BLOCK: u32 = 5;
+}
+
+impl recovery::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+}
+
+impl treasury::Config for Runtime {
+	fn is_approver(who: &types::AccountId) -> bool {
+		who == TREASURY_APPROVER
+	}
+
+	const MAX_SPEND_RETRIES: u32 = 2;
+}
+
+/// The amount minted to the author of each block.
+const BLOCK_REWARD: types::Balance = types::Balance(10);
+
+/// The fee charged for each call, see `Runtime::estimate_fee`, which is split following
+/// `FEE_SPLIT`.
+const TRANSACTION_FEE: types::Balance = types::Balance(1);
+
+/// Whether the fee of an extrinsic which fails is refunded to its caller, rather than split
+/// following `FEE_SPLIT`.
+const REFUND_FAILED_FEES: bool = true;
+
+/// Most of each fee is burned and the rest goes to the treasury, while what is left after rounding
+/// is paid to the block author. With the current `TRANSACTION_FEE`, this is the whole fee.
+const FEE_SPLIT: FeeSplit = FeeSplit { burn_percent: 80, remainder: FeeRemainder::Author };
+
+/// The deposit reserved for each byte of the content of a claim, from the owner of the claim.
+const CLAIM_DEPOSIT_PER_BYTE: types::Balance = types::Balance(2);
+
+/// The maximum length of an account id, in bytes.
+const MAX_ACCOUNT_ID_LENGTH: usize = 32;
+
+/// The account which receives the part of the fees which is not burned.
+const TREASURY: &str = "treasury";
+
+/// The account which can dispatch privileged calls, like blacklisting accounts.
+const SUDO: &str = "sudo";
+
+/// The account which approves the spend proposals of the treasury.
+const TREASURY_APPROVER: &str = "council";
+
+/// How fees are split between being burned and going to the treasury.
+#[derive(Debug, Clone, Copy)]
+pub struct FeeSplit {
+	/// The percentage of each fee which is burned, capped at 100.
+	pub burn_percent: u8,
+	/// Where the units left over after rounding down both shares go.
+	pub remainder: FeeRemainder,
+}
+
+/// Where the remainder of a fee split goes, see `FeeSplit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeRemainder {
+	Treasury,
+	Burn,
+	/// The author of the block which included the extrinsic.
+	Author,
+}
+
+impl FeeSplit {
+	/// Split a `fee` into the amounts which are burned, go to the treasury, and go to the block
+	/// author, in that order. Both the burned and the treasury shares are rounded down, and what
+	/// is left goes where `remainder` says.
+	fn split(&self, fee: types::Balance) -> (types::Balance, types::Balance, types::Balance) {
+		let burn_percent = types::Balance(u128::from(self.burn_percent.min(100)));
+		let hundred = types::Balance(100);
+		// The hundreds and the rest of the fee are shared separately, so that large fees do not
+		// overflow.
+		let share = |percent| fee / hundred * percent + fee % hundred * percent / hundred;
+		let burn = share(burn_percent);
+		let treasury = share(hundred - burn_percent);
+		let remainder = fee - burn - treasury;
+		match self.remainder {
+			FeeRemainder::Treasury => (burn, treasury + remainder, Zero::zero()),
+			FeeRemainder::Burn => (burn + remainder, treasury, Zero::zero()),
+			FeeRemainder::Author => (burn, treasury, remainder),
+		}
+	}
+}
+
+/// The reap hook of the runtime, which keeps the accounts reaped by the balances pallet until
+/// their claims are revoked, see `Runtime::revoke_reaped_claims`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RevokeClaims(Vec<types::AccountId>);
+
+impl balances::OnReap<types::AccountId> for RevokeClaims {
+	fn on_reap(&mut self, who: &types::AccountId) {
+		self.0.push(who.clone());
+	}
+}
+
+/// The version of the runtime compiled into this binary. This must be bumped whenever a change
+/// requires a migration of the existing state, which is then done in `Runtime::on_runtime_upgrade`.
+const SPEC_VERSION: u32 = 1;
+
+impl support::Hooks<types::Header> for Runtime {
+	fn on_initialize(&mut self, header: &types::Header) {
+		// Migrate the state first if it was produced by an older runtime.
+		if self.system.spec_version() < Self::runtime_version() {
+			self.on_runtime_upgrade();
+		}
+		// Warn if the state was last modified by a different binary, unless it is a new state.
+		let code_hash = Self::code_hash();
+		let stored_code_hash = self.system.code_hash();
+		if stored_code_hash != [0; 32] && stored_code_hash != code_hash {
+			eprintln!(
+				"Code Hash Mismatch\n\tBlock Number: {}\n\tStored: {:?}\n\tCurrent: {:?}",
+				header.block_number, stored_code_hash, code_hash
+			);
+		}
+		self.system.set_code_hash(code_hash);
+
+		let now = self.system.block_number();
+		self.balances.on_initialize(now);
+		self.proof_of_existence.on_initialize(now);
+		// Release the deposits of the claims which expired, and collect their events.
+		let _res = self.apply_deposit_changes();
+		for event in self.proof_of_existence.take_events() {
+			self.deposit_event(event);
+		}
+
+		// Reward the author of the block.
+		let _res = self
+			.balances
+			.mint(&header.author, BLOCK_REWARD)
+			.map_err(|e| eprintln!("Block Reward Error\n\tBlock Number: {}\n\tError: {}", now, e));
+
+		// Dispatch the calls which were scheduled for this block, up to `MAX_SCHEDULED_PER_BLOCK`.
+		// Their weight was paid when they were scheduled.
+		for (caller, call) in self.scheduler.take_agenda(now) {
+			let _res = self.dispatch(caller, call).map_err(|e| {
+				eprintln!("Scheduled Call Error\n\tBlock Number: {}\n\tError: {}", now, e)
+			});
+		}
+	}
+
+	fn on_finalize(&mut self, _header: &types::Header) {
+		// Pay the treasury spends which were approved so far. Those which cannot be paid are
+		// retried in the next block, up to `MAX_SPEND_RETRIES` times.
+		for (id, beneficiary, amount) in self.treasury.take_approved() {
+			let call =
+				RuntimeCall::balances(balances::Call::transfer { to: beneficiary.clone(), amount });
+			if let Err(error) = self.dispatch(TREASURY.to_string(), call) {
+				self.treasury.spend_failed(id, beneficiary, amount, error);
+			}
+		}
+		for event in self.treasury.take_events() {
+			self.deposit_event(event);
+		}
+
+		// Accounts can also be reaped outside of calls, for example by paying a fee.
+		self.revoke_reaped_claims();
+		let _res = self.apply_deposit_changes();
+
+		self.balances.on_finalize();
+	}
+
+	fn on_dispatched(&mut self) -> support::DispatchResult {
+		// Revoke the claims of the accounts reaped by the call, and apply the deposits of the
+		// claims changed by the call.
+		let calls = self.recovery.take_calls();
+		self.revoke_reaped_claims();
+		self.apply_deposit_changes()?;
+		// Dispatch the calls which a rescuer made on behalf of the account they recovered.
+		for (lost, call) in calls {
+			self.dispatch(lost, call)?;
+		}
+		Ok(())
+	}
+}
+
+impl support::Fees<types::Header, types::AccountId, RuntimeCall> for Runtime {
+	type Fee = types::Balance;
+
+	fn charge_fee(
+		&mut self,
+		caller: &types::AccountId,
+		call: &RuntimeCall,
+	) -> Result<types::Balance, &'static str> {
+		let fee = self.estimate_fee(call);
+		self.balances.withdraw(caller, fee).map_err(|_| "Cannot pay the fee")?;
+		Ok(fee)
+	}
+
+	fn settle_fee(
+		&mut self,
+		header: &types::Header,
+		caller: &types::AccountId,
+		fee: types::Balance,
+		result: &support::DispatchResult,
+	) -> support::DispatchResult {
+		// The fee was withdrawn from the caller, so whatever is not minted again is burned.
+		let payouts = if result.is_err() && REFUND_FAILED_FEES {
+			vec![(caller.clone(), fee)]
+		} else {
+			self.balances.note_fee(fee);
+			let (_burn, treasury, author) = FEE_SPLIT.split(fee);
+			vec![(TREASURY.to_string(), treasury), (header.author.clone(), author)]
+		};
+		for (payee, amount) in payouts {
+			if !amount.is_zero() {
+				self.balances.mint(&payee, amount)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl support::CallFilter<types::AccountId, RuntimeCall> for Runtime {
+	fn filter_call(
+		&self,
+		caller: &types::AccountId,
+		call: &RuntimeCall,
+	) -> support::DispatchResult {
+		// The treasury must be able to pay every approved spend.
+		if let RuntimeCall::treasury(treasury::Call::approve_spend { id }) = call {
+			// Let the pallet reject callers which cannot approve, and unknown proposals.
+			let Some(proposal) = self
+				.treasury
+				.proposal(*id)
+				.filter(|_| <Self as treasury::Config>::is_approver(caller))
+			else {
+				return Ok(());
+			};
+			let committed = self.treasury.approved_total().saturating_add(proposal.amount);
+			if self.balances.balance(&TREASURY.to_string()) < committed {
+				return Err("Insufficient treasury funds");
+			}
+			return Ok(());
+		}
+
+		// The pallet checks the funds of claims, since deposits are only reserved after the call.
+		if let RuntimeCall::proof_of_existence(call) = call {
+			return self
+				.proof_of_existence
+				.check_funds(caller, call, |who| self.balances.balance(who));
+		}
+		Ok(())
+	}
+}
+
+/// The initial state of our state machine, used to build the runtime before any block is
+/// executed.
+#[derive(Debug, Default)]
+pub struct GenesisConfig {
+	/// The initial balance of each account.
+	pub balances: Vec<(types::AccountId, types::Balance)>,
+	/// The initial claims, and the account which owns each of them.
+	pub claims: Vec<(types::Content, types::AccountId)>,
+	/// The initial nonce of each account, for example to resume an existing chain.
+	pub nonces: Vec<(types::AccountId, types::Nonce)>,
+	/// The initial validators which take turns authoring blocks, and their stake. The stake is
+	/// minted on top of their initial balance, and staked.
+	pub validators: Vec<(types::AccountId, types::Balance)>,
+}
+
+/// The extrinsics submitted to the chain which are waiting to be included in a block.
+#[derive(Debug, Default)]
+pub struct TxPool {
+	pending: Vec<types::Extrinsic>,
+}
+
+impl TxPool {
+	/// How far ahead of the current nonce of its caller an extrinsic can be to enter the pool.
+	/// This bounds the memory used by extrinsics which cannot be included for a long time.
+	const MAX_FUTURE_NONCE: types::Nonce = 16;
+
+	/// Add `extrinsic` to the pool, checking its nonce against the state of `runtime`.
+	fn submit(
+		&mut self,
+		runtime: &Runtime,
+		extrinsic: types::Extrinsic,
+	) -> Result<(), &'static str> {
+		let next_nonce = runtime.system.next_nonce(&extrinsic.caller);
+		if extrinsic.nonce > next_nonce.saturating_add(Self::MAX_FUTURE_NONCE) {
+			return Err("Nonce too far in future");
+		}
+		self.pending.push(extrinsic);
+		Ok(())
+	}
+
+	/// Get the extrinsics waiting in the pool, in the order they were submitted.
+	fn pending(&self) -> &[types::Extrinsic] {
+		&self.pending
+	}
+
+	/// Remove all the extrinsics from the pool, for example to include them in a block.
+	fn take(&mut self) -> Vec<types::Extrinsic> {
+		std::mem::take(&mut self.pending)
+	}
 }
 
+/// Statistics about all the blocks executed since genesis, see `Runtime::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainStats {
+	/// The number of extrinsics dispatched, whether they succeeded or not.
+	pub total_extrinsics: u64,
+	/// The transaction fees which were not refunded.
+	pub total_fees: types::Balance,
+	/// The average number of extrinsics per block, counting empty blocks.
+	pub average_extrinsics_per_block: f64,
+}
+
+impl Runtime {
+	/// Get the version of the runtime compiled into this binary.
+	fn runtime_version() -> u32 {
+		SPEC_VERSION
+	}
+
+	/// Get the hash of the runtime code compiled into this binary. This only depends on the
+	/// version of the crate and `SPEC_VERSION`, so it is the same across runs of the same binary.
+	fn code_hash() -> [u8; 32] {
+		let version = (env!("CARGO_PKG_VERSION").to_string(), SPEC_VERSION);
+		<system::Pallet<Self>>::hash(&support::Encode::encode(&version))
+	}
+
+	/// Called before the first block executed by a newer version of the runtime than the one which
+	/// last modified the state. This is where migrations of the state should be added.
+	fn on_runtime_upgrade(&mut self) {
+		self.upgrade_to(Self::runtime_version(), |_runtime| {});
+	}
+
+	/// Run the `migrate` function if the state was last modified by an older runtime than
+	/// `version`, and store `version` as the new version of the state.
+	/// Returns whether the migration was run.
+	fn upgrade_to(&mut self, version: u32, migrate: impl FnOnce(&mut Self)) -> bool {
+		if self.system.spec_version() >= version {
+			return false;
+		}
+		migrate(self);
+		self.system.set_spec_version(version);
+		true
+	}
+
+	/// Halt the chain in an emergency. No block can be executed until the chain is resumed, but
+	/// the state can still be queried.
+	///
+	/// This is an API for the operator of the node, not a call: since a halted chain executes no
+	/// block, it could not be resumed by an extrinsic. Still, only the sudo account can use it, so
+	/// this returns an error if the `caller` is not the sudo account.
+	fn halt(&mut self, caller: &types::AccountId) -> support::DispatchResult {
+		system::Pallet::<Self>::ensure_sudo(caller)?;
+		self.system.set_halted(true);
+		Ok(())
+	}
+
+	/// Resume the chain after it was halted by the sudo account, see `halt`.
+	fn resume(&mut self, caller: &types::AccountId) -> support::DispatchResult {
+		system::Pallet::<Self>::ensure_sudo(caller)?;
+		self.system.set_halted(false);
+		Ok(())
+	}
+
+	/// Build a new runtime with the initial state described by the genesis `config`.
+	/// This returns an error if the same account is given more than one balance, if the same
+	/// content is claimed more than once, or if the owner of a claim cannot pay its deposit.
+	fn build_genesis(config: GenesisConfig) -> Result<Self, &'static str> {
+		let mut runtime = Self::new();
+		runtime.balances.set_balances(config.balances)?;
+		for (claim, owner) in config.claims {
+			runtime.proof_of_existence.create_claim(owner, claim, None)?;
+		}
+		runtime.apply_deposit_changes()?;
+		// The genesis state is not built by any block, so there is no block to deposit events in.
+		runtime.proof_of_existence.take_events();
+		for (who, nonce) in config.nonces {
+			runtime.system.set_nonce(who, nonce);
+		}
+		let mut validators = Vec::new();
+		for (who, stake) in config.validators {
+			runtime.balances.mint(&who, stake)?;
+			runtime.balances.stake(&who, stake)?;
+			if !validators.contains(&who) {
+				validators.push(who);
+			}
+		}
+		runtime.system.set_validators(validators);
+
+		// The genesis hash covers the resulting state rather than the config, so that it does not
+		// depend on the order of the config entries.
+		let balances: Vec<_> = runtime.balances.iter_balances().collect();
+		let claims: Vec<_> = runtime.proof_of_existence.iter_claims().collect();
+		let nonces: Vec<_> = runtime.system.nonces().collect();
+		let validators: Vec<_> = runtime
+			.system
+			.validators()
+			.iter()
+			.map(|who| (who, runtime.balances.stake_of(who)))
+			.collect();
+		let state = (((balances, claims), nonces), validators);
+		let genesis_hash = <system::Pallet<Self>>::hash(&support::Encode::encode(&state));
+		runtime.system.set_genesis_hash(genesis_hash);
+		// Snapshot the genesis issuance, as if it was produced by block 0.
+		runtime.balances.on_finalize();
+		Ok(runtime)
+	}
+
+	/// Get the hash of the genesis state this runtime was built from, see `build_genesis`.
+	fn genesis_hash(&self) -> [u8; 32] {
+		self.system.genesis_hash()
+	}
+
+	/// Build the next block, authored by `author`, with `extrinsics`, and execute it. The header
+	/// references the current block as its parent. Returns the header of the sealed block.
+	fn seal_block(
+		&mut self,
+		author: types::AccountId,
+		extrinsics: Vec<types::Extrinsic>,
+	) -> Result<types::Header, &'static str> {
+		let header = support::Header {
+			block_number: self.system.block_number() + 1,
+			parent_hash: self.system.parent_hash(),
+			author,
+		};
+		self.execute_block(types::Block { header: header.clone(), extrinsics })?;
+		Ok(header)
+	}
+
+	/// Re-execute `block` on a copy of `pre_state`, the state before the block was executed, and
+	/// return the resulting state. This can be used to verify the state after a block.
+	/// The events and log lines of the replay already happened, so they are not streamed, and the
+	/// returned state has no handlers.
+	fn replay_block(pre_state: &Runtime, block: &types::Block) -> Result<Runtime, &'static str> {
+		let mut runtime = pre_state.clone();
+		runtime.system.clear_handlers();
+		runtime.execute_block(block.clone())?;
+		Ok(runtime)
+	}
+
+	/// Check that `blocks` form a valid chain on top of the `genesis` state, by executing them one
+	/// after the other. This checks everything `execute_block` does: sequential block numbers,
+	/// linked parent hashes, valid nonces, and so on. Returns the index of the first invalid block
+	/// along with the error, or no index if the genesis config itself is invalid.
+	fn verify_chain(
+		genesis: GenesisConfig,
+		blocks: &[types::Block],
+	) -> Result<(), (Option<usize>, &'static str)> {
+		let mut runtime = Self::build_genesis(genesis).map_err(|e| (None, e))?;
+		for (i, block) in blocks.iter().enumerate() {
+			runtime.execute_block(block.clone()).map_err(|e| (Some(i), e))?;
+		}
+		Ok(())
+	}
+
+	/// Merge the balances and claims of `other` into this runtime:
+	/// - the free balance of each account of `other` is minted on top of its balance here, which
+	///   follows the configured `OverflowStrategy`,
+	/// - each claim of `other` is created here with the same owner and content type, and a new
+	///   expiry, and its deposit is reserved from its owner. A claim with the same owner in both
+	///   runtimes is kept as is, but a claim owned by different accounts is a conflict, which makes
+	///   the merge fail.
+	///
+	/// Everything else, like reserved balances, nonces and the block number, is kept from this
+	/// runtime. If the merge fails, this runtime is left unchanged.
+	fn merge(&mut self, other: &Runtime) -> Result<(), String> {
+		let mut merged = self.clone();
+		for (who, amount) in other.balances.iter_balances() {
+			merged
+				.balances
+				.mint(who, *amount)
+				.map_err(|e| format!("Cannot merge the balance of {who}: {e}"))?;
+		}
+		for (claim, owner) in other.proof_of_existence.iter_claims() {
+			match merged.proof_of_existence.get_claim(claim) {
+				Some(existing) if existing == owner => continue,
+				Some(existing) => {
+					return Err(format!(
+						"Conflicting owners for claim {claim:?}: {existing} and {owner}"
+					))
+				},
+				None => {},
+			}
+			let content_type = other.proof_of_existence.content_type(claim).map(String::from);
+			merged
+				.proof_of_existence
+				.create_claim(owner.clone(), claim.clone(), content_type)
+				.map_err(|e| format!("Cannot merge the claim {claim:?}: {e}"))?;
+		}
+		merged
+			.apply_deposit_changes()
+			.map_err(|e| format!("Cannot merge the claim deposits: {e}"))?;
+		// The merge is not part of any block, so there is no block to deposit events in.
+		merged.balances.take_events();
+		merged.proof_of_existence.take_events();
+		*self = merged;
+		Ok(())
+	}
+
+	/// Get how much the total issuance changed from the end of `from_block` to the end of
+	/// `to_block`, positive for inflation and negative for deflation. The issuance at each block
+	/// comes from the snapshot taken at the end of every block, and is zero before the first one.
+	fn issuance_delta(&self, from_block: types::BlockNumber, to_block: types::BlockNumber) -> i128 {
+		let issuance = |block| {
+			let issuance = self.balances.issuance_at(block).unwrap_or_default();
+			i128::try_from(issuance.0).unwrap_or(i128::MAX)
+		};
+		issuance(to_block) - issuance(from_block)
+	}
+
+	/// Get the root of the Merkle tree over the free balance of every stored account, ordered by
+	/// account. This commits to all the balances, so that they can be proven with `balance_proof`.
+	fn balances_root(&self) -> [u8; 32] {
+		support::merkle_root::<types::Hashing>(&self.balance_leaves())
+	}
+
+	/// Get the root of the whole state, which commits to the `balances_root`, the nonce root of
+	/// the system pallet, the reserved balances and stakes, the claims and their owners, the
+	/// validators, whether the chain is halted, and the state hash of each pallet: the blacklist
+	/// and spec version, the vesting schedules, the rest of the claims, the scheduled calls, the
+	/// recoveries and the treasury spends. Changing any of them changes the root.
+	fn state_root(&self) -> [u8; 32] {
+		support::merkle_root::<types::Hashing>(&self.state_leaves())
+	}
+
+	/// Get the Merkle tree leaves of the `state_root`, each of which is the root of a part of the
+	/// state. The `balances_root` comes first, so that `balance_proof` can prove it.
+	fn state_leaves(&self) -> Vec<[u8; 32]> {
+		let hash = |bytes: Vec<u8>| <system::Pallet<Runtime>>::hash(&bytes);
+		let reserves: Vec<_> = self
+			.balances
+			.find_reserves()
+			.into_iter()
+			.map(|(who, reserved)| {
+				hash(support::Encode::encode(&(who, (reserved, self.balances.stake_of(who)))))
+			})
+			.collect();
+		let claims: Vec<_> = self
+			.proof_of_existence
+			.iter_claims()
+			.map(|claim| hash(support::Encode::encode(&claim)))
+			.collect();
+		vec![
+			self.balances_root(),
+			self.system.nonce_root(),
+			support::merkle_root::<types::Hashing>(&reserves),
+			support::merkle_root::<types::Hashing>(&claims),
+			hash(support::Encode::encode(self.system.validators())),
+			hash(support::Encode::encode(&u8::from(self.system.is_halted()))),
+			self.system.config_hash(),
+			self.balances.vesting_hash(),
+			self.proof_of_existence.state_hash(),
+			self.scheduler.agenda_hash(),
+			self.recovery.state_hash(),
+			self.treasury.state_hash(),
+		]
+	}
+
+	/// Get the Merkle tree leaves of the `balances_root`.
+	fn balance_leaves(&self) -> Vec<[u8; 32]> {
+		self.balances
+			.iter_balances()
+			.map(|(who, balance)| balance_leaf(who, *balance))
+			.collect()
+	}
+
+	/// Build a proof of the free balance of `who` against the `balances_root`, and of that root
+	/// against the `state_root`, which can be checked with `verify_balance_proof`. Returns `None`
+	/// if `who` has no stored balance.
+	fn balance_proof(&self, who: &types::AccountId) -> Option<BalanceProof> {
+		let leaves = self.balance_leaves();
+		let index = self.balances.iter_balances().position(|(account, _)| account == who)?;
+		Some(BalanceProof {
+			who: who.clone(),
+			balance: self.balances.balance(who),
+			index: index as u32,
+			siblings: support::merkle_proof::<types::Hashing>(&leaves, index)?,
+			root: support::merkle_root::<types::Hashing>(&leaves),
+			state_siblings: support::merkle_proof::<types::Hashing>(&self.state_leaves(), 0)?,
+		})
+	}
+
+	/// Clear the state of every pallet, see the `clear` function of each of them. The chain can
+	/// keep executing blocks on top of the cleared state.
+	fn clear_state(&mut self) {
+		self.system.clear();
+		self.balances.clear();
+		self.proof_of_existence.clear();
+		self.scheduler.clear();
+		self.recovery.clear();
+		self.treasury.clear();
+	}
+
+	/// Get the total value held by `who` across all pallets: their free and reserved balances.
+	fn total_holdings(&self, who: &types::AccountId) -> types::Balance {
+		self.balances.balance(who).saturating_add(self.balances.reserved_balance(who))
+	}
+
+	/// Get the events deposited during the current block which match the filter `f`.
+	fn events_filtered<F: Fn(&RuntimeEvent) -> bool>(&self, f: F) -> Vec<&RuntimeEvent> {
+		self.system.events().iter().filter(|event| f(event)).collect()
+	}
+
+	/// Get the outcomes of the most recent dispatches, across blocks, oldest first.
+	fn recent_dispatches(&self) -> &[system::DispatchRecord<Self>] {
+		self.system.recent_dispatches()
+	}
+
+	/// Aggregate statistics about all the blocks executed since genesis.
+	fn stats(&self) -> ChainStats {
+		let total_extrinsics: u64 =
+			self.system.extrinsic_counts().map(|(_, count)| u64::from(*count)).sum();
+		let total_fees = self.balances.fees_collected().map(|(_, fees)| fees).sum();
+		let blocks = self.system.block_number();
+		let average_extrinsics_per_block =
+			if blocks == 0 { 0.0 } else { total_extrinsics as f64 / f64::from(blocks) };
+		ChainStats { total_extrinsics, total_fees, average_extrinsics_per_block }
+	}
+
+	/// Dump the whole state as a report for humans, for example to attach to a support ticket.
+	/// Unlike `Display`, which is a short summary, the report is split into sections, and also
+	/// shows the reserved balances and the events of the current block.
+	fn report(&self) -> String {
+		let mut report = String::from("== Block ==\n");
+		report.push_str(&format!("\tNumber: {}\n", self.system.block_number()));
+
+		report.push_str("== Balances ==\n");
+		for (who, amount) in self.balances.iter_balances() {
+			report.push_str(&format!("\t{}: {}", who, format_balance(*amount)));
+			let reserved = self.balances.reserved_balance(who);
+			if !reserved.is_zero() {
+				report.push_str(&format!(" ({} reserved)", format_balance(reserved)));
+			}
+			report.push('\n');
+		}
+
+		let mut claims_by_owner = BTreeMap::<_, Vec<_>>::new();
+		for (claim, owner) in self.proof_of_existence.iter_claims() {
+			claims_by_owner.entry(owner).or_default().push(claim);
+		}
+		report.push_str("== Claims ==\n");
+		for (owner, claims) in claims_by_owner {
+			report.push_str(&format!("\t{}: {:?}\n", owner, claims));
+		}
+
+		report.push_str("== Recent events ==\n");
+		for event in self.system.events() {
+			report.push_str(&format!("\t{:?}\n", event));
+		}
+		report
+	}
+
+	/// Simulate dispatching a `call` on behalf of the `caller`, and return the result.
+	/// The call is dispatched against a clone of the runtime, so the real state is never mutated.
+	fn simulate(&self, caller: types::AccountId, call: RuntimeCall) -> support::DispatchResult {
+		let mut runtime = self.clone();
+		// The events of a simulated dispatch never happened, so they are not streamed.
+		runtime.system.clear_event_handler();
+		runtime.dispatch(caller, call)
+	}
+
+	/// Set a callback which is invoked with each event as it is deposited during dispatch, for
+	/// example to log events live. Clones of the runtime share the callback.
+	fn set_event_handler(&mut self, handler: support::BoxedEventHandler<RuntimeEvent>) {
+		self.system.set_event_handler(handler);
+	}
+
+	/// Set a callback which is invoked with a JSON line describing the outcome of each executed
+	/// extrinsic, for example to feed a log collector. Clones of the runtime share the callback.
+	#[cfg(feature = "logging")]
+	fn set_log_handler(&mut self, handler: support::BoxedLogHandler) {
+		self.system.set_log_handler(handler);
+	}
+
+	/// Select the author of the next block among `validators`, in proportion to their stake in
+	/// the balances pallet. Validators with equal stakes take turns, and if no validator has any
+	/// stake, they all take turns. Returns `None` if there is no validator.
+	///
+	/// Each period has as many blocks as the validators have stake, once scaled down, and each
+	/// validator authors as many consecutive blocks of it as their scaled stake, in order. So the
+	/// selection only depends on the block number and the stakes.
+	fn select_author(&self, validators: &[types::AccountId]) -> Option<types::AccountId> {
+		if validators.is_empty() {
+			return None;
+		}
+		let mut stakes: Vec<_> = validators.iter().map(|who| self.balances.stake_of(who)).collect();
+		if stakes.iter().all(|stake| stake.is_zero()) {
+			stakes.iter_mut().for_each(|stake| *stake = types::Balance(1));
+		}
+		// Scale the stakes down, which keeps the same shares with a shorter period.
+		let divisor = stakes.iter().copied().fold(Zero::zero(), gcd);
+		let weights: Vec<_> = stakes.iter().map(|stake| *stake / divisor).collect();
+		let period = weights
+			.iter()
+			.fold(Zero::zero(), |period: types::Balance, weight| period.saturating_add(*weight));
+
+		// Find the validator whose blocks cover the position of the block in its period.
+		let position = types::Balance(u128::from(self.system.block_number())) % period;
+		let mut end: types::Balance = Zero::zero();
+		let selected = weights.iter().position(|weight| {
+			end = end.saturating_add(*weight);
+			position < end
+		})?;
+		validators.get(selected).cloned()
+	}
+
+	/// Select the author of the next block among the validators set at genesis, see
+	/// `select_author`.
+	fn next_author(&self) -> Option<types::AccountId> {
+		self.select_author(self.system.validators())
+	}
+
+	/// Get the fee which dispatching `call` in an extrinsic would charge, without executing it.
+	/// Each call costs `TRANSACTION_FEE`, and batch calls pay for each of their parts instead: a
+	/// `create_claims` pays for each claim, and a `schedule` or an `as_recovered` pays for the
+	/// call it carries on top of its own fee, since that call is never charged by itself.
+	fn estimate_fee(&self, call: &RuntimeCall) -> types::Balance {
+		match call {
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claims { claims }) => {
+				TRANSACTION_FEE.saturating_mul(&types::Balance(claims.len().max(1) as u128))
+			},
+			RuntimeCall::scheduler(scheduler::Call::schedule { call, .. })
+			| RuntimeCall::recovery(recovery::Call::as_recovered { call, .. }) => {
+				TRANSACTION_FEE.saturating_add(self.estimate_fee(call))
+			},
+			_ => TRANSACTION_FEE,
+		}
+	}
+
+	/// Revoke the claims of the accounts which were reaped by the balances pallet, except the
+	/// locked ones, and collect the events of the revoked claims.
+	fn revoke_reaped_claims(&mut self) {
+		for who in core::mem::take(&mut self.balances.on_reap_mut().0) {
+			self.proof_of_existence.revoke_all(who);
+		}
+		for event in self.proof_of_existence.take_events() {
+			self.deposit_event(event);
+		}
+	}
+
+	/// Apply the changes to the claim deposits made by the proof of existence pallet to the
+	/// balances of the accounts. A deposit which cannot be reserved is forgotten, and the first
+	/// such error is returned once all the changes were applied.
+	fn apply_deposit_changes(&mut self) -> support::DispatchResult {
+		let mut result = Ok(());
+		for change in self.proof_of_existence.take_deposit_changes() {
+			match change {
+				proof_of_existence::DepositChange::Reserve { who, amount, claim } => {
+					if let Err(error) = self.balances.reserve(&who, amount) {
+						self.proof_of_existence.forget_deposit(&claim, amount);
+						result = result.and(Err(error));
+					}
+				},
+				proof_of_existence::DepositChange::Release { who, amount } => {
+					self.balances.unreserve(&who, amount);
+				},
+			}
+		}
+		result
+	}
+
+	/// Get a read only view of the runtime, for example for reporting.
+	fn view(&self) -> RuntimeView<'_> {
+		RuntimeView { runtime: self }
+	}
+}
+
+/// A read only view of a runtime, which only exposes queries. The runtime cannot be mutated while
+/// a view of it exists, since the view borrows it.
+#[derive(Debug, Clone, Copy)]
+pub struct RuntimeView<'a> {
+	runtime: &'a Runtime,
+}
+
+impl RuntimeView<'_> {
+	/// Get the current block number.
+	fn block_number(&self) -> types::BlockNumber {
+		self.runtime.system.block_number()
+	}
+
+	/// Get the free balance of `who`.
+	fn balance(&self, who: &types::AccountId) -> types::Balance {
+		self.runtime.balances.balance(who)
+	}
+
+	/// Get the reserved balance of `who`.
+	fn reserved_balance(&self, who: &types::AccountId) -> types::Balance {
+		self.runtime.balances.reserved_balance(who)
+	}
+
+	/// Get the total issuance of the runtime.
+	fn total_issuance(&self) -> types::Balance {
+		self.runtime.balances.total_issuance()
+	}
+
+	/// Get the owner (if any) of a claim.
+	fn claim_owner(&self, claim: &types::Content) -> Option<&types::AccountId> {
+		self.runtime.proof_of_existence.get_claim(claim)
+	}
+
+	/// Iterate over all the claims and their owners, ordered by content.
+	fn iter_claims(&self) -> impl Iterator<Item = (&types::Content, &types::AccountId)> {
+		self.runtime.proof_of_existence.iter_claims()
+	}
+
+	/// Get the nonce the next extrinsic of `who` must use.
+	fn next_nonce(&self, who: &types::AccountId) -> types::Nonce {
+		self.runtime.system.next_nonce(who)
+	}
+
+	/// Get the events deposited during the current block.
+	fn events(&self) -> &[RuntimeEvent] {
+		self.runtime.system.events()
+	}
+
+	/// Get the root of the whole state, see `Runtime::state_root`.
+	fn state_root(&self) -> [u8; 32] {
+		self.runtime.state_root()
+	}
+}
+
+/// A guard which gives mutable access to a runtime, and restores the state the runtime had when
+/// the guard was created once it is dropped, unless the changes are kept with `commit`.
+/// The changes may be rolled back, so the events and log lines of the guarded runtime are not
+/// streamed to its handlers until the guard is gone.
+pub struct StateGuard<'a> {
+	runtime: &'a mut Runtime,
+	snapshot: Option<Runtime>,
+}
+
+impl<'a> StateGuard<'a> {
+	/// Snapshot the state of `runtime`, and guard it until the returned guard is dropped.
+	fn new(runtime: &'a mut Runtime) -> Self {
+		let snapshot = Some(runtime.clone());
+		runtime.system.clear_handlers();
+		Self { runtime, snapshot }
+	}
+
+	/// Keep all the changes made through this guard.
+	fn commit(mut self) {
+		if let Some(snapshot) = self.snapshot.take() {
+			self.runtime.system.share_handlers(&snapshot.system);
+		}
+	}
+}
+
+impl ops::Deref for StateGuard<'_> {
+	type Target = Runtime;
+
+	fn deref(&self) -> &Runtime {
+		self.runtime
+	}
+}
+
+impl ops::DerefMut for StateGuard<'_> {
+	fn deref_mut(&mut self) -> &mut Runtime {
+		self.runtime
+	}
+}
+
+impl Drop for StateGuard<'_> {
+	fn drop(&mut self) {
+		if let Some(snapshot) = self.snapshot.take() {
+			*self.runtime = snapshot;
+		}
+	}
+}
+
+/// A proof that `who` has a free balance of `balance`, in the state with the balances `root`,
+/// along with a proof that this root is part of the state root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceProof {
+	pub who: types::AccountId,
+	pub balance: types::Balance,
+	/// The index of the account among all the accounts with a stored balance, ordered by account.
+	pub index: u32,
+	/// The sibling hashes from the leaf of the account up to the root, see `support::merkle_proof`.
+	pub siblings: Vec<[u8; 32]>,
+	pub root: [u8; 32],
+	/// The sibling hashes from the balances `root`, which is the first leaf of the state root, up
+	/// to the state root.
+	pub state_siblings: Vec<[u8; 32]>,
+}
+
+/// Get the greatest common divisor of `a` and `b`, where the divisor of anything and `0` is
+/// the thing itself.
+fn gcd(a: types::Balance, b: types::Balance) -> types::Balance {
+	if b.is_zero() {
+		a
+	} else {
+		gcd(b, a % b)
+	}
+}
+
+/// Hash the Merkle tree leaf for the free `balance` of `who`.
+fn balance_leaf(who: &types::AccountId, balance: types::Balance) -> [u8; 32] {
+	<system::Pallet<Runtime>>::hash(&support::Encode::encode(&(who, balance)))
+}
+
+/// Check that `proof` proves the balance of its account against the balances root of the proof,
+/// and that root against the `state_root`, which the verifier must get from a source they trust
+/// rather than from the proof.
+fn verify_balance_proof(proof: &BalanceProof, state_root: [u8; 32]) -> bool {
+	support::verify_merkle_proof::<types::Hashing>(
+		balance_leaf(&proof.who, proof.balance),
+		proof.index as usize,
+		&proof.siblings,
+		proof.root,
+	) && support::verify_merkle_proof::<types::Hashing>(
+		proof.root,
+		0,
+		&proof.state_siblings,
+		state_root,
+	)
+}
+
+/// Encode a whole `block` into bytes, for example to send it over the network.
+fn encode_block(block: &types::Block) -> Vec<u8> {
+	support::Encode::encode(block)
+}
+
+/// Decode a block from `bytes` produced by `encode_block`.
+fn decode_block(bytes: &[u8]) -> Result<types::Block, String> {
+	let mut input = bytes;
+	let block = support::Decode::decode(&mut input)?;
+	if !input.is_empty() {
+		return Err(format!("{} bytes left after decoding the block", input.len()));
+	}
+	Ok(block)
+}
+
+/// Format a raw `amount` as a human readable token amount, using the token decimals.
+fn format_balance(amount: types::Balance) -> String {
+	let decimals = <Runtime as balances::Config>::DECIMALS;
+	let unit = types::Balance(10u128.pow(decimals));
+	format!("{}.{:0width$}", amount / unit, amount % unit, width = decimals as usize)
+}
+
+/// A human readable summary of the runtime state.
+/// Use `Debug` instead when you need the exact raw values.
+impl fmt::Display for Runtime {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "Block number: {}", self.system.block_number())?;
+
+		writeln!(f, "Balances:")?;
+		for (who, amount) in self.balances.iter_balances() {
+			writeln!(f, "\t{}: {}", who, format_balance(*amount))?;
+		}
+
+		// Group the claims by their owner.
+		let mut claims_by_owner = BTreeMap::<_, Vec<_>>::new();
+		for (claim, owner) in self.proof_of_existence.iter_claims() {
+			claims_by_owner.entry(owner).or_default().push(claim);
+		}
+		writeln!(f, "Claims:")?;
+		for (owner, claims) in claims_by_owner {
+			writeln!(f, "\t{}: {:?}", owner, claims)?;
+		}
+		Ok(())
+	}
+}
 
 // These are the concrete types we will use in our simple state machine.
 // Modules are configured for these types directly, and they satisfy all of our
 // trait requirements.
 mod types {
 	pub type AccountId = String;
-	pub type Balance = u128;
+	pub use crate::support::Balance;
 	pub type BlockNumber = u32;
 	pub type Nonce = u32;
-	pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
-	pub type Header = crate::support::Header<BlockNumber>;
+	pub type Hashing = crate::support::Sha256;
+	pub type Content = String;
+	pub type Extrinsic =
+		crate::support::Extrinsic<AccountId, crate::RuntimeCall, Nonce, Balance, BlockNumber>;
+	pub type Header = crate::support::Header<BlockNumber, AccountId>;
 	pub type Block = crate::support::Block<Header, Extrinsic>;
 }
 
-
 fn main() {
-	let mut runtime = Runtime::new();
 	let alice = &"alice".to_string();
 	let bob = &"bob".to_string();
 	let charlie = &"charlie".to_string();
-	runtime.balances.set_balance(alice.into(), 100);
-
-	let block_1 = Block {
-		header: support::Header { block_number: 1 },
-		extrinsics: vec![support::Extrinsic {
-			caller: "alice".to_string(),
-			call: RuntimeCall::balances(balances::Call::transfer {
-				to: "bob".to_string(),
-				amount: 69,
-			}),
-		}],
-	};
-
-    let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "Hello, world!",
-                }),
-            },
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "Hello, world!",
-                }),
-            },
-        ],
-    };
-
-    let block_3 = types::Block {
-        header: support::Header { block_number: 3 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
-                    claim: "Hello, world!",
-                }),
-            },
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "Hello, world!",
-                }),
-            },
-        ],
-    };
+	let mut runtime = Runtime::build_genesis(GenesisConfig {
+		balances: vec![(alice.clone(), types::Balance(100))],
+		..Default::default()
+	})
+	.expect("The genesis config must be valid.");
 
 	runtime
-		.execute_block(block_1)
+		.seal_block(
+			charlie.clone(),
+			vec![support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer {
+					to: bob.clone(),
+					amount: types::Balance(69),
+				}),
+				0,
+			)],
+		)
 		.expect("All blocks being executed must be valid.");
 
 	runtime
-		.execute_block(block_2)
+		.seal_block(
+			charlie.clone(),
+			vec![
+				support::Extrinsic::new(
+					alice.clone(),
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: "Hello, world!".to_string(),
+						content_type: None,
+					}),
+					1,
+				),
+				support::Extrinsic::new(
+					bob.clone(),
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: "Hello, world!".to_string(),
+						content_type: None,
+					}),
+					0,
+				),
+			],
+		)
 		.expect("All blocks being executed must be valid.");
 
 	runtime
-		.execute_block(block_3)
+		.seal_block(
+			charlie.clone(),
+			vec![
+				support::Extrinsic::new(
+					alice.clone(),
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+						claim: "Hello, world!".to_string(),
+					}),
+					2,
+				),
+				support::Extrinsic::new(
+					bob.clone(),
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: "Hello, world!".to_string(),
+						content_type: None,
+					}),
+					1,
+				),
+			],
+		)
 		.expect("All blocks being executed must be valid.");
 
 	// inspect the runtime state
 	println!("{:#?}", runtime);
+	println!("{}", runtime);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A tiny deterministic pseudo random number generator (splitmix64), so that generated test
+	/// blocks are reproducible from their seed.
+	struct Rng(u64);
+
+	impl Rng {
+		fn next(&mut self) -> u64 {
+			self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+			let mut z = self.0;
+			z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+			z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+			z ^ (z >> 31)
+		}
+
+		fn below(&mut self, n: usize) -> usize {
+			(self.next() % n as u64) as usize
+		}
+	}
+
+	/// Build a runtime from a genesis config where `who` has a balance of 100, and nothing else.
+	fn funded_runtime(who: &types::AccountId) -> Runtime {
+		Runtime::build_genesis(GenesisConfig {
+			balances: vec![(who.clone(), types::Balance(100))],
+			..Default::default()
+		})
+		.unwrap()
+	}
+
+	/// Build the block with `extrinsics` which can be executed next on `runtime`.
+	fn next_block(runtime: &Runtime, extrinsics: Vec<types::Extrinsic>) -> types::Block {
+		types::Block {
+			header: support::Header {
+				block_number: runtime.system.block_number() + 1,
+				parent_hash: runtime.system.parent_hash(),
+				author: "author".to_string(),
+			},
+			extrinsics,
+		}
+	}
+
+	/// Generate a random block of transfers and claims between `accounts`, which can be executed
+	/// next on `runtime`. Some of the extrinsics may fail, for example because of a lack of funds.
+	fn gen_block(runtime: &Runtime, accounts: &[types::AccountId], seed: u64) -> types::Block {
+		const CONTENT: [&str; 4] = ["a", "b", "c", "d"];
+		let mut rng = Rng(seed);
+		let mut nonces = BTreeMap::new();
+		let extrinsics = (0..rng.below(10))
+			.map(|_| {
+				let caller = accounts[rng.below(accounts.len())].clone();
+				let next_nonce =
+					nonces.entry(caller.clone()).or_insert(runtime.system.next_nonce(&caller));
+				let nonce = *next_nonce;
+				*next_nonce += 1;
+				let call = match rng.below(3) {
+					0 => RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: CONTENT[rng.below(CONTENT.len())].to_string(),
+						content_type: None,
+					}),
+					1 => RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+						claim: CONTENT[rng.below(CONTENT.len())].to_string(),
+					}),
+					_ => RuntimeCall::balances(balances::Call::transfer {
+						to: accounts[rng.below(accounts.len())].clone(),
+						amount: types::Balance(rng.below(100) as u128),
+					}),
+				};
+				support::Extrinsic::new(caller, call, nonce)
+			})
+			.collect();
+
+		next_block(runtime, extrinsics)
+	}
+
+	#[test]
+	fn generated_blocks_conserve_issuance() {
+		let accounts = ["alice", "bob", "charlie"].map(String::from);
+		for seed in 0..20 {
+			let mut runtime = Runtime::build_genesis(GenesisConfig {
+				balances: accounts.iter().map(|who| (who.clone(), types::Balance(100))).collect(),
+				..Default::default()
+			})
+			.unwrap();
+
+			for i in 0..50 {
+				let block = gen_block(&runtime, &accounts, seed * 1000 + i);
+				runtime.execute_block(block).unwrap();
+
+				// Only block rewards create new tokens, while claim deposits are only reserved.
+				let free: types::Balance =
+					runtime.balances.iter_balances().map(|(_, amount)| amount).sum();
+				let total = free + runtime.balances.reserved_total();
+				let expected = types::Balance(300) + BLOCK_REWARD * types::Balance(i as u128 + 1);
+				assert_eq!(total, expected);
+				assert_eq!(runtime.balances.total_issuance(), expected);
+			}
+		}
+	}
+
+	#[test]
+	fn simulate_does_not_mutate_state() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+
+		let call = RuntimeCall::balances(balances::Call::transfer {
+			to: bob.clone(),
+			amount: types::Balance(150),
+		});
+		assert_eq!(runtime.simulate(alice.clone(), call), Err("Not enough funds!"));
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100));
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(0));
+
+		let call = RuntimeCall::balances(balances::Call::transfer {
+			to: bob.clone(),
+			amount: types::Balance(50),
+		});
+		assert_eq!(runtime.simulate(alice.clone(), call), Ok(()));
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100));
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(0));
+	}
+
+	#[test]
+	fn display_is_human_readable() {
+		let mut runtime = Runtime::new();
+		runtime
+			.balances
+			.set_balances(vec![
+				("alice".to_string(), types::Balance(12345)),
+				("bob".to_string(), types::Balance(7)),
+			])
+			.unwrap();
+		runtime
+			.proof_of_existence
+			.create_claim("bob".to_string(), "b".to_string(), None)
+			.unwrap();
+		runtime
+			.proof_of_existence
+			.create_claim("alice".to_string(), "a".to_string(), None)
+			.unwrap();
+		runtime
+			.proof_of_existence
+			.create_claim("bob".to_string(), "c".to_string(), None)
+			.unwrap();
+
+		let display = runtime.to_string();
+		assert!(display.contains("Block number: 0"));
+		assert!(display.contains("alice: 123.45"));
+		assert!(display.contains("bob: 0.07"));
+		assert!(display.contains("alice: [\"a\"]"));
+		assert!(display.contains("bob: [\"b\", \"c\"]"));
+	}
+
+	#[test]
+	fn report() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(alice.clone(), types::Balance(12345)).unwrap();
+		runtime.balances.reserve(&alice, types::Balance(30)).unwrap();
+		runtime
+			.proof_of_existence
+			.create_claim(bob.clone(), "b".to_string(), None)
+			.unwrap();
+		let extrinsic = support::Extrinsic::new(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::transfer {
+				to: bob.clone(),
+				amount: types::Balance(7),
+			}),
+			0,
+		);
+		runtime.execute_block(next_block(&runtime, vec![extrinsic])).unwrap();
+
+		let report = runtime.report();
+		for header in ["== Block ==", "== Balances ==", "== Claims ==", "== Recent events =="] {
+			assert!(report.contains(header), "missing {header}");
+		}
+		assert!(report.contains("Number: 1"));
+		let alice_balance = format_balance(runtime.balances.balance(&alice));
+		assert!(report.contains(&format!("alice: {} (0.30 reserved)", alice_balance)));
+		assert!(report.contains("bob: 0.07"));
+		assert!(report.contains("bob: [\"b\"]"));
+		assert!(report.contains("Transfer"));
+	}
+
+	#[test]
+	fn execute_block_checks_parent_hash() {
+		let mut runtime = Runtime::new();
+		let block = |block_number, parent_hash| types::Block {
+			header: support::Header { block_number, parent_hash, author: "author".to_string() },
+			extrinsics: vec![],
+		};
+
+		// The first block uses the zero hash as its parent.
+		assert_eq!(runtime.execute_block(block(1, [1; 32])), Err("Parent hash mismatch"));
+		assert_eq!(runtime.system.block_number(), 0);
+		assert_eq!(runtime.execute_block(block(1, [0; 32])), Ok(()));
+
+		let block_1_hash = runtime.system.block_hash(1).unwrap();
+		assert_ne!(block_1_hash, [0; 32]);
+		assert_eq!(runtime.execute_block(block(2, [0; 32])), Err("Parent hash mismatch"));
+		assert_eq!(runtime.execute_block(block(2, block_1_hash)), Ok(()));
+		assert_eq!(runtime.system.block_number(), 2);
+	}
+
+	#[test]
+	fn execute_block_deposits_extrinsic_events() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+
+		let transfer = |amount, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount }),
+				nonce,
+			)
+		};
+		let block = types::Block {
+			header: support::Header {
+				block_number: 1,
+				parent_hash: [0; 32],
+				author: "author".to_string(),
+			},
+			extrinsics: vec![transfer(types::Balance(60), 0), transfer(types::Balance(60), 1)],
+		};
+		runtime.execute_block(block).unwrap();
+
+		assert_eq!(
+			runtime.events_filtered(|event| matches!(event, RuntimeEvent::system(_))),
+			vec![
+				&RuntimeEvent::system(system::Event::ExtrinsicSuccess { index: 0 }),
+				&RuntimeEvent::system(system::Event::ExtrinsicFailed {
+					index: 1,
+					error: "Not enough funds!"
+				}),
+			]
+		);
+	}
+
+	#[test]
+	fn build_genesis_with_claims() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![
+				(alice.clone(), types::Balance(100)),
+				(bob.clone(), CLAIM_DEPOSIT_PER_BYTE),
+			],
+			claims: vec![("a".to_string(), alice.clone()), ("b".to_string(), bob.clone())],
+			..Default::default()
+		})
+		.unwrap();
+		// The deposits of the claims are reserved from their owners.
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100) - CLAIM_DEPOSIT_PER_BYTE);
+		assert_eq!(runtime.balances.reserved_balance(&alice), CLAIM_DEPOSIT_PER_BYTE);
+		assert_eq!(runtime.proof_of_existence.get_claim(&"a".to_string()), Some(&alice));
+		assert_eq!(runtime.proof_of_existence.get_claim(&"b".to_string()), Some(&bob));
+		// The genesis claims do not deposit events in the first block.
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert!(!runtime
+			.system
+			.events()
+			.iter()
+			.any(|event| matches!(event, RuntimeEvent::proof_of_existence(_))));
+
+		let result = Runtime::build_genesis(GenesisConfig {
+			claims: vec![("a".to_string(), alice.clone()), ("a".to_string(), bob)],
+			..Default::default()
+		});
+		assert_eq!(result.unwrap_err(), "This content is already claimed.");
+
+		let result = Runtime::build_genesis(GenesisConfig {
+			claims: vec![("a".to_string(), alice)],
+			..Default::default()
+		});
+		assert_eq!(result.unwrap_err(), "Not enough funds!");
+	}
+
+	#[test]
+	fn scheduled_calls_are_dispatched() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let transfer = RuntimeCall::balances(balances::Call::transfer {
+			to: bob.clone(),
+			amount: types::Balance(10),
+		});
+		let schedule = |when, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::scheduler(scheduler::Call::schedule {
+					when,
+					call: Box::new(transfer.clone()),
+				}),
+				nonce,
+			)
+		};
+		runtime
+			.execute_block(next_block(&runtime, vec![schedule(3, 0), schedule(3, 1)]))
+			.unwrap();
+		assert_eq!(runtime.scheduler.pending(), vec![(3, 2)]);
+
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(0));
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(20));
+		assert_eq!(runtime.scheduler.pending(), vec![]);
+	}
+
+	#[test]
+	fn scheduled_calls_are_weighed_and_capped() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let transfer = RuntimeCall::balances(balances::Call::transfer {
+			to: bob.clone(),
+			amount: types::Balance(1),
+		});
+		let schedule = RuntimeCall::scheduler(scheduler::Call::schedule {
+			when: 2,
+			call: Box::new(transfer.clone()),
+		});
+		assert_eq!(
+			support::GetWeight::weight(&schedule),
+			5 + support::GetWeight::weight(&transfer)
+		);
+
+		let extrinsics =
+			(0..6).map(|nonce| support::Extrinsic::new(alice.clone(), schedule.clone(), nonce));
+		runtime.execute_block(next_block(&runtime, extrinsics.collect())).unwrap();
+
+		// Only `MAX_SCHEDULED_PER_BLOCK` calls are dispatched in a block, the rest are dispatched
+		// in the next one.
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(5));
+		assert_eq!(runtime.scheduler.pending(), vec![(2, 1)]);
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(6));
+		assert_eq!(runtime.scheduler.pending(), vec![]);
+	}
+
+	#[test]
+	fn canceled_calls_are_not_dispatched() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let schedule = |amount, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::scheduler(scheduler::Call::schedule {
+					when: 5,
+					call: Box::new(RuntimeCall::balances(balances::Call::transfer {
+						to: bob.clone(),
+						amount,
+					})),
+				}),
+				nonce,
+			)
+		};
+		runtime
+			.execute_block(next_block(
+				&runtime,
+				vec![schedule(types::Balance(10), 0), schedule(types::Balance(20), 1)],
+			))
+			.unwrap();
+		assert_eq!(runtime.scheduler.cancel(5, 2), Err("No such scheduled call"));
+		assert_eq!(runtime.scheduler.cancel(4, 0), Err("No such scheduled call"));
+		assert_eq!(runtime.scheduler.cancel(5, 0), Ok(()));
+		assert_eq!(runtime.scheduler.pending(), vec![(5, 1)]);
+
+		while runtime.system.block_number() < 5 {
+			runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		}
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(20));
+		assert_eq!(runtime.scheduler.pending(), vec![]);
+	}
+
+	#[test]
+	fn block_author_is_rewarded() {
+		let mut runtime = Runtime::new();
+		let author = "author".to_string();
+		for _ in 0..3 {
+			runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		}
+		assert_eq!(runtime.balances.balance(&author), types::Balance(3) * BLOCK_REWARD);
+		assert_eq!(runtime.balances.total_issuance(), types::Balance(3) * BLOCK_REWARD);
+	}
+
+	#[test]
+	fn runtime_upgrade() {
+		let mut runtime = Runtime::new();
+		assert_eq!(runtime.system.spec_version(), 0);
+
+		// The first block executed by this binary upgrades the state to its version.
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.system.spec_version(), Runtime::runtime_version());
+
+		// Simulate an upgrade to the next version, with a migration.
+		let next_version = Runtime::runtime_version() + 1;
+		let alice = "alice".to_string();
+		let migrate = |runtime: &mut Runtime| {
+			runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap()
+		};
+		assert!(runtime.upgrade_to(next_version, migrate));
+		assert_eq!(runtime.system.spec_version(), next_version);
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100));
+
+		// The migration only runs once.
+		let migrate = |runtime: &mut Runtime| {
+			runtime.balances.set_balance(alice.clone(), types::Balance(0)).unwrap()
+		};
+		assert!(!runtime.upgrade_to(next_version, migrate));
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100));
+
+		// Executing blocks with an older binary never downgrades the state.
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.system.spec_version(), next_version);
+	}
+
+	#[test]
+	fn code_hash() {
+		// The code hash only depends on compile-time constants.
+		assert_eq!(Runtime::code_hash(), Runtime::code_hash());
+		assert_ne!(Runtime::code_hash(), [0; 32]);
+
+		let mut runtime = Runtime::new();
+		assert_eq!(runtime.system.code_hash(), [0; 32]);
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.system.code_hash(), Runtime::code_hash());
+
+		// A state produced by a different binary is taken over by this one.
+		runtime.system.set_code_hash([1; 32]);
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.system.code_hash(), Runtime::code_hash());
+	}
+
+	#[test]
+	fn halt_and_resume() {
+		let alice = "alice".to_string();
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+
+		// Only the sudo account can halt and resume the chain.
+		let sudo = SUDO.to_string();
+		assert_eq!(runtime.halt(&alice), Err("Not the sudo account"));
+		assert!(!runtime.system.is_halted());
+		assert_eq!(runtime.halt(&sudo), Ok(()));
+		assert_eq!(runtime.execute_block(next_block(&runtime, vec![])), Err("Chain halted"));
+		assert_eq!(runtime.system.block_number(), 0);
+		// The state can still be queried.
+		assert_eq!(runtime.view().balance(&alice), types::Balance(100));
+
+		assert_eq!(runtime.resume(&alice), Err("Not the sudo account"));
+		assert!(runtime.system.is_halted());
+		assert_eq!(runtime.resume(&sudo), Ok(()));
+		assert_eq!(runtime.execute_block(next_block(&runtime, vec![])), Ok(()));
+		assert_eq!(runtime.system.block_number(), 1);
+	}
+
+	#[test]
+	fn overweight_blocks() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		// Each transfer weighs 10, so only 10 of them fit in a block.
+		let transfer = |nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer {
+					to: bob.clone(),
+					amount: types::Balance(1),
+				}),
+				nonce,
+			)
+		};
+		let block = next_block(&runtime, (0..12).map(transfer).collect());
+
+		assert_eq!(runtime.execute_block(block.clone()), Err("Block weight limit exceeded"));
+		assert_eq!(runtime.system.block_number(), 0);
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(0));
+
+		let applied = runtime.execute_block_with_mode(block, support::OverweightMode::Partial);
+		assert_eq!(applied, Ok(10));
+		assert_eq!(runtime.system.block_number(), 1);
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(10));
+
+		// The nonces are still checked when only part of the block is executed.
+		let block = next_block(&runtime, vec![transfer(11)]);
+		let applied = runtime.execute_block_with_mode(block, support::OverweightMode::Partial);
+		assert_eq!(applied, Err("Invalid nonce"));
+		assert_eq!(runtime.system.block_number(), 1);
+	}
+
+	#[test]
+	fn extrinsics_are_ordered_by_tip() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![
+				(alice.clone(), types::Balance(100)),
+				(bob.clone(), types::Balance(100)),
+			],
+			..Default::default()
+		})
+		.unwrap();
+
+		let claim = |caller: &types::AccountId, tip| support::Extrinsic {
+			caller: caller.clone(),
+			call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello, world!".to_string(),
+				content_type: None,
+			}),
+			nonce: 0,
+			tip,
+			valid_from: None,
+			valid_until: None,
+		};
+		let block = next_block(
+			&runtime,
+			vec![claim(&alice, types::Balance(1)), claim(&bob, types::Balance(2))],
+		);
+		runtime.execute_block(block).unwrap();
+
+		// Bob offered the higher tip, so his claim is executed first.
+		assert_eq!(runtime.proof_of_existence.get_claim(&"Hello, world!".to_string()), Some(&bob));
+	}
+
+	#[test]
+	fn tips_keep_the_nonce_order_of_each_caller() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let transfer = |amount, nonce, tip| support::Extrinsic {
+			tip,
+			..support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount }),
+				nonce,
+			)
+		};
+		// The later extrinsic offers the higher tip, but cannot run before the earlier one.
+		let block = next_block(
+			&runtime,
+			vec![
+				transfer(types::Balance(10), 0, types::Balance(1)),
+				transfer(types::Balance(20), 1, types::Balance(5)),
+			],
+		);
+		assert_eq!(runtime.pre_validate_block(&block), Ok(()));
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(30));
+		assert_eq!(runtime.system.next_nonce(&alice), 2);
+	}
+
+	#[test]
+	fn events_filtered() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let extrinsic = |nonce, call| support::Extrinsic::new(alice.clone(), call, nonce);
+		let transfer =
+			|amount| RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount });
+		let claim = |claim: &str| {
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: claim.to_string(),
+				content_type: None,
+			})
+		};
+		let block = next_block(
+			&runtime,
+			vec![
+				extrinsic(0, transfer(types::Balance(10))),
+				extrinsic(1, claim("a")),
+				extrinsic(2, transfer(types::Balance(20))),
+				// This one fails, so it does not emit any transfer event.
+				extrinsic(3, transfer(types::Balance(100))),
+				extrinsic(4, claim("b")),
+			],
+		);
+		runtime.execute_block(block).unwrap();
+
+		let transfer_event = |amount| {
+			RuntimeEvent::balances(balances::Event::Transfer {
+				from: alice.clone(),
+				to: bob.clone(),
+				amount,
+			})
+		};
+		assert_eq!(
+			runtime.events_filtered(|event| matches!(event, RuntimeEvent::balances(_))),
+			vec![&transfer_event(types::Balance(10)), &transfer_event(types::Balance(20))]
+		);
+		assert_eq!(
+			runtime.events_filtered(|event| matches!(event, RuntimeEvent::proof_of_existence(_))),
+			vec![
+				&RuntimeEvent::proof_of_existence(proof_of_existence::Event::Created {
+					owner: alice.clone(),
+					claim: "a".to_string()
+				}),
+				&RuntimeEvent::proof_of_existence(proof_of_existence::Event::Created {
+					owner: alice.clone(),
+					claim: "b".to_string()
+				}),
+			]
+		);
+	}
+
+	#[test]
+	fn pre_validate_block() {
+		let alice = "alice".to_string();
+		let mut runtime = Runtime::new();
+		let claim = |claim: &str, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+					claim: claim.to_string(),
+					content_type: None,
+				}),
+				nonce,
+			)
+		};
+		let valid = next_block(&runtime, vec![claim("a", 0), claim("b", 1)]);
+		assert_eq!(runtime.pre_validate_block(&valid), Ok(()));
+
+		let mut bad_number = valid.clone();
+		bad_number.header.block_number = 2;
+		assert_eq!(
+			runtime.pre_validate_block(&bad_number),
+			Err("block number does not match what is expected")
+		);
+
+		let mut bad_parent = valid.clone();
+		bad_parent.header.parent_hash = [1; 32];
+		assert_eq!(runtime.pre_validate_block(&bad_parent), Err("Parent hash mismatch"));
+
+		let bad_nonce = next_block(&runtime, vec![claim("a", 0), claim("b", 0)]);
+		assert_eq!(runtime.pre_validate_block(&bad_nonce), Err("Invalid nonce"));
+
+		// Each claim weighs 10, so 11 of them are too heavy for a block.
+		let too_heavy = next_block(&runtime, (0..11).map(|nonce| claim("a", nonce)).collect());
+		assert_eq!(runtime.pre_validate_block(&too_heavy), Err("Block weight limit exceeded"));
+
+		// None of the checks mutate the runtime, and execution runs them too.
+		assert_eq!(runtime.execute_block(bad_nonce), Err("Invalid nonce"));
+		assert_eq!(runtime.system.block_number(), 0);
+		assert_eq!(runtime.execute_block(valid), Ok(()));
+		assert_eq!(runtime.system.next_nonce(&alice), 2);
+	}
+
+	#[test]
+	fn recent_dispatches() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let claim = |claim: &str, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+					claim: claim.to_string(),
+					content_type: None,
+				}),
+				nonce,
+			)
+		};
+
+		// Each block claims "a" to "d", which only succeeds in the first block.
+		for block in 0..3 {
+			let extrinsics = ["a", "b", "c", "d"]
+				.into_iter()
+				.enumerate()
+				.map(|(i, content)| claim(content, block * 4 + i as u32))
+				.collect();
+			runtime.execute_block(next_block(&runtime, extrinsics)).unwrap();
+		}
+
+		// Only the 8 most recent dispatches are kept, oldest first.
+		let log: Vec<_> = runtime
+			.recent_dispatches()
+			.iter()
+			.map(|record| (record.block, record.extrinsic_index, record.result))
+			.collect();
+		let already_claimed = Err("This content is already claimed.");
+		assert_eq!(
+			log,
+			vec![
+				(2, 0, already_claimed),
+				(2, 1, already_claimed),
+				(2, 2, already_claimed),
+				(2, 3, already_claimed),
+				(3, 0, already_claimed),
+				(3, 1, already_claimed),
+				(3, 2, already_claimed),
+				(3, 3, already_claimed),
+			]
+		);
+		assert!(runtime.recent_dispatches().iter().all(|record| record.caller == alice));
+	}
+
+	#[test]
+	fn remark() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let remark = support::Extrinsic::new(
+			alice.clone(),
+			RuntimeCall::system(system::Call::remark { message: b"Hello, world!".to_vec() }),
+			0,
+		);
+		runtime.execute_block(next_block(&runtime, vec![remark])).unwrap();
+
+		assert_eq!(
+			runtime.system.events(),
+			&[
+				RuntimeEvent::system(system::Event::Remarked {
+					who: alice,
+					message: b"Hello, world!".to_vec()
+				}),
+				RuntimeEvent::system(system::Event::ExtrinsicSuccess { index: 0 }),
+			]
+		);
+	}
+
+	#[test]
+	fn replay_block() {
+		let accounts = ["alice".to_string(), "bob".to_string(), "charlie".to_string()];
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: accounts.iter().map(|who| (who.clone(), types::Balance(100))).collect(),
+			..Default::default()
+		})
+		.unwrap();
+		runtime.execute_block(gen_block(&runtime, &accounts, 1)).unwrap();
+
+		let pre_state = runtime.clone();
+		let block = gen_block(&runtime, &accounts, 2);
+		runtime.execute_block(block.clone()).unwrap();
+
+		let replayed = Runtime::replay_block(&pre_state, &block).unwrap();
+		assert_eq!(replayed.state_root(), runtime.state_root());
+		assert_ne!(pre_state.state_root(), runtime.state_root());
+		assert_eq!(replayed, runtime);
+
+		// A block can only be replayed on the state it was built on.
+		assert_eq!(
+			Runtime::replay_block(&runtime, &block),
+			Err("block number does not match what is expected")
+		);
+	}
+
+	#[test]
+	fn fees_are_refunded_on_failure() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let transfer = |to: &types::AccountId, amount, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer { to: to.clone(), amount }),
+				nonce,
+			)
+		};
+		let block = next_block(
+			&runtime,
+			vec![transfer(&bob, types::Balance(10), 0), transfer(&bob, types::Balance(1_000), 1)],
+		);
+		runtime.execute_block(block).unwrap();
+
+		// Only the successful transfer pays its fee, to the block author.
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100 - 10) - TRANSACTION_FEE);
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(10));
+		assert_eq!(runtime.balances.balance(&"author".to_string()), BLOCK_REWARD + TRANSACTION_FEE);
+
+		// Once the fee is charged, Bob cannot transfer all his funds, so the fee is refunded.
+		let block = next_block(
+			&runtime,
+			vec![support::Extrinsic::new(
+				bob.clone(),
+				RuntimeCall::balances(balances::Call::transfer {
+					to: alice.clone(),
+					amount: types::Balance(10),
+				}),
+				0,
+			)],
+		);
+		runtime.execute_block(block).unwrap();
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(10));
+		assert_eq!(
+			runtime.recent_dispatches().last().map(|record| record.result),
+			Some(Err("Not enough funds!"))
+		);
+	}
+
+	#[test]
+	fn blocks_with_an_invalid_author_are_rejected() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let pre_state = runtime.clone();
+
+		// The fees could not be paid to an author with an invalid account id.
+		let transfer = support::Extrinsic::new(
+			alice.clone(),
+			RuntimeCall::balances(balances::Call::transfer {
+				to: bob.clone(),
+				amount: types::Balance(10),
+			}),
+			0,
+		);
+		assert_eq!(
+			runtime.seal_block("the author".to_string(), vec![transfer]),
+			Err("Invalid account id character")
+		);
+		assert_eq!(runtime, pre_state);
+	}
+
+	#[test]
+	fn seal_block() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let claim = |claim: &str, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+					claim: claim.to_string(),
+					content_type: None,
+				}),
+				nonce,
+			)
+		};
+
+		let header_1 = runtime.seal_block("author".to_string(), vec![claim("a", 0)]).unwrap();
+		let header_2 = runtime.seal_block("author".to_string(), vec![claim("b", 1)]).unwrap();
+
+		assert_eq!((header_1.block_number, header_2.block_number), (1, 2));
+		assert_eq!(header_1.parent_hash, [0; 32]);
+		let hash_1 = <system::Pallet<Runtime>>::hash(&support::Encode::encode(&header_1));
+		assert_eq!(runtime.system.block_hash(1), Some(hash_1));
+		assert_eq!(header_2.parent_hash, hash_1);
+		assert!(runtime.proof_of_existence.is_claimed(&"b".to_string()));
+	}
+
+	#[test]
+	fn clear_state() {
+		let accounts = ["alice".to_string(), "bob".to_string()];
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: accounts.iter().map(|who| (who.clone(), types::Balance(100))).collect(),
+			claims: vec![("a".to_string(), accounts[0].clone())],
+			..Default::default()
+		})
+		.unwrap();
+		runtime.execute_block(gen_block(&runtime, &accounts, 1)).unwrap();
+
+		runtime.clear_state();
+		assert_eq!(runtime.balances.total_issuance(), types::Balance(0));
+		assert_eq!(runtime.proof_of_existence.iter_claims().count(), 0);
+		assert_eq!(runtime.system.next_nonce(&accounts[0]), 0);
+		assert_eq!(runtime.execute_block(next_block(&runtime, vec![])), Ok(()));
+		assert_eq!(runtime.system.block_number(), 2);
+	}
+
+	#[test]
+	fn validity_window() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+
+		let claim = |claim: &str, nonce, valid_until| support::Extrinsic {
+			caller: alice.clone(),
+			call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: claim.to_string(),
+				content_type: None,
+			}),
+			nonce,
+			tip: types::Balance(0),
+			valid_from: Some(1),
+			valid_until: Some(valid_until),
+		};
+		let block = next_block(&runtime, vec![claim("expired", 0, 1), claim("valid", 1, 2)]);
+		runtime.execute_block(block).unwrap();
+
+		assert!(!runtime.proof_of_existence.is_claimed(&"expired".to_string()));
+		assert!(runtime.proof_of_existence.is_claimed(&"valid".to_string()));
+		assert_eq!(
+			runtime.recent_dispatches()[0].result,
+			Err("Extrinsic is outside of its validity window")
+		);
+		// No fee is charged for the skipped extrinsic.
+		assert_eq!(runtime.total_holdings(&alice), types::Balance(100) - TRANSACTION_FEE);
+	}
+
+	#[test]
+	fn total_holdings() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		runtime.balances.reserve(&alice, types::Balance(30)).unwrap();
+
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(70));
+		assert_eq!(runtime.total_holdings(&alice), types::Balance(100));
+		assert_eq!(runtime.total_holdings(&"bob".to_string()), types::Balance(0));
+	}
+
+	#[test]
+	fn genesis_hash() {
+		let genesis = |alice_balance| {
+			Runtime::build_genesis(GenesisConfig {
+				balances: vec![
+					("alice".to_string(), alice_balance),
+					("bob".to_string(), types::Balance(50)),
+				],
+				claims: vec![("Hello, world!".to_string(), "bob".to_string())],
+				..Default::default()
+			})
+			.unwrap()
+		};
+
+		assert_eq!(
+			genesis(types::Balance(100)).genesis_hash(),
+			genesis(types::Balance(100)).genesis_hash()
+		);
+		assert_ne!(
+			genesis(types::Balance(100)).genesis_hash(),
+			genesis(types::Balance(101)).genesis_hash()
+		);
+		assert_ne!(genesis(types::Balance(100)).genesis_hash(), [0; 32]);
+
+		// Executing blocks does not change the genesis hash.
+		let mut runtime = genesis(types::Balance(100));
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.genesis_hash(), genesis(types::Balance(100)).genesis_hash());
+	}
+
+	#[test]
+	fn fee_split() {
+		let split = |remainder| FeeSplit { burn_percent: 80, remainder };
+		let amounts = |burn, treasury, author| {
+			(types::Balance(burn), types::Balance(treasury), types::Balance(author))
+		};
+		assert_eq!(split(FeeRemainder::Treasury).split(types::Balance(10)), amounts(8, 2, 0));
+		// 80% of 7 is 5.6 and 20% is 1.4, so 1 is left after rounding down both.
+		assert_eq!(split(FeeRemainder::Treasury).split(types::Balance(7)), amounts(5, 2, 0));
+		assert_eq!(split(FeeRemainder::Burn).split(types::Balance(7)), amounts(6, 1, 0));
+		assert_eq!(split(FeeRemainder::Author).split(types::Balance(7)), amounts(5, 1, 1));
+		assert_eq!(split(FeeRemainder::Treasury).split(types::Balance(1)), amounts(0, 1, 0));
+		assert_eq!(
+			FeeSplit { burn_percent: 200, remainder: FeeRemainder::Author }
+				.split(types::Balance(7)),
+			amounts(7, 0, 0)
+		);
+		// The largest fee is split without overflowing.
+		let fee = types::Balance::MAX;
+		let (burn, treasury, author) = split(FeeRemainder::Author).split(fee);
+		assert_eq!(burn, types::Balance(u128::MAX / 100 * 80 + u128::MAX % 100 * 80 / 100));
+		assert_eq!(burn + treasury + author, fee);
+
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let claim = support::Extrinsic::new(
+			alice.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello, world!".to_string(),
+				content_type: None,
+			}),
+			0,
+		);
+		let issuance = runtime.balances.total_issuance();
+		runtime.execute_block(next_block(&runtime, vec![claim])).unwrap();
+
+		// A fee of 1 is too small to burn or to share with the treasury, so the whole fee is left
+		// to the block author.
+		assert_eq!(
+			FEE_SPLIT.split(TRANSACTION_FEE),
+			(types::Balance(0), types::Balance(0), types::Balance(1))
+		);
+		assert_eq!(runtime.total_holdings(&alice), types::Balance(99));
+		assert_eq!(runtime.balances.balance(&TREASURY.to_string()), types::Balance(0));
+		assert_eq!(
+			runtime.balances.balance(&"author".to_string()),
+			BLOCK_REWARD + types::Balance(1)
+		);
+		assert_eq!(runtime.balances.total_issuance(), issuance + BLOCK_REWARD);
+	}
+
+	#[test]
+	fn encode_and_decode_block() {
+		let alice = "alice".to_string();
+		let runtime = Runtime::new();
+		let extrinsic = |call, nonce| support::Extrinsic {
+			caller: alice.clone(),
+			call,
+			nonce,
+			tip: types::Balance(7),
+			valid_from: None,
+			valid_until: Some(10),
+		};
+		let block = next_block(
+			&runtime,
+			vec![
+				extrinsic(
+					RuntimeCall::balances(balances::Call::transfer {
+						to: "bob".to_string(),
+						amount: types::Balance(42),
+					}),
+					0,
+				),
+				extrinsic(
+					RuntimeCall::scheduler(scheduler::Call::schedule {
+						when: 5,
+						call: Box::new(RuntimeCall::proof_of_existence(
+							proof_of_existence::Call::create_claims {
+								claims: vec!["a".to_string(), "b".to_string()],
+							},
+						)),
+					}),
+					1,
+				),
+				extrinsic(RuntimeCall::system(system::Call::remark { message: vec![1, 2, 3] }), 2),
+			],
+		);
+
+		let bytes = encode_block(&block);
+		assert_eq!(decode_block(&bytes), Ok(block));
+
+		assert!(decode_block(&bytes[..bytes.len() - 1]).is_err());
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		assert_eq!(
+			decode_block(&trailing),
+			Err("1 bytes left after decoding the block".to_string())
+		);
+	}
+
+	#[test]
+	fn tx_pool_max_future_nonce() {
+		let runtime = Runtime::new();
+		let alice = "alice".to_string();
+		let extrinsic = |nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::system(system::Call::remark { message: vec![] }),
+				nonce,
+			)
+		};
+		let mut pool = TxPool::default();
+
+		assert_eq!(pool.submit(&runtime, extrinsic(TxPool::MAX_FUTURE_NONCE)), Ok(()));
+		assert_eq!(
+			pool.submit(&runtime, extrinsic(TxPool::MAX_FUTURE_NONCE + 1)),
+			Err("Nonce too far in future")
+		);
+		assert_eq!(pool.pending(), &[extrinsic(TxPool::MAX_FUTURE_NONCE)]);
+		assert_eq!(pool.take().len(), 1);
+		assert!(pool.pending().is_empty());
+	}
+
+	#[test]
+	fn chain_stats() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		assert_eq!(
+			runtime.stats(),
+			ChainStats {
+				total_extrinsics: 0,
+				total_fees: types::Balance(0),
+				average_extrinsics_per_block: 0.0
+			}
+		);
+
+		let mut nonce = 0;
+		let mut claim = |content: &str| {
+			nonce += 1;
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+					claim: content.to_string(),
+					content_type: None,
+				}),
+				nonce - 1,
+			)
+		};
+		let blocks = vec![
+			vec![claim("a")],
+			// Claiming the same content again fails, so its fee is refunded.
+			vec![claim("b"), claim("c"), claim("b")],
+			vec![claim("d"), claim("e")],
+		];
+		for extrinsics in blocks {
+			runtime.seal_block("author".to_string(), extrinsics).unwrap();
+		}
+
+		assert_eq!(
+			runtime.stats(),
+			ChainStats {
+				total_extrinsics: 6,
+				total_fees: types::Balance(5) * TRANSACTION_FEE,
+				average_extrinsics_per_block: 2.0,
+			}
+		);
+	}
+
+	#[test]
+	fn active_block_count() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let remark = |nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::system(system::Call::remark { message: vec![] }),
+				nonce,
+			)
+		};
+
+		runtime.seal_block("author".to_string(), vec![remark(0)]).unwrap();
+		runtime.seal_block("author".to_string(), vec![]).unwrap();
+		runtime.seal_block("author".to_string(), vec![remark(1), remark(2)]).unwrap();
+
+		assert_eq!(runtime.system.active_block_count(&alice), 2);
+		assert_eq!(runtime.system.active_block_count(&"bob".to_string()), 0);
+	}
+
+	#[test]
+	fn pallet_events_are_collected_in_dispatch_order() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		let extrinsic = |nonce, call| support::Extrinsic::new(alice.clone(), call, nonce);
+		let block = next_block(
+			&runtime,
+			vec![
+				extrinsic(
+					0,
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: "a".to_string(),
+						content_type: None,
+					}),
+				),
+				extrinsic(
+					1,
+					RuntimeCall::balances(balances::Call::transfer {
+						to: bob.clone(),
+						amount: types::Balance(10),
+					}),
+				),
+				extrinsic(
+					2,
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: "b".to_string(),
+						content_type: None,
+					}),
+				),
+			],
+		);
+		runtime.execute_block(block).unwrap();
+
+		assert_eq!(
+			runtime.events_filtered(|event| !matches!(event, RuntimeEvent::system(_))),
+			vec![
+				&RuntimeEvent::proof_of_existence(proof_of_existence::Event::Created {
+					owner: alice.clone(),
+					claim: "a".to_string()
+				}),
+				&RuntimeEvent::balances(balances::Event::Transfer {
+					from: alice.clone(),
+					to: bob.clone(),
+					amount: types::Balance(10),
+				}),
+				&RuntimeEvent::proof_of_existence(proof_of_existence::Event::Created {
+					owner: alice.clone(),
+					claim: "b".to_string()
+				}),
+			]
+		);
+
+		// The events of any pallet can also be deposited in the runtime directly.
+		let event = balances::Event::Transfer {
+			from: bob.clone(),
+			to: alice.clone(),
+			amount: types::Balance(1),
+		};
+		runtime.deposit_event(event.clone());
+		assert_eq!(runtime.system.events().last(), Some(&RuntimeEvent::balances(event)));
+	}
+
+	#[test]
+	fn verify_chain() {
+		let alice = "alice".to_string();
+		let genesis = || GenesisConfig {
+			balances: vec![(alice.clone(), types::Balance(100))],
+			..Default::default()
+		};
+		let remark = |nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::system(system::Call::remark { message: vec![] }),
+				nonce,
+			)
+		};
+
+		let mut runtime = Runtime::build_genesis(genesis()).unwrap();
+		let mut blocks = Vec::new();
+		for extrinsics in [vec![remark(0)], vec![], vec![remark(1), remark(2)], vec![remark(3)]] {
+			let block = next_block(&runtime, extrinsics);
+			runtime.execute_block(block.clone()).unwrap();
+			blocks.push(block);
+		}
+		assert_eq!(Runtime::verify_chain(genesis(), &blocks), Ok(()));
+
+		let mut broken = blocks.clone();
+		broken[2].header.parent_hash = [0; 32];
+		assert_eq!(
+			Runtime::verify_chain(genesis(), &broken),
+			Err((Some(2), "Parent hash mismatch"))
+		);
+
+		let mut replayed = blocks.clone();
+		replayed[3].extrinsics[0].nonce = 0;
+		assert_eq!(Runtime::verify_chain(genesis(), &replayed), Err((Some(3), "Invalid nonce")));
+
+		// Blocks cannot be skipped.
+		assert_eq!(
+			Runtime::verify_chain(genesis(), &blocks[1..]),
+			Err((Some(0), "block number does not match what is expected"))
+		);
+
+		// An invalid genesis config is not blamed on the first block.
+		let invalid = GenesisConfig {
+			balances: vec![(String::new(), types::Balance(100))],
+			..Default::default()
+		};
+		assert_eq!(
+			Runtime::verify_chain(invalid, &blocks),
+			Err((None, "Invalid account id length"))
+		);
+	}
+
+	#[test]
+	fn min_balance_to_claim() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		// Bob can pay the fee, but is left with less than the minimum balance to claim.
+		let min_balance = <Runtime as proof_of_existence::Config>::MIN_BALANCE_TO_CLAIM;
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![
+				(alice.clone(), types::Balance(100)),
+				(bob.clone(), min_balance + TRANSACTION_FEE - types::Balance(1)),
+			],
+			..Default::default()
+		})
+		.unwrap();
+		let claim = |caller: &types::AccountId, claim: &str| {
+			support::Extrinsic::new(
+				caller.clone(),
+				RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+					claim: claim.to_string(),
+					content_type: None,
+				}),
+				0,
+			)
+		};
+		runtime
+			.seal_block("author".to_string(), vec![claim(&bob, "b"), claim(&alice, "a")])
+			.unwrap();
+
+		let results: Vec<_> = runtime
+			.recent_dispatches()
+			.iter()
+			.map(|r| (r.caller.clone(), r.result))
+			.collect();
+		assert_eq!(
+			results,
+			vec![(bob, Err("Insufficient balance to claim")), (alice.clone(), Ok(()))]
+		);
+		assert_eq!(runtime.proof_of_existence.get_claim(&"a".to_string()), Some(&alice));
+		assert_eq!(runtime.proof_of_existence.get_claim(&"b".to_string()), None);
+	}
+
+	#[test]
+	fn state_guard() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let pre_state = runtime.clone();
+
+		{
+			let mut guard = StateGuard::new(&mut runtime);
+			guard.balances.mint(&alice, types::Balance(50)).unwrap();
+			guard.seal_block("author".to_string(), vec![]).unwrap();
+			assert_eq!(guard.balances.balance(&alice), types::Balance(150));
+		}
+		assert_eq!(runtime, pre_state);
+
+		let mut guard = StateGuard::new(&mut runtime);
+		guard.balances.mint(&alice, types::Balance(50)).unwrap();
+		guard.commit();
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(150));
+	}
+
+	#[test]
+	fn blacklisted_accounts_cannot_dispatch() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let sudo = SUDO.to_string();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![
+				(alice.clone(), types::Balance(100)),
+				(bob.clone(), types::Balance(100)),
+				(sudo.clone(), types::Balance(100)),
+			],
+			..Default::default()
+		})
+		.unwrap();
+		let blacklist = |who: &types::AccountId| {
+			RuntimeCall::system(system::Call::add_to_blacklist { who: who.clone() })
+		};
+
+		let extrinsic = |caller: &types::AccountId, nonce, call| {
+			support::Extrinsic::new(caller.clone(), call, nonce)
+		};
+		let remark = || RuntimeCall::system(system::Call::remark { message: vec![] });
+		let transfer = |to: &types::AccountId| {
+			RuntimeCall::balances(balances::Call::transfer {
+				to: to.clone(),
+				amount: types::Balance(10),
+			})
+		};
+		runtime
+			.seal_block(
+				"author".to_string(),
+				vec![
+					// Only the sudo account can blacklist accounts.
+					extrinsic(&alice, 0, blacklist(&bob)),
+					extrinsic(&sudo, 0, blacklist(&bob)),
+					extrinsic(&bob, 0, remark()),
+					extrinsic(&bob, 1, transfer(&alice)),
+					extrinsic(&alice, 1, transfer(&bob)),
+				],
+			)
+			.unwrap();
+
+		let results: Vec<_> = runtime
+			.recent_dispatches()
+			.iter()
+			.map(|r| (r.caller.clone(), r.result))
+			.collect();
+		assert_eq!(
+			results,
+			vec![
+				(alice.clone(), Err("Not the sudo account")),
+				(sudo.clone(), Ok(())),
+				(bob.clone(), Err("Account blacklisted")),
+				(bob.clone(), Err("Account blacklisted")),
+				(alice.clone(), Ok(())),
+			]
+		);
+		assert_eq!(runtime.simulate(bob.clone(), remark()), Err("Account blacklisted"));
+
+		let unblacklist =
+			RuntimeCall::system(system::Call::remove_from_blacklist { who: bob.clone() });
+		runtime
+			.seal_block("author".to_string(), vec![extrinsic(&sudo, 1, unblacklist)])
+			.unwrap();
+		assert_eq!(runtime.simulate(bob.clone(), remark()), Ok(()));
+	}
+
+	#[test]
+	fn merge() {
+		let genesis = |balances: &[(&str, types::Balance)], claims: &[(&str, &str)]| {
+			Runtime::build_genesis(GenesisConfig {
+				balances: balances.iter().map(|(who, b)| (who.to_string(), *b)).collect(),
+				claims: claims
+					.iter()
+					.map(|(c, owner)| (c.to_string(), owner.to_string()))
+					.collect(),
+				..Default::default()
+			})
+			.unwrap()
+		};
+		let mut runtime = genesis(
+			&[("alice", types::Balance(100)), ("bob", types::Balance(50))],
+			&[("a", "alice"), ("b", "bob")],
+		);
+		let other = genesis(
+			&[("bob", types::Balance(20)), ("charlie", types::Balance(30))],
+			&[("b", "bob"), ("c", "charlie")],
+		);
+
+		assert_eq!(runtime.merge(&other), Ok(()));
+		// Only the free balances are merged, and the deposit of each new claim is reserved again.
+		let deposit = CLAIM_DEPOSIT_PER_BYTE;
+		let balances: Vec<_> =
+			runtime.balances.iter_balances().map(|(who, b)| (who.as_str(), *b)).collect();
+		assert_eq!(
+			balances,
+			vec![
+				("alice", types::Balance(100) - deposit),
+				("bob", types::Balance(70) - types::Balance(2) * deposit),
+				("charlie", types::Balance(30) - types::Balance(2) * deposit)
+			]
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"charlie".to_string()), deposit);
+		assert_eq!(
+			runtime.balances.total_issuance(),
+			types::Balance(200) - types::Balance(2) * deposit
+		);
+		let claims: Vec<_> = runtime
+			.proof_of_existence
+			.iter_claims()
+			.map(|(c, owner)| (c.as_str(), owner.as_str()))
+			.collect();
+		assert_eq!(claims, vec![("a", "alice"), ("b", "bob"), ("c", "charlie")]);
+
+		let conflicting = genesis(&[("bob", deposit)], &[("a", "bob")]);
+		let pre_state = runtime.clone();
+		assert_eq!(
+			runtime.merge(&conflicting),
+			Err("Conflicting owners for claim \"a\": alice and bob".to_string())
+		);
+		assert_eq!(runtime, pre_state);
+	}
+
+	/// The kinds of calls which can be measured with `benchmark_call`.
+	#[derive(Debug, Clone, Copy)]
+	enum CallKind {
+		Transfer,
+		Claim,
+	}
+
+	/// How many times `benchmark_call` dispatches a call.
+	const BENCHMARK_RUNS: u32 = 1_000;
+
+	/// Measure the average time it takes to dispatch a call of the given `kind`, to compare the
+	/// relative cost of calls when choosing their weights. Each run dispatches a call which
+	/// succeeds, on a runtime which grows with every run.
+	fn benchmark_call(kind: CallKind) -> std::time::Duration {
+		let alice = "alice".to_string();
+		let mut runtime = Runtime::new();
+		runtime
+			.balances
+			.set_balance(alice.clone(), types::Balance::MAX / types::Balance(2))
+			.unwrap();
+		let calls: Vec<_> = (0..BENCHMARK_RUNS)
+			.map(|i| match kind {
+				CallKind::Transfer => RuntimeCall::balances(balances::Call::transfer {
+					to: format!("account-{i}"),
+					amount: types::Balance(1),
+				}),
+				CallKind::Claim => {
+					RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+						claim: format!("content {i}"),
+						content_type: None,
+					})
+				},
+			})
+			.collect();
+
+		let start = std::time::Instant::now();
+		for call in calls {
+			runtime.dispatch(alice.clone(), call).unwrap();
+		}
+		start.elapsed() / BENCHMARK_RUNS
+	}
+
+	#[test]
+	#[ignore = "slow, and its timings depend on the machine"]
+	fn benchmark_calls() {
+		for kind in [CallKind::Transfer, CallKind::Claim] {
+			assert!(benchmark_call(kind) > std::time::Duration::ZERO, "{kind:?} took no time");
+		}
+	}
+
+	#[test]
+	fn issuance_delta() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+
+		// Block 1 mints the block reward, and burns most of the fee of the remark.
+		let remark = support::Extrinsic::new(
+			alice.clone(),
+			RuntimeCall::system(system::Call::remark { message: vec![] }),
+			0,
+		);
+		runtime.seal_block("author".to_string(), vec![remark]).unwrap();
+		let (burned_fee, _, _) = FEE_SPLIT.split(TRANSACTION_FEE);
+		let block_1 = BLOCK_REWARD.0 as i128 - burned_fee.0 as i128;
+		// Changes made between blocks are only seen by the next snapshot.
+		runtime.balances.withdraw(&alice, types::Balance(50)).unwrap();
+		runtime.seal_block("author".to_string(), vec![]).unwrap();
+		let block_2 = BLOCK_REWARD.0 as i128 - 50;
+
+		assert_eq!(runtime.issuance_delta(0, 1), block_1);
+		assert_eq!(runtime.issuance_delta(1, 2), block_2);
+		assert_eq!(runtime.issuance_delta(0, 2), block_1 + block_2);
+		assert_eq!(runtime.issuance_delta(2, 0), -(block_1 + block_2));
+		assert_eq!(runtime.issuance_delta(2, 2), 0);
+	}
+
+	#[test]
+	fn duplicate_extrinsics_are_skipped() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let transfer = |nonce, amount| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount }),
+				nonce,
+			)
+		};
+		let block = next_block(
+			&runtime,
+			vec![
+				transfer(0, types::Balance(10)),
+				transfer(0, types::Balance(10)),
+				transfer(1, types::Balance(10)),
+			],
+		);
+		assert_eq!(runtime.pre_validate_block(&block), Ok(()));
+		runtime.execute_block(block).unwrap();
+
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(20));
+		assert_eq!(runtime.system.next_nonce(&alice), 2);
+		assert_eq!(runtime.stats().total_extrinsics, 2);
+	}
+
+	#[test]
+	fn balance_proof() {
+		let balances = ["alice", "bob", "charlie"]
+			.iter()
+			.map(|who| (who.to_string(), types::Balance(100)))
+			.collect();
+		let mut runtime =
+			Runtime::build_genesis(GenesisConfig { balances, ..Default::default() }).unwrap();
+		runtime.balances.set_balance("bob".to_string(), types::Balance(42)).unwrap();
+		let root = runtime.state_root();
+
+		let proof = runtime.balance_proof(&"bob".to_string()).unwrap();
+		assert_eq!(proof.balance, types::Balance(42));
+		assert_eq!(proof.root, runtime.balances_root());
+		assert!(verify_balance_proof(&proof, root));
+
+		// A proof of a different balance, or against a different root, does not verify.
+		assert!(!verify_balance_proof(
+			&BalanceProof { balance: types::Balance(100), ..proof.clone() },
+			root
+		));
+		assert!(!verify_balance_proof(&proof, runtime.balances_root()));
+		runtime.balances.set_balance("alice".to_string(), types::Balance(1)).unwrap();
+		assert!(!verify_balance_proof(&proof, runtime.state_root()));
+		// Changing another part of the state changes the state root, but not the balances root.
+		let proof = runtime.balance_proof(&"bob".to_string()).unwrap();
+		runtime.halt(&SUDO.to_string()).unwrap();
+		assert!(!verify_balance_proof(&proof, runtime.state_root()));
+		assert!(verify_balance_proof(
+			&runtime.balance_proof(&"bob".to_string()).unwrap(),
+			runtime.state_root()
+		));
+
+		assert_eq!(runtime.balance_proof(&"dave".to_string()), None);
+	}
+
+	#[test]
+	fn state_root() {
+		let alice = "alice".to_string();
+		let mut runtime = funded_runtime(&alice);
+		let mut roots = vec![runtime.state_root()];
+
+		// A claim changes the claims, and holds a deposit from the reserved balance of its owner.
+		let claim = support::Extrinsic::new(
+			alice.clone(),
+			RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "Hello, world!".to_string(),
+				content_type: None,
+			}),
+			0,
+		);
+		let leaves = runtime.state_leaves();
+		runtime.execute_block(next_block(&runtime, vec![claim])).unwrap();
+		assert_ne!(runtime.state_leaves()[2], leaves[2]);
+		assert_ne!(runtime.state_leaves()[3], leaves[3]);
+		roots.push(runtime.state_root());
+
+		runtime.balances.stake(&alice, types::Balance(10)).unwrap();
+		roots.push(runtime.state_root());
+		runtime.system.set_validators(vec![alice.clone()]);
+		roots.push(runtime.state_root());
+		runtime.halt(&SUDO.to_string()).unwrap();
+		roots.push(runtime.state_root());
+		runtime.resume(&SUDO.to_string()).unwrap();
+		assert_eq!(runtime.state_root(), roots[3]);
+
+		// The state of every other pallet is covered as well.
+		let bob = "bob".to_string();
+		runtime.system.add_to_blacklist(SUDO.to_string(), bob.clone()).unwrap();
+		roots.push(runtime.state_root());
+		runtime.system.set_spec_version(SPEC_VERSION + 1);
+		roots.push(runtime.state_root());
+		runtime.balances.vest(&alice, types::Balance(10), 5);
+		roots.push(runtime.state_root());
+		runtime
+			.proof_of_existence
+			.lock_claim(alice.clone(), "Hello, world!".to_string())
+			.unwrap();
+		roots.push(runtime.state_root());
+		runtime.proof_of_existence.add_delegate(alice.clone(), bob.clone()).unwrap();
+		roots.push(runtime.state_root());
+		let remark = RuntimeCall::system(system::Call::remark { message: vec![] });
+		runtime.scheduler.schedule(alice.clone(), 5, Box::new(remark)).unwrap();
+		roots.push(runtime.state_root());
+		runtime.recovery.create_recovery(alice.clone(), vec![bob.clone()], 1).unwrap();
+		roots.push(runtime.state_root());
+		runtime
+			.treasury
+			.propose_spend(alice.clone(), bob.clone(), types::Balance(10))
+			.unwrap();
+		roots.push(runtime.state_root());
+
+		// Every change led to a different root, and the root is stable otherwise.
+		let mut distinct = roots.clone();
+		distinct.sort();
+		distinct.dedup();
+		assert_eq!(distinct.len(), roots.len());
+		assert_eq!(runtime.state_root(), *roots.last().unwrap());
+	}
+
+	#[test]
+	fn validate_account() {
+		let mut runtime = Runtime::new();
+		let too_long = "a".repeat(MAX_ACCOUNT_ID_LENGTH + 1);
+		assert_eq!(
+			runtime.balances.set_balance(String::new(), types::Balance(100)),
+			Err("Invalid account id length")
+		);
+		assert_eq!(
+			runtime.balances.set_balance(too_long.clone(), types::Balance(100)),
+			Err("Invalid account id length")
+		);
+		assert_eq!(
+			runtime.balances.set_balance("alice smith".to_string(), types::Balance(100)),
+			Err("Invalid account id character")
+		);
+		assert_eq!(runtime.balances.total_issuance(), types::Balance(0));
+
+		let alice = "alice".to_string();
+		assert_eq!(runtime.balances.set_balance(alice.clone(), types::Balance(100)), Ok(()));
+		assert_eq!(
+			runtime
+				.balances
+				.set_balance("a".repeat(MAX_ACCOUNT_ID_LENGTH), types::Balance(1)),
+			Ok(())
+		);
+		assert_eq!(
+			runtime.balances.transfer(alice.clone(), too_long, types::Balance(10)),
+			Err("Invalid account id length")
+		);
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100));
+
+		// Accounts cannot be created with an invalid id by minting or swapping either.
+		assert_eq!(
+			runtime.balances.mint(&String::new(), types::Balance(10)),
+			Err("Invalid account id length")
+		);
+		assert_eq!(
+			runtime.balances.swap(&alice, &"alice smith".to_string()),
+			Err("Invalid account id character")
+		);
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100));
+		assert_eq!(runtime.balances.total_issuance(), types::Balance(101));
+
+		assert_eq!(
+			runtime
+				.proof_of_existence
+				.create_claim(String::new(), "content".to_string(), None),
+			Err("Invalid account id length")
+		);
+		assert_eq!(
+			runtime
+				.proof_of_existence
+				.create_claims(String::new(), vec!["content".to_string()]),
+			Err("Invalid account id length")
+		);
+		assert_eq!(
+			runtime
+				.proof_of_existence
+				.create_claim(alice.clone(), "content".to_string(), None),
+			Ok(())
+		);
+	}
+
+	#[test]
+	fn event_handler() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+
+		let received = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+		let sink = received.clone();
+		runtime.set_event_handler(Box::new(move |event| sink.borrow_mut().push(event.clone())));
+
+		// Simulated dispatches do not stream any event.
+		let call = RuntimeCall::balances(balances::Call::transfer {
+			to: bob.clone(),
+			amount: types::Balance(10),
+		});
+		assert_eq!(runtime.simulate(alice.clone(), call), Ok(()));
+		assert!(received.borrow().is_empty());
+
+		let extrinsic = |call, nonce| support::Extrinsic::new(alice.clone(), call, nonce);
+		let transfer =
+			RuntimeCall::balances(balances::Call::transfer { to: bob, amount: types::Balance(10) });
+		let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+			claim: "content".to_string(),
+			content_type: None,
+		});
+		let block = next_block(&runtime, vec![extrinsic(transfer, 0), extrinsic(claim, 1)]);
+		let mut pre_state = runtime.clone();
+		runtime.execute_block(block.clone()).unwrap();
+
+		// The events were streamed in the order they were deposited.
+		assert!(received.borrow().len() > 2);
+		assert_eq!(received.borrow().as_slice(), runtime.system.events());
+
+		// Replaying the block, or executing one in a guard, does not stream its events again.
+		let streamed = received.borrow().len();
+		Runtime::replay_block(&pre_state, &block).unwrap();
+		{
+			let mut guard = StateGuard::new(&mut pre_state);
+			guard.execute_block(block.clone()).unwrap();
+		}
+		assert_eq!(received.borrow().len(), streamed);
+
+		// A committed guard keeps streaming the events of the runtime once it is gone.
+		let guard = StateGuard::new(&mut runtime);
+		guard.commit();
+		let remark = RuntimeCall::system(system::Call::remark { message: vec![] });
+		runtime.execute_block(next_block(&runtime, vec![extrinsic(remark, 2)])).unwrap();
+		assert!(received.borrow().len() > streamed);
+	}
+
+	#[test]
+	fn block_size_limit() {
+		let mut runtime = Runtime::new();
+		let alice = "alice".to_string();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+
+		// Each extrinsic takes a bit more than half of the maximum block size.
+		let half = <Runtime as system::Config>::MAX_BLOCK_BYTES as usize / 2;
+		let claim = |claim: &str, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+					claim: claim.to_string(),
+					content_type: Some("a".repeat(half)),
+				}),
+				nonce,
+			)
+		};
+
+		let block = next_block(&runtime, vec![claim("first", 0), claim("second", 1)]);
+		assert_eq!(runtime.pre_validate_block(&block), Err("Block size limit exceeded"));
+		assert_eq!(runtime.execute_block(block), Err("Block size limit exceeded"));
+		assert_eq!(runtime.system.block_number(), 0);
+
+		let block = next_block(&runtime, vec![claim("first", 0)]);
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime.proof_of_existence.get_claim(&"first".to_string()), Some(&alice));
+	}
+
+	#[test]
+	fn treasury_spends() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let council = TREASURY_APPROVER.to_string();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![
+				(alice.clone(), types::Balance(100)),
+				(council.clone(), types::Balance(100)),
+				(TREASURY.to_string(), types::Balance(50)),
+			],
+			..Default::default()
+		})
+		.unwrap();
+
+		let extrinsic = |caller: &types::AccountId, call, nonce| {
+			support::Extrinsic::new(caller.clone(), RuntimeCall::treasury(call), nonce)
+		};
+		let block = next_block(
+			&runtime,
+			vec![
+				extrinsic(
+					&alice,
+					treasury::Call::propose_spend {
+						beneficiary: bob.clone(),
+						amount: types::Balance(40),
+					},
+					0,
+				),
+				extrinsic(
+					&alice,
+					treasury::Call::propose_spend {
+						beneficiary: bob.clone(),
+						amount: types::Balance(1_000),
+					},
+					1,
+				),
+				extrinsic(&council, treasury::Call::approve_spend { id: 0 }, 0),
+				extrinsic(&council, treasury::Call::approve_spend { id: 1 }, 1),
+			],
+		);
+		let treasury_before = runtime.balances.balance(&TREASURY.to_string());
+		runtime.execute_block(block).unwrap();
+
+		// The first spend was paid at the end of the block.
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(40));
+		assert_eq!(runtime.treasury.proposal(0), None);
+		// The fees go to the block author, so the treasury only paid the spend.
+		assert_eq!(
+			runtime.balances.balance(&TREASURY.to_string()),
+			treasury_before - types::Balance(40)
+		);
+
+		// The second spend could not be approved, since the treasury cannot pay it.
+		assert!(runtime.system.events().contains(&RuntimeEvent::system(
+			system::Event::ExtrinsicFailed { index: 3, error: "Insufficient treasury funds" }
+		)));
+		assert!(runtime.treasury.proposal(1).is_some());
+	}
+
+	#[test]
+	fn failed_treasury_spends_are_retried() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let council = TREASURY_APPROVER.to_string();
+		let amount = <Runtime as balances::Config>::MAX_TRANSFER_AMOUNT + types::Balance(1);
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![
+				(alice.clone(), types::Balance(100)),
+				(council.clone(), types::Balance(100)),
+				(TREASURY.to_string(), types::Balance(2) * amount),
+			],
+			..Default::default()
+		})
+		.unwrap();
+
+		// The treasury can afford the spend, but it is too large for a single transfer.
+		let extrinsic = |caller: &types::AccountId, call| {
+			support::Extrinsic::new(caller.clone(), RuntimeCall::treasury(call), 0)
+		};
+		let block = next_block(
+			&runtime,
+			vec![
+				extrinsic(
+					&alice,
+					treasury::Call::propose_spend { beneficiary: bob.clone(), amount },
+				),
+				extrinsic(&council, treasury::Call::approve_spend { id: 0 }),
+			],
+		);
+		runtime.execute_block(block).unwrap();
+		assert!(runtime.system.events().contains(&RuntimeEvent::treasury(
+			treasury::Event::SpendFailed { id: 0, error: "Transfer exceeds maximum" }
+		)));
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(0));
+
+		// The spend stays approved, and is retried at the end of each block.
+		for _ in 0..<Runtime as treasury::Config>::MAX_SPEND_RETRIES - 1 {
+			assert_eq!(runtime.treasury.approved_total(), amount);
+			runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+			assert!(runtime.system.events().contains(&RuntimeEvent::treasury(
+				treasury::Event::SpendFailed { id: 0, error: "Transfer exceeds maximum" }
+			)));
+		}
+
+		// After its last retry, the spend is dropped and no longer holds its amount.
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert!(runtime.system.events().contains(&RuntimeEvent::treasury(
+			treasury::Event::SpendDropped { id: 0, error: "Transfer exceeds maximum" }
+		)));
+		assert_eq!(runtime.treasury.approved_total(), types::Balance(0));
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert!(!runtime
+			.system
+			.events()
+			.iter()
+			.any(|event| matches!(event, RuntimeEvent::treasury(_))));
+	}
+
+	#[test]
+	fn build_genesis_with_nonces() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![(alice.clone(), types::Balance(100))],
+			nonces: vec![(alice.clone(), 5), (bob.clone(), 2)],
+			..Default::default()
+		})
+		.unwrap();
+		assert_eq!(runtime.system.next_nonce(&alice), 5);
+		assert_eq!(runtime.system.next_nonce(&bob), 2);
+		assert_eq!(runtime.system.next_nonce(&"charlie".to_string()), 0);
+
+		// Extrinsics continue from the preset nonce.
+		let transfer = |nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer {
+					to: bob.clone(),
+					amount: types::Balance(10),
+				}),
+				nonce,
+			)
+		};
+		let block = next_block(&runtime, vec![transfer(0)]);
+		assert_eq!(runtime.execute_block(block), Err("Invalid nonce"));
+		let block = next_block(&runtime, vec![transfer(5)]);
+		assert_eq!(runtime.execute_block(block), Ok(()));
+		assert_eq!(runtime.system.next_nonce(&alice), 6);
+	}
+
+	#[test]
+	fn runtime_view() {
+		let alice = "alice".to_string();
+		let runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![(alice.clone(), types::Balance(100))],
+			claims: vec![("content".to_string(), alice.clone())],
+			nonces: vec![(alice.clone(), 3)],
+			..Default::default()
+		})
+		.unwrap();
+
+		// Views only need a shared borrow, so several of them and the runtime itself can be read
+		// at the same time. None of their methods take `&mut self`.
+		let view = runtime.view();
+		let copy = view;
+		assert_eq!(view.block_number(), 0);
+		let deposit = CLAIM_DEPOSIT_PER_BYTE * types::Balance("content".len() as u128);
+		assert_eq!(view.balance(&alice), types::Balance(100) - deposit);
+		assert_eq!(copy.reserved_balance(&alice), deposit);
+		assert_eq!(view.total_issuance(), runtime.balances.total_issuance());
+		assert_eq!(view.claim_owner(&"content".to_string()), Some(&alice));
+		assert_eq!(view.iter_claims().collect::<Vec<_>>(), vec![(&"content".to_string(), &alice)]);
+		assert_eq!(copy.next_nonce(&alice), 3);
+		assert!(view.events().is_empty());
+		assert_eq!(view.state_root(), runtime.state_root());
+	}
+
+	#[test]
+	fn select_author() {
+		let validators: Vec<_> = ["alice", "bob", "charlie"].map(String::from).to_vec();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: validators.iter().map(|who| (who.clone(), types::Balance(100))).collect(),
+			..Default::default()
+		})
+		.unwrap();
+		let rotation = |runtime: &Runtime| -> Vec<String> {
+			let mut runtime = runtime.clone();
+			(0..6)
+				.map(|_| {
+					let author = runtime.select_author(&validators).unwrap();
+					runtime.system.inc_block_number();
+					author
+				})
+				.collect()
+		};
+		let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+		// Without stakes, or with equal stakes, validators take turns.
+		assert_eq!(rotation(&runtime), names(&["alice", "bob", "charlie"].repeat(2)));
+		for who in &validators {
+			runtime.balances.stake(who, types::Balance(10)).unwrap();
+		}
+		assert_eq!(rotation(&runtime), names(&["alice", "bob", "charlie"].repeat(2)));
+		// Reserves which are not staked, like claim deposits, do not count.
+		runtime.balances.reserve(&validators[2], types::Balance(50)).unwrap();
+		assert_eq!(rotation(&runtime), names(&["alice", "bob", "charlie"].repeat(2)));
+
+		// Otherwise, they author blocks in proportion to their stake, over the period.
+		runtime.balances.stake(&validators[0], types::Balance(20)).unwrap();
+		runtime.balances.stake(&validators[1], types::Balance(10)).unwrap();
+		assert_eq!(
+			rotation(&runtime),
+			names(&["alice", "alice", "alice", "bob", "bob", "charlie"])
+		);
+
+		// Executing blocks moves the rotation forward.
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.select_author(&validators), Some(validators[1].clone()));
+		assert_eq!(runtime.select_author(&[]), None);
+
+		// Stakes whose total does not fit in a balance do not overflow.
+		let mut runtime = Runtime::new();
+		runtime
+			.balances
+			.set_balance(validators[0].clone(), types::Balance::MAX)
+			.unwrap();
+		runtime
+			.balances
+			.set_balance(validators[1].clone(), types::Balance::MAX)
+			.unwrap();
+		runtime.balances.stake(&validators[0], types::Balance::MAX).unwrap();
+		runtime
+			.balances
+			.stake(&validators[1], types::Balance::MAX - types::Balance(1))
+			.unwrap();
+		assert_eq!(runtime.select_author(&validators[..2]), Some(validators[0].clone()));
+	}
+
+	#[test]
+	fn genesis_validators() {
+		let validators: Vec<_> = ["alice", "bob", "charlie"].map(String::from).to_vec();
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: vec![(validators[0].clone(), types::Balance(100))],
+			validators: validators.iter().cloned().zip([30, 20, 10].map(types::Balance)).collect(),
+			..Default::default()
+		})
+		.unwrap();
+		assert_eq!(runtime.system.validators(), validators.as_slice());
+		// The stakes are minted and staked, on top of the genesis balances.
+		assert_eq!(runtime.balances.balance(&validators[0]), types::Balance(100));
+		assert_eq!(runtime.balances.reserved_balance(&validators[0]), types::Balance(30));
+		assert_eq!(runtime.balances.stake_of(&validators[0]), types::Balance(30));
+		assert_eq!(runtime.balances.total_issuance(), types::Balance(160));
+
+		// The stakes are used from the first block.
+		let authors: Vec<_> = (0..6)
+			.map(|_| {
+				let author = runtime.next_author().unwrap();
+				runtime.system.inc_block_number();
+				author
+			})
+			.collect();
+		let expected = ["alice", "alice", "alice", "bob", "bob", "charlie"].map(String::from);
+		assert_eq!(authors, expected);
+
+		// A slashed validator authors blocks in proportion to the stake it has left.
+		assert_eq!(
+			runtime.balances.slash_reserved(&validators[0], types::Balance(20)),
+			types::Balance(20)
+		);
+		assert_eq!(runtime.balances.stake_of(&validators[0]), types::Balance(10));
+		let mut authors: Vec<_> = (0..4)
+			.map(|_| {
+				let author = runtime.next_author().unwrap();
+				runtime.system.inc_block_number();
+				author
+			})
+			.collect();
+		authors.sort();
+		assert_eq!(authors, ["alice", "bob", "bob", "charlie"].map(String::from));
+
+		assert_eq!(Runtime::new().next_author(), None);
+	}
+
+	#[test]
+	#[cfg(feature = "logging")]
+	fn log_handler() {
+		let alice = "alice".to_string();
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+		let lines = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+		let captured = lines.clone();
+		runtime.set_log_handler(Box::new(move |line| captured.borrow_mut().push(line.to_string())));
+
+		let extrinsic = |nonce, call| support::Extrinsic::new(alice.clone(), call, nonce);
+		let extrinsics = vec![
+			extrinsic(
+				0,
+				RuntimeCall::balances(balances::Call::transfer {
+					to: "bob".to_string(),
+					amount: types::Balance(10),
+				}),
+			),
+			extrinsic(1, RuntimeCall::system(system::Call::remark { message: vec![1] })),
+			extrinsic(
+				2,
+				RuntimeCall::balances(balances::Call::transfer {
+					to: "bob".to_string(),
+					amount: types::Balance(500),
+				}),
+			),
+		];
+		runtime.execute_block(next_block(&runtime, extrinsics)).unwrap();
+
+		assert_eq!(
+			*lines.borrow(),
+			vec![
+				r#"{"block":1,"index":0,"caller":"alice","call":"balances.transfer","result":"ok"}"#,
+				r#"{"block":1,"index":1,"caller":"alice","call":"system.remark","result":"ok"}"#,
+				concat!(
+					r#"{"block":1,"index":2,"caller":"alice","call":"balances.transfer","#,
+					r#""result":"error","error":"Not enough funds!"}"#,
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn estimate_fee() {
+		let alice = "alice".to_string();
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(alice.clone(), types::Balance(100)).unwrap();
+
+		let call = RuntimeCall::system(system::Call::remark { message: vec![1, 2, 3] });
+		let fee = runtime.estimate_fee(&call);
+		assert_eq!(fee, TRANSACTION_FEE);
+		let extrinsic = support::Extrinsic::new(alice.clone(), call, 0);
+		runtime.execute_block(next_block(&runtime, vec![extrinsic])).unwrap();
+		assert_eq!(runtime.balances.balance(&alice), types::Balance(100) - fee);
+
+		// A batch call pays for each of its parts.
+		let claims = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claims {
+			claims: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+		});
+		let fee = runtime.estimate_fee(&claims);
+		assert_eq!(fee, types::Balance(3) * TRANSACTION_FEE);
+		let schedule = RuntimeCall::scheduler(scheduler::Call::schedule {
+			when: 10,
+			call: Box::new(claims.clone()),
+		});
+		assert_eq!(runtime.estimate_fee(&schedule), TRANSACTION_FEE + fee);
+
+		let holdings = |runtime: &Runtime| {
+			runtime.balances.balance(&alice) + runtime.balances.reserved_balance(&alice)
+		};
+		let before = holdings(&runtime);
+		let extrinsic = support::Extrinsic::new(alice.clone(), claims, 1);
+		runtime.execute_block(next_block(&runtime, vec![extrinsic])).unwrap();
+		assert_eq!(runtime.proof_of_existence.iter_claims().count(), 3);
+		// The claim deposits are only reserved, so the fee is all the caller lost.
+		assert_eq!(holdings(&runtime), before - fee);
+	}
+
+	#[test]
+	fn claim_deposit() {
+		let alice = "alice".to_string();
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(alice.clone(), types::Balance(1_000)).unwrap();
+		let deposit =
+			|claim: &str| <Runtime as proof_of_existence::Config>::deposit_for(claim.len());
+		let poe = |call| RuntimeCall::proof_of_existence(call);
+
+		let small = "small".to_string();
+		let large = "a much larger content to claim".to_string();
+		let extrinsics = vec![
+			support::Extrinsic::new(
+				alice.clone(),
+				poe(proof_of_existence::Call::create_claim {
+					claim: small.clone(),
+					content_type: None,
+				}),
+				0,
+			),
+			support::Extrinsic::new(
+				alice.clone(),
+				poe(proof_of_existence::Call::create_claim {
+					claim: large.clone(),
+					content_type: None,
+				}),
+				1,
+			),
+		];
+		runtime.execute_block(next_block(&runtime, extrinsics)).unwrap();
+		assert!(deposit(&large) > deposit(&small));
+		assert_eq!(deposit(&small), CLAIM_DEPOSIT_PER_BYTE * types::Balance(5));
+		assert_eq!(runtime.balances.reserved_balance(&alice), deposit(&small) + deposit(&large));
+		assert_eq!(runtime.proof_of_existence.deposit_of(&large), Some((&alice, deposit(&large))));
+		assert_eq!(
+			runtime.balances.balance(&alice),
+			types::Balance(1_000)
+				- types::Balance(2) * TRANSACTION_FEE
+				- deposit(&small)
+				- deposit(&large)
+		);
+
+		// Growing the content tops up the deposit, and revoking the claim releases it.
+		let id = runtime.proof_of_existence.claim_id(&small).unwrap();
+		let grown = "small, but grown".to_string();
+		let extrinsics = vec![
+			support::Extrinsic::new(
+				alice.clone(),
+				poe(proof_of_existence::Call::update_content {
+					claim_id: id,
+					new_content: grown.clone(),
+				}),
+				2,
+			),
+			support::Extrinsic::new(
+				alice.clone(),
+				poe(proof_of_existence::Call::revoke_claim { claim: large.clone() }),
+				3,
+			),
+		];
+		runtime.execute_block(next_block(&runtime, extrinsics)).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), deposit(&grown));
+		assert_eq!(
+			runtime.balances.balance(&alice),
+			types::Balance(1_000) - types::Balance(4) * TRANSACTION_FEE - deposit(&grown)
+		);
+
+		// A claim whose deposit cannot be reserved is not created.
+		let bob = "bob".to_string();
+		let min_balance = <Runtime as proof_of_existence::Config>::MIN_BALANCE_TO_CLAIM;
+		runtime
+			.balances
+			.set_balance(bob.clone(), min_balance + TRANSACTION_FEE)
+			.unwrap();
+		let call = poe(proof_of_existence::Call::create_claim {
+			claim: large.clone(),
+			content_type: None,
+		});
+		let extrinsic = support::Extrinsic::new(bob.clone(), call, 0);
+		runtime.execute_block(next_block(&runtime, vec![extrinsic])).unwrap();
+		assert_eq!(
+			runtime.recent_dispatches().last().unwrap().result,
+			Err("Insufficient balance for the claim deposit")
+		);
+		assert_eq!(runtime.proof_of_existence.get_claim(&large), None);
+		assert_eq!(runtime.balances.reserved_balance(&bob), types::Balance(0));
+	}
+
+	#[test]
+	fn rescuer_moves_the_funds_of_a_recovered_account() {
+		let [alice, bob, charlie, eve] = ["alice", "bob", "charlie", "eve"].map(String::from);
+		let mut runtime = Runtime::build_genesis(GenesisConfig {
+			balances: [&alice, &bob, &charlie, &eve]
+				.map(|who| (who.clone(), types::Balance(100)))
+				.to_vec(),
+			..Default::default()
+		})
+		.unwrap();
+		let recovery = |caller: &String, nonce, call| {
+			support::Extrinsic::new(caller.clone(), RuntimeCall::recovery(call), nonce)
+		};
+		let friends = vec![bob.clone(), charlie.clone()];
+		let vouch = || recovery::Call::vouch { lost: alice.clone(), rescuer: eve.clone() };
+		let transfer = |amount| {
+			Box::new(RuntimeCall::balances(balances::Call::transfer { to: eve.clone(), amount }))
+		};
+		let as_recovered =
+			|amount| recovery::Call::as_recovered { lost: alice.clone(), call: transfer(amount) };
+		let extrinsics = vec![
+			recovery(&alice, 0, recovery::Call::create_recovery { friends, threshold: 2 }),
+			recovery(&bob, 0, vouch()),
+			recovery(&charlie, 0, vouch()),
+			// The transfer can only be made once the account is recovered.
+			recovery(&eve, 0, as_recovered(types::Balance(10))),
+			recovery(&eve, 1, recovery::Call::claim_recovery { lost: alice.clone() }),
+			recovery(&eve, 2, as_recovered(types::Balance(50))),
+			// A call which fails on behalf of the lost account fails the whole extrinsic.
+			recovery(&eve, 3, as_recovered(types::Balance(1_000))),
+		];
+		runtime.execute_block(next_block(&runtime, extrinsics)).unwrap();
+
+		let results: Vec<_> = runtime.recent_dispatches().iter().map(|r| r.result).collect();
+		assert_eq!(
+			results[3..],
+			[Err("Not the rescuer of the account"), Ok(()), Ok(()), Err("Not enough funds!")]
+		);
+		assert_eq!(runtime.recovery.recovered_by(&alice), Some(&eve));
+		assert_eq!(
+			runtime.balances.balance(&alice),
+			types::Balance(100) - TRANSACTION_FEE - types::Balance(50)
+		);
+		// The fees of the failed extrinsics are refunded, and a transfer on behalf of the lost
+		// account pays for both calls.
+		let fee = runtime.estimate_fee(&RuntimeCall::recovery(as_recovered(types::Balance(50))));
+		assert_eq!(fee, types::Balance(2) * TRANSACTION_FEE);
+		assert_eq!(
+			runtime.balances.balance(&eve),
+			types::Balance(100) - TRANSACTION_FEE - fee + types::Balance(50)
+		);
+	}
+
+	#[test]
+	fn reaping_an_account_revokes_its_claims() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+		// Claims of an empty content need no deposit, which would keep the account from being
+		// reaped.
+		let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+			claim: String::new(),
+			content_type: None,
+		});
+		let transfer = RuntimeCall::balances(balances::Call::transfer {
+			to: bob.clone(),
+			amount: types::Balance(100) - types::Balance(2) * TRANSACTION_FEE,
+		});
+		let extrinsics = vec![
+			support::Extrinsic::new(alice.clone(), claim, 0),
+			support::Extrinsic::new(alice.clone(), transfer, 1),
+		];
+		runtime.execute_block(next_block(&runtime, extrinsics)).unwrap();
+
+		assert_eq!(runtime.balances.account_count(), 2);
+		assert_eq!(runtime.proof_of_existence.get_claim(&String::new()), None);
+		let events = runtime.events_filtered(|event| {
+			matches!(
+				event,
+				RuntimeEvent::balances(balances::Event::Reaped { .. })
+					| RuntimeEvent::proof_of_existence(proof_of_existence::Event::Revoked { .. })
+			)
+		});
+		assert_eq!(
+			events,
+			vec![
+				&RuntimeEvent::balances(balances::Event::Reaped {
+					who: alice.clone(),
+					burned: types::Balance(0)
+				}),
+				&RuntimeEvent::proof_of_existence(proof_of_existence::Event::Revoked {
+					owner: alice,
+					claim: String::new()
+				}),
+			]
+		);
+
+		// Accounts removed as dust are reaped too, and their claims revoked with the next block.
+		let charlie = "charlie".to_string();
+		runtime.balances.set_balance(charlie.clone(), types::Balance(1)).unwrap();
+		runtime.proof_of_existence.create_claim(charlie, String::new(), None).unwrap();
+		assert_eq!(runtime.balances.redistribute_dust(types::Balance(2)), types::Balance(1));
+		runtime.execute_block(next_block(&runtime, vec![])).unwrap();
+		assert_eq!(runtime.proof_of_existence.get_claim(&String::new()), None);
+	}
+
+	#[test]
+	fn supported_calls() {
+		let calls = Runtime::supported_calls();
+		for call in [
+			"system.remark",
+			"system.add_to_blacklist",
+			"balances.transfer",
+			"proof_of_existence.create_claim",
+			"proof_of_existence.update_content",
+			"scheduler.schedule",
+			"recovery.claim_recovery",
+			"treasury.approve_spend",
+		] {
+			assert!(calls.contains(&call), "{call} is missing");
+		}
+
+		// Every call of every pallet is listed once, so adding a call grows the list.
+		let count = system::Call::<Runtime>::NAMES.len()
+			+ balances::Call::<Runtime>::NAMES.len()
+			+ proof_of_existence::Call::<Runtime>::NAMES.len()
+			+ scheduler::Call::<Runtime>::NAMES.len()
+			+ recovery::Call::<Runtime>::NAMES.len()
+			+ treasury::Call::<Runtime>::NAMES.len();
+		assert_eq!(calls.len(), count);
+		assert_eq!(calls.iter().collect::<std::collections::BTreeSet<_>>().len(), count);
+	}
+
+	#[test]
+	fn auto_nonce_mode() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = funded_runtime(&alice);
+		runtime.system.set_nonce_mode(support::NonceMode::Auto);
+
+		// The nonces are all wrong, but the extrinsics still execute in order.
+		let transfer = |amount, nonce| {
+			support::Extrinsic::new(
+				alice.clone(),
+				RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount }),
+				nonce,
+			)
+		};
+		let block = next_block(
+			&runtime,
+			vec![
+				transfer(types::Balance(60), 7),
+				transfer(types::Balance(50), 7),
+				transfer(types::Balance(30), 0),
+			],
+		);
+		assert_eq!(runtime.execute_block(block.clone()), Ok(()));
+
+		// The second transfer failed, since the first one already spent most of the funds.
+		assert_eq!(
+			runtime.balances.balance(&alice),
+			types::Balance(100 - 60 - 30) - types::Balance(2) * TRANSACTION_FEE
+		);
+		assert_eq!(runtime.balances.balance(&bob), types::Balance(90));
+		assert_eq!(runtime.system.next_nonce(&alice), 3);
+
+		// Switching back to strict nonces rejects the same extrinsics.
+		runtime.system.set_nonce_mode(support::NonceMode::Strict);
+		assert_eq!(
+			runtime.execute_block(next_block(&runtime, block.extrinsics)),
+			Err("Invalid nonce")
+		);
+	}
+
+	/// A minimal runtime where all the accounts share a single nonce, and extrinsics are ordered
+	/// by tip.
+	mod global_nonce {
+		use crate::{support, system};
+		use support::Dispatch;
+
+		mod types {
+			pub type AccountId = String;
+			pub type BlockNumber = u32;
+			pub type Extrinsic =
+				crate::support::Extrinsic<AccountId, super::RuntimeCall, u32, u32, BlockNumber>;
+			pub type Header = crate::support::Header<BlockNumber, AccountId>;
+			pub type Block = crate::support::Block<Header, Extrinsic>;
+		}
+
+		#[derive(Debug, Clone, PartialEq)]
+		#[macros::runtime]
+		struct Runtime {
+			system: system::Pallet<Self>,
+		}
+
+		impl system::Config for Runtime {
+			type BlockNumber = types::BlockNumber;
+			type AccountId = types::AccountId;
+			type Nonce = u32;
+			type Hashing = support::Sha256;
+			type RuntimeEvent = RuntimeEvent;
+			const BLOCK_HASH_COUNT: u32 = 256;
+			const MAX_BLOCK_WEIGHT: support::Weight = 100;
+			const ORDERING_POLICY: support::OrderingPolicy = support::OrderingPolicy::ByTip;
+			const NONCE_STRATEGY: support::NonceStrategy = support::NonceStrategy::Global;
+		}
+
+		impl support::Hooks<types::Header> for Runtime {}
+
+		impl support::Fees<types::Header, types::AccountId, RuntimeCall> for Runtime {
+			type Fee = ();
+
+			fn charge_fee(
+				&mut self,
+				_: &types::AccountId,
+				_: &RuntimeCall,
+			) -> Result<(), &'static str> {
+				Ok(())
+			}
+
+			fn settle_fee(
+				&mut self,
+				_: &types::Header,
+				_: &types::AccountId,
+				_: (),
+				_: &support::DispatchResult,
+			) -> support::DispatchResult {
+				Ok(())
+			}
+		}
+
+		impl support::CallFilter<types::AccountId, RuntimeCall> for Runtime {
+			fn filter_call(
+				&self,
+				_: &types::AccountId,
+				_: &RuntimeCall,
+			) -> support::DispatchResult {
+				Ok(())
+			}
+		}
+
+		#[test]
+		fn global_nonces() {
+			let mut runtime = Runtime::new();
+			let remark = |caller: &str, nonce, tip| support::Extrinsic {
+				tip,
+				..support::Extrinsic::new(
+					caller.to_string(),
+					RuntimeCall::system(system::Call::remark { message: vec![] }),
+					nonce,
+				)
+			};
+			let block = |extrinsics| types::Block {
+				header: support::Header {
+					block_number: 1,
+					parent_hash: [0; 32],
+					author: "author".to_string(),
+				},
+				extrinsics,
+			};
+
+			// The nonces follow the order of the block, but Bob's higher tip makes him go first.
+			let unordered = block(vec![remark("alice", 0, 1), remark("bob", 1, 5)]);
+			assert_eq!(runtime.pre_validate_block(&unordered), Err("Invalid nonce"));
+			assert_eq!(
+				runtime.execute_block_with_mode(unordered, support::OverweightMode::Partial),
+				Err("Invalid nonce")
+			);
+
+			// Once the tips agree with the nonces, both callers share a single sequence.
+			let ordered = block(vec![remark("alice", 1, 1), remark("bob", 0, 5)]);
+			assert_eq!(runtime.execute_block(ordered), Ok(()));
+			assert_eq!(runtime.system.next_nonce(&"alice".to_string()), 2);
+			assert_eq!(runtime.system.next_nonce(&"charlie".to_string()), 2);
+		}
+	}
 }
@@ -1,45 +1,730 @@
-use core::fmt::Debug;
-use std::collections::BTreeMap;
+use core::{borrow::Borrow, fmt::Debug, ops::Bound};
+use std::collections::{BTreeMap, BTreeSet};
 
-use crate::support::DispatchResult;
+use num::{CheckedAdd, Saturating, Zero};
 
-pub trait Config: crate::system::Config {
+use crate::support::{DepositEvent, DispatchResult, Encode, EventBuffer};
+
+pub trait Config: crate::balances::Config {
 	/// The type which represents the content that can be claimed using this pallet.
 	/// Could be the content directly as bytes, or better yet the hash of that content.
 	/// We leave that decision to the runtime developer.
-	type Content: Debug + Ord;
+	type Content: Debug + Ord + Clone;
+	/// The maximum number of claims which can exist at the same time, across all accounts.
+	const MAX_TOTAL_CLAIMS: u32;
+	/// The number of blocks after which a new claim expires, unless it is renewed. If `None`, the
+	/// default, claims never expire.
+	const CLAIM_LIFETIME: Option<Self::BlockNumber> = None;
+	/// Whether `transfer_claim` only proposes the transfer, which the recipient must then accept
+	/// with `accept_claim`, rather than transferring the claim directly.
+	const REQUIRE_TRANSFER_ACCEPTANCE: bool = false;
+	/// The number of blocks after its creation block before a claim is final, see
+	/// `is_final_claim`. Until then, the claim could still be undone by a reorganization.
+	const CLAIM_CONFIRMATIONS: Self::BlockNumber;
+	/// The free balance an account needs to own a new claim, on top of its deposit. This is
+	/// checked by `check_funds`, before the call charges anything else than its fee.
+	const MIN_BALANCE_TO_CLAIM: Self::Balance;
+
+	/// Normalize some content before it is claimed or looked up, so that contents which should be
+	/// considered equal, for example ignoring case, map to the same claim. This must be idempotent.
+	/// By default, contents are used as is.
+	fn normalize(content: Self::Content) -> Self::Content {
+		content
+	}
+
+	/// The deposit for a claim on content of `len` bytes, for runtimes whose deposit grows with the
+	/// size of the content. By default, this is zero whatever the length.
+	fn deposit_for(_len: usize) -> Self::Balance {
+		Zero::zero()
+	}
+
+	/// The deposit reserved from the owner of a claim on `content` for as long as they own it, for
+	/// example the `deposit_for` its length. By default, claims need no deposit.
+	fn claim_deposit(_content: &Self::Content) -> Self::Balance {
+		Zero::zero()
+	}
+}
+
+/// A change to the deposit held for a claim, which the runtime applies to the balance of the
+/// account, see `Pallet::take_deposit_changes`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepositChange<T: Config> {
+	/// Reserve `amount` from the free balance of `who`, for their claim on `claim`.
+	Reserve { who: T::AccountId, amount: T::Balance, claim: T::Content },
+	/// Release `amount` from the reserved balance of `who`.
+	Release { who: T::AccountId, amount: T::Balance },
+}
+
+/// The events emitted by the Proof of Existence Module.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T: Config> {
+	/// `owner` claimed `claim`.
+	Created { owner: T::AccountId, claim: T::Content },
+	/// `owner` revoked their claim on `claim`.
+	Revoked { owner: T::AccountId, claim: T::Content },
+	/// The claim on `claim` was transferred from `from` to `to`.
+	Transferred { from: T::AccountId, to: T::AccountId, claim: T::Content },
+	/// `from` proposed to transfer their claim on `claim` to `to`, who must accept it.
+	TransferProposed { from: T::AccountId, to: T::AccountId, claim: T::Content },
+	/// `owner` locked their claim on `claim`, which can no longer be revoked or transferred.
+	Locked { owner: T::AccountId, claim: T::Content },
+	/// `owner` updated the content of the claim `claim_id` from `old` to `new`.
+	ContentUpdated { owner: T::AccountId, claim_id: u64, old: T::Content, new: T::Content },
+	/// The claim of `owner` on `claim` expired, and was removed.
+	Expired { owner: T::AccountId, claim: T::Content },
+	/// `owner` renewed their claim on `claim`, which now expires after block `expires_at`.
+	Renewed { owner: T::AccountId, claim: T::Content, expires_at: T::BlockNumber },
+	/// `owner` allowed `delegate` to create claims on their behalf.
+	DelegateAdded { owner: T::AccountId, delegate: T::AccountId },
+	/// `owner` no longer allows `delegate` to create claims on their behalf.
+	DelegateRemoved { owner: T::AccountId, delegate: T::AccountId },
+	/// `owner` was added to the owners of `claim`.
+	OwnerAdded { owner: T::AccountId, claim: T::Content },
+	/// `owner` left `claim`, which is kept by its other owners.
+	OwnerLeft { owner: T::AccountId, claim: T::Content },
 }
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pallet<T: Config> {
 	/// A simple storage map from content to the owner of that content.
 	/// Accounts can make multiple different claims, but each claim can only have one owner.
 	claims: BTreeMap<T::Content, T::AccountId>,
+	/// All the owners of each claim. This includes its owner in `claims`, who alone can revoke,
+	/// transfer or lock it, while the other owners can only leave it.
+	owners: BTreeMap<T::Content, BTreeSet<T::AccountId>>,
+	/// The number of claims owned by each account, kept in sync with `claims` so that it can be
+	/// queried without scanning all the claims. Accounts without claims are not stored.
+	owner_claims: BTreeMap<T::AccountId, usize>,
+	/// The history of the owner of each claim, as a list of `(block_number, owner)` entries
+	/// ordered by block number. An owner of `None` means the claim was revoked at that block.
+	#[allow(clippy::type_complexity)]
+	history: BTreeMap<T::Content, Vec<(T::BlockNumber, Option<T::AccountId>)>>,
+	/// The claims which were locked by their owner, and thus can never change owner again.
+	locked: BTreeSet<T::Content>,
+	/// The index of each claim in the order claims were created, counting from `0`.
+	creation_order: BTreeMap<T::Content, u64>,
+	/// The current content of each claim, by its index in `creation_order`.
+	claims_by_id: BTreeMap<u64, T::Content>,
+	/// The index the next created claim gets in `creation_order`.
+	next_creation_index: u64,
+	/// The content type each claim was tagged with when it was created, for tagged claims.
+	content_types: BTreeMap<T::Content, String>,
+	/// The proposed transfers waiting to be accepted, from the claim to its proposed new owner.
+	pending_transfers: BTreeMap<T::Content, T::AccountId>,
+	/// The accounts which each owner allows to create claims on their behalf.
+	delegates: BTreeMap<T::AccountId, BTreeSet<T::AccountId>>,
+	/// The last block in which each claim is valid, for claims which expire. Expired claims are
+	/// removed at the start of the next block.
+	expiries: BTreeMap<T::Content, T::BlockNumber>,
+	/// The account which holds the deposit of each claim, and the amount held. Claims without a
+	/// deposit are not stored.
+	deposits: BTreeMap<T::Content, (T::AccountId, T::Balance)>,
+	/// The changes to the deposits which have not been applied by the runtime yet.
+	deposit_changes: Vec<DepositChange<T>>,
+	/// The current block number, used to record when ownership changes.
+	block_number: T::BlockNumber,
+	/// The events deposited by this pallet which have not been collected by the runtime yet.
+	events: EventBuffer<Event<T>>,
 }
 
 impl<T: Config> Pallet<T> {
 	/// Create a new instance of the Proof of Existence Module.
 	pub fn new() -> Self {
-		Self { claims: BTreeMap::new() }
+		Self {
+			claims: BTreeMap::new(),
+			owners: BTreeMap::new(),
+			owner_claims: BTreeMap::new(),
+			history: BTreeMap::new(),
+			locked: BTreeSet::new(),
+			expiries: BTreeMap::new(),
+			pending_transfers: BTreeMap::new(),
+			content_types: BTreeMap::new(),
+			creation_order: BTreeMap::new(),
+			claims_by_id: BTreeMap::new(),
+			next_creation_index: 0,
+			delegates: BTreeMap::new(),
+			deposits: BTreeMap::new(),
+			deposit_changes: Vec::new(),
+			block_number: T::BlockNumber::zero(),
+			events: EventBuffer::new(),
+		}
+	}
+
+	/// Clear all the claims, along with their history, locks, content types and pending transfers.
+	/// The current block number is kept.
+	pub fn clear(&mut self) {
+		self.claims.clear();
+		self.owners.clear();
+		self.owner_claims.clear();
+		self.history.clear();
+		self.locked.clear();
+		self.expiries.clear();
+		self.pending_transfers.clear();
+		self.content_types.clear();
+		self.creation_order.clear();
+		self.claims_by_id.clear();
+		self.next_creation_index = 0;
+		self.delegates.clear();
+		self.deposits.clear();
+		self.deposit_changes.clear();
+	}
+
+	/// Take all the events deposited by this pallet since they were last taken.
+	pub fn take_events(&mut self) -> Vec<Event<T>> {
+		self.events.take()
+	}
+
+	/// Take all the changes to the deposits since they were last taken, in the order they
+	/// happened. The runtime must apply them to the balances of the accounts, and call
+	/// `forget_deposit` for the reserves which fail.
+	pub fn take_deposit_changes(&mut self) -> Vec<DepositChange<T>> {
+		core::mem::take(&mut self.deposit_changes)
+	}
+
+	/// Forget `amount` of the deposit held for `claim`, because the runtime could not reserve it.
+	pub fn forget_deposit(&mut self, claim: &T::Content, amount: T::Balance) {
+		if let Some((_, held)) = self.deposits.get_mut(claim) {
+			*held = held.saturating_sub(amount);
+			if held.is_zero() {
+				self.deposits.remove(claim);
+			}
+		}
+	}
+
+	/// Get the account which holds the deposit of `claim`, and the amount held, if any.
+	pub fn deposit_of(&self, claim: &T::Content) -> Option<(&T::AccountId, T::Balance)> {
+		self.deposits.get(&T::normalize(claim.clone())).map(|(who, held)| (who, *held))
+	}
+
+	/// Check that the accounts involved in `call` have the funds it needs, given the free balance
+	/// of each account from `balance`: a new claim needs its owner to hold `MIN_BALANCE_TO_CLAIM`,
+	/// and whoever takes over a deposit must be able to reserve it. The runtime must check this
+	/// before dispatching `call` from `caller`, since the deposits are only reserved once the call
+	/// succeeded.
+	pub fn check_funds(
+		&self,
+		caller: &T::AccountId,
+		call: &Call<T>,
+		balance: impl Fn(&T::AccountId) -> T::Balance,
+	) -> DispatchResult {
+		// The owner of a claim created by a delegate must hold the balance, not the delegate.
+		let claimer = match call {
+			Call::create_claim { .. } | Call::create_claims { .. } => Some(caller),
+			Call::create_claim_for { owner, .. } => Some(owner),
+			_ => None,
+		};
+		if claimer.is_some_and(|claimer| balance(claimer) < T::MIN_BALANCE_TO_CLAIM) {
+			return Err("Insufficient balance to claim");
+		}
+		if let Some((who, deposit)) = self.deposit_needed(caller, call) {
+			if balance(&who) < deposit {
+				return Err("Insufficient balance for the claim deposit");
+			}
+		}
+		Ok(())
+	}
+
+	/// Get the account which must be able to reserve a deposit for `caller` to dispatch `call`,
+	/// and the amount, if any.
+	fn deposit_needed(
+		&self,
+		caller: &T::AccountId,
+		call: &Call<T>,
+	) -> Option<(T::AccountId, T::Balance)> {
+		let deposit = |claim: &T::Content| T::claim_deposit(&T::normalize(claim.clone()));
+		let (who, amount) = match call {
+			Call::create_claim { claim, .. } | Call::accept_claim { claim } => {
+				(caller.clone(), deposit(claim))
+			},
+			Call::create_claim_for { owner, claim, .. } => (owner.clone(), deposit(claim)),
+			Call::create_claims { claims } => {
+				let total = claims
+					.iter()
+					.fold(T::Balance::zero(), |total, claim| total.saturating_add(deposit(claim)));
+				(caller.clone(), total)
+			},
+			Call::transfer_claim { claim, to } if !T::REQUIRE_TRANSFER_ACCEPTANCE => {
+				(to.clone(), deposit(claim))
+			},
+			// Only the growth of the deposit is reserved when the content changes.
+			Call::update_content { claim_id, new_content } => {
+				let held = self
+					.claim_by_id(*claim_id)
+					.and_then(|claim| self.deposits.get(claim))
+					.map_or(T::Balance::zero(), |(_, held)| *held);
+				(caller.clone(), deposit(new_content).saturating_sub(held))
+			},
+			// The next owner takes over the deposit when the owner leaves.
+			Call::leave_claim { claim } => {
+				let claim = T::normalize(claim.clone());
+				if self.claims.get(&claim) != Some(caller) {
+					return None;
+				}
+				let owners = self.owners.get(&claim)?;
+				let next = owners.iter().find(|owner| *owner != caller)?;
+				(next.clone(), deposit(&claim))
+			},
+			_ => return None,
+		};
+		Some((who, amount)).filter(|(_, amount)| !amount.is_zero())
+	}
+
+	/// Called by the runtime at the start of every block, so that we know the current block.
+	/// The claims which expired before `now` are removed, unless they are locked, depositing an
+	/// `Expired` event for each of them.
+	pub fn on_initialize(&mut self, now: T::BlockNumber) {
+		self.block_number = now;
+
+		let expired: Vec<_> = self
+			.expiries
+			.iter()
+			.filter(|(claim, expires_at)| **expires_at < now && !self.locked.contains(*claim))
+			.map(|(claim, _)| claim.clone())
+			.collect();
+		for claim in expired {
+			if let Some(owner) = self.claims.get(&claim).cloned() {
+				self.set_owner(claim.clone(), None);
+				self.events.deposit_event(Event::Expired { owner, claim });
+			}
+		}
 	}
 
 	/// Get the owner (if any) of a claim.
 	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-		self.claims.get(claim)
+		self.claims.get(&T::normalize(claim.clone()))
+	}
+
+	/// Get the number of claims owned by `owner`.
+	pub fn claim_count_of(&self, owner: &T::AccountId) -> usize {
+		self.owner_claims.get(owner).copied().unwrap_or(0)
+	}
+
+	/// Get all the owners of a claim, ordered by account. This is empty if the content is not
+	/// claimed.
+	pub fn owners_of(&self, claim: &T::Content) -> Vec<&T::AccountId> {
+		self.owners.get(&T::normalize(claim.clone())).into_iter().flatten().collect()
+	}
+
+	/// Check whether some content has been claimed.
+	pub fn is_claimed(&self, claim: &T::Content) -> bool {
+		self.get_claim(claim).is_some()
+	}
+
+	/// Check that `claim` exists and is owned by `caller`.
+	fn ensure_owner(&self, caller: &T::AccountId, claim: &T::Content) -> DispatchResult {
+		let owner = self.get_claim(claim).ok_or("Claim does not exist.")?;
+		if caller != owner {
+			return Err("This content is owned by someone else.");
+		}
+		Ok(())
+	}
+
+	/// Check whether a claim has been locked by its owner.
+	pub fn is_locked(&self, claim: &T::Content) -> bool {
+		self.locked.contains(&T::normalize(claim.clone()))
+	}
+
+	/// Check that `count` more claims can be created without going over `MAX_TOTAL_CLAIMS`.
+	fn ensure_capacity(&self, count: usize) -> DispatchResult {
+		if self.claims.len() + count > T::MAX_TOTAL_CLAIMS as usize {
+			return Err("Claim storage full");
+		}
+		Ok(())
+	}
+
+	/// Check that `claim` is not locked.
+	fn ensure_unlocked(&self, claim: &T::Content) -> DispatchResult {
+		if self.is_locked(claim) {
+			return Err("Claim locked");
+		}
+		Ok(())
+	}
+
+	/// Get the owner (if any) of a claim at the end of block `block`.
+	pub fn owner_at(&self, claim: &T::Content, block: T::BlockNumber) -> Option<&T::AccountId> {
+		self.history
+			.get(&T::normalize(claim.clone()))?
+			.iter()
+			.rev()
+			.find(|(changed_at, _)| *changed_at <= block)
+			.and_then(|(_, owner)| owner.as_ref())
+	}
+
+	/// Check whether `owner` allows `agent` to create claims on their behalf.
+	pub fn is_delegate(&self, owner: &T::AccountId, agent: &T::AccountId) -> bool {
+		self.delegates.get(owner).is_some_and(|delegates| delegates.contains(agent))
+	}
+
+	/// Create a new claim on `claim`, owned by `owner`, and tagged with `content_type` if any.
+	fn do_create_claim(
+		&mut self,
+		owner: T::AccountId,
+		claim: T::Content,
+		content_type: Option<String>,
+	) -> DispatchResult {
+		T::validate_account(&owner)?;
+		let claim = T::normalize(claim);
+		if self.is_claimed(&claim) {
+			return Err("This content is already claimed.");
+		}
+		self.ensure_capacity(1)?;
+		self.start_expiry(&claim)?;
+		self.set_owner(claim.clone(), Some(owner.clone()));
+		if let Some(content_type) = content_type {
+			self.content_types.insert(claim.clone(), content_type);
+		}
+		self.events.deposit_event(Event::Created { owner, claim });
+		Ok(())
+	}
+
+	/// Get the content type `claim` was tagged with when it was created, if any.
+	pub fn content_type(&self, claim: &T::Content) -> Option<&str> {
+		self.content_types.get(&T::normalize(claim.clone())).map(String::as_str)
+	}
+
+	/// Get all the claims which were tagged with the content type `tag`, ordered by content.
+	pub fn claims_by_type(&self, tag: &str) -> Vec<&T::Content> {
+		self.content_types
+			.iter()
+			.filter(|(_, content_type)| *content_type == tag)
+			.map(|(claim, _)| claim)
+			.collect()
+	}
+
+	/// Get the last block in which `claim` is valid, if it is claimed and expires.
+	pub fn expires_at(&self, claim: &T::Content) -> Option<T::BlockNumber> {
+		self.expiries.get(&T::normalize(claim.clone())).copied()
+	}
+
+	/// Start the expiry of a newly created `claim`, if claims expire. This fails without changing
+	/// anything if the expiry would overflow.
+	fn start_expiry(&mut self, claim: &T::Content) -> DispatchResult {
+		if let Some(lifetime) = T::CLAIM_LIFETIME {
+			let expires_at = self.block_number.checked_add(&lifetime).ok_or("Expiry overflow")?;
+			self.expiries.insert(claim.clone(), expires_at);
+		}
+		Ok(())
+	}
+
+	/// Get the block in which the current claim on `claim` was created, if it is claimed. Later
+	/// transfers of the claim do not change its creation block.
+	pub fn created_at(&self, claim: &T::Content) -> Option<T::BlockNumber> {
+		// The claim was created by the oldest entry since it was last revoked.
+		self.history
+			.get(&T::normalize(claim.clone()))?
+			.iter()
+			.rev()
+			.take_while(|(_, owner)| owner.is_some())
+			.last()
+			.map(|(created_at, _)| *created_at)
+	}
+
+	/// Check whether `claim` is claimed and final at block `now`, which is the case once
+	/// `CLAIM_CONFIRMATIONS` blocks passed since the block in which its claim was created.
+	/// Claims which are claimed but not final yet are still pending. A claim which would only be
+	/// final after the last block number is never final.
+	pub fn is_final_claim(&self, claim: &T::Content, now: T::BlockNumber) -> bool {
+		self.created_at(claim)
+			.and_then(|created_at| created_at.checked_add(&T::CLAIM_CONFIRMATIONS))
+			.is_some_and(|final_at| final_at <= now)
+	}
+
+	/// Get all the claims created between blocks `from` and `to` included, along with their
+	/// owners, ordered by content.
+	pub fn claims_between(
+		&self,
+		from: T::BlockNumber,
+		to: T::BlockNumber,
+	) -> Vec<(&T::Content, &T::AccountId)> {
+		self.claims
+			.iter()
+			.filter(|(claim, _)| {
+				self.created_at(claim)
+					.is_some_and(|created_at| from <= created_at && created_at <= to)
+			})
+			.collect()
+	}
+
+	/// Get all the claims in the order they were created, oldest first. A revoked claim which is
+	/// claimed again counts as newly created.
+	pub fn claims_by_creation(&self) -> Vec<&T::Content> {
+		self.claims_by_id.values().collect()
+	}
+
+	/// Get the stable id of the claim on `claim`, if it is claimed. This is its index in the order
+	/// claims were created, which is kept when the content of the claim is updated.
+	pub fn claim_id(&self, claim: &T::Content) -> Option<u64> {
+		self.creation_order.get(&T::normalize(claim.clone())).copied()
+	}
+
+	/// Get the current content of the claim with the id `claim_id`, if it exists.
+	pub fn claim_by_id(&self, claim_id: u64) -> Option<&T::Content> {
+		self.claims_by_id.get(&claim_id)
+	}
+
+	/// Move the claim on `from` to the content `to`, keeping its owner, id, expiry and content
+	/// type. The ownership history records that `from` is no longer claimed, and `to` now is.
+	fn move_claim(&mut self, from: T::Content, to: T::Content) {
+		let Some(owner) = self.claims.remove(&from) else {
+			return;
+		};
+		self.pending_transfers.remove(&from);
+		if let Some(id) = self.creation_order.remove(&from) {
+			self.creation_order.insert(to.clone(), id);
+			self.claims_by_id.insert(id, to.clone());
+		}
+		if let Some(expires_at) = self.expiries.remove(&from) {
+			self.expiries.insert(to.clone(), expires_at);
+		}
+		if let Some(content_type) = self.content_types.remove(&from) {
+			self.content_types.insert(to.clone(), content_type);
+		}
+		if let Some(owners) = self.owners.remove(&from) {
+			self.owners.insert(to.clone(), owners);
+		}
+		// The same account keeps holding the deposit, which follows the size of the new content.
+		let (holder, held) = self.deposits.remove(&from).unwrap_or((owner.clone(), Zero::zero()));
+		let needed = T::claim_deposit(&to);
+		if needed < held {
+			let amount = held - needed;
+			self.deposit_changes
+				.push(DepositChange::Release { who: holder.clone(), amount });
+		} else if needed > held {
+			let amount = needed - held;
+			let (who, claim) = (holder.clone(), to.clone());
+			self.deposit_changes.push(DepositChange::Reserve { who, amount, claim });
+		}
+		if !needed.is_zero() {
+			self.deposits.insert(to.clone(), (holder, needed));
+		}
+		self.claims.insert(to.clone(), owner.clone());
+		self.history.entry(from).or_default().push((self.block_number, None));
+		self.history.entry(to).or_default().push((self.block_number, Some(owner)));
+	}
+
+	/// Set the owner of a `claim`, or remove the claim if `owner` is `None`, and record the change
+	/// in the ownership history. The new owner replaces the previous one among the owners of the
+	/// claim, and removing the claim removes all its owners. The deposit of the claim is released,
+	/// and reserved again from the new owner, if any.
+	fn set_owner(&mut self, claim: T::Content, owner: Option<T::AccountId>) {
+		self.pending_transfers.remove(&claim);
+		if owner.as_ref() != self.claims.get(&claim) {
+			self.release_deposit(&claim);
+			if let Some(owner) = &owner {
+				self.hold_deposit(&claim, owner.clone());
+			}
+		}
+		let previous = match &owner {
+			Some(owner) => {
+				if !self.claims.contains_key(&claim) {
+					self.creation_order.insert(claim.clone(), self.next_creation_index);
+					self.claims_by_id.insert(self.next_creation_index, claim.clone());
+					self.next_creation_index += 1;
+				}
+				*self.owner_claims.entry(owner.clone()).or_default() += 1;
+				let previous = self.claims.insert(claim.clone(), owner.clone());
+				let owners = self.owners.entry(claim.clone()).or_default();
+				if let Some(previous) = &previous {
+					owners.remove(previous);
+				}
+				owners.insert(owner.clone());
+				previous
+			},
+			None => {
+				self.owners.remove(&claim);
+				if let Some(id) = self.creation_order.remove(&claim) {
+					self.claims_by_id.remove(&id);
+				}
+				self.expiries.remove(&claim);
+				self.content_types.remove(&claim);
+				self.claims.remove(&claim)
+			},
+		};
+		if let Some(previous) = previous {
+			if let Some(count) = self.owner_claims.get_mut(&previous) {
+				*count -= 1;
+				if *count == 0 {
+					self.owner_claims.remove(&previous);
+				}
+			}
+		}
+		self.history.entry(claim).or_default().push((self.block_number, owner));
+	}
+
+	/// Release the deposit held for `claim`, if any.
+	fn release_deposit(&mut self, claim: &T::Content) {
+		if let Some((who, amount)) = self.deposits.remove(claim) {
+			self.deposit_changes.push(DepositChange::Release { who, amount });
+		}
+	}
+
+	/// Hold the deposit of `claim` from `who`, which must not hold a deposit for it yet.
+	fn hold_deposit(&mut self, claim: &T::Content, who: T::AccountId) {
+		let amount = T::claim_deposit(claim);
+		if amount.is_zero() {
+			return;
+		}
+		self.deposits.insert(claim.clone(), (who.clone(), amount));
+		self.deposit_changes
+			.push(DepositChange::Reserve { who, amount, claim: claim.clone() });
+	}
+
+	/// Get the account which `claim` was proposed to be transferred to, if any.
+	pub fn pending_transfer(&self, claim: &T::Content) -> Option<&T::AccountId> {
+		self.pending_transfers.get(&T::normalize(claim.clone()))
+	}
+
+	/// Revoke all the claims owned by `caller`, except the locked ones, and return how many were
+	/// revoked. The claims of other owners are never touched. A `Revoked` event is deposited for
+	/// each claim, which the runtime must collect since this is not a call.
+	pub fn revoke_all(&mut self, caller: T::AccountId) -> u32 {
+		let owned: Vec<_> = self
+			.claims
+			.iter()
+			.filter(|(claim, owner)| **owner == caller && !self.locked.contains(*claim))
+			.map(|(claim, _)| claim.clone())
+			.collect();
+		let revoked = owned.len() as u32;
+		for claim in owned {
+			self.set_owner(claim.clone(), None);
+			self.events.deposit_event(Event::Revoked { owner: caller.clone(), claim });
+		}
+		revoked
+	}
+
+	/// Get an owned copy of all the claims and their owners, which is independent from any later
+	/// change to the state.
+	pub fn claims_snapshot(&self) -> BTreeMap<T::Content, T::AccountId> {
+		self.claims.clone()
+	}
+
+	/// Iterate over all the claims and their owners, ordered by content.
+	pub fn iter_claims(&self) -> impl Iterator<Item = (&T::Content, &T::AccountId)> {
+		self.claims.iter()
+	}
+}
+
+/// Functions which need the claims to be encoded.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::BlockNumber: Encode,
+	T::Balance: Encode,
+	T::Content: Encode,
+{
+	/// Get the hash of the state of the claims besides their owner in `iter_claims`: their other
+	/// owners, history, creation order, content types, locks, expiries, deposits and pending
+	/// transfers, and the delegates of each owner. This lets the state root cover all of them.
+	pub fn state_hash(&self) -> [u8; 32] {
+		let mut bytes = self.owners.encode();
+		bytes.extend(self.history.encode());
+		bytes.extend(self.locked.encode());
+		bytes.extend(self.creation_order.encode());
+		bytes.extend(self.next_creation_index.encode());
+		bytes.extend(self.content_types.encode());
+		bytes.extend(self.pending_transfers.encode());
+		bytes.extend(self.delegates.encode());
+		bytes.extend(self.expiries.encode());
+		bytes.extend(self.deposits.encode());
+		crate::system::Pallet::<T>::hash(&bytes)
+	}
+}
+
+/// Queries which only make sense when the content is a string.
+impl<T: Config> Pallet<T>
+where
+	T::Content: Borrow<str>,
+{
+	/// Get all the claims whose content starts with `prefix`, along with their owners.
+	/// Since claims are ordered by content, this only visits the matching claims.
+	pub fn claims_with_prefix(&self, prefix: &str) -> Vec<(&str, &T::AccountId)> {
+		self.claims
+			.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+			.map(|(claim, owner)| (claim.borrow(), owner))
+			.take_while(|(claim, _)| claim.starts_with(prefix))
+			.collect()
 	}
 }
 
 #[macros::call]
 impl<T: Config> Pallet<T> {
-	/// Create a new claim on behalf of the `caller`.
-	/// This function will return an error if someone already has claimed that content.
-	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		if self.claims.contains_key(&claim) {
-			return Err("This content is already claimed.");
+	/// Create a new claim on behalf of the `caller`, optionally tagged with a `content_type`.
+	/// This function will return an error if someone already has claimed that content, or if
+	/// there are already `MAX_TOTAL_CLAIMS` claims.
+	pub fn create_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: T::Content,
+		content_type: Option<String>,
+	) -> DispatchResult {
+		self.do_create_claim(caller, claim, content_type)
+	}
+
+	/// Create a new claim owned by `owner`, on behalf of the `caller` which must be one of their
+	/// delegates. Otherwise, this fails like `create_claim`.
+	pub fn create_claim_for(
+		&mut self,
+		caller: T::AccountId,
+		owner: T::AccountId,
+		claim: T::Content,
+		content_type: Option<String>,
+	) -> DispatchResult {
+		if !self.is_delegate(&owner, &caller) {
+			return Err("Not a delegate of the owner");
+		}
+		self.do_create_claim(owner, claim, content_type)
+	}
+
+	/// Allow `delegate` to create claims on behalf of the `caller`.
+	pub fn add_delegate(&mut self, caller: T::AccountId, delegate: T::AccountId) -> DispatchResult {
+		if self.delegates.entry(caller.clone()).or_default().insert(delegate.clone()) {
+			self.events.deposit_event(Event::DelegateAdded { owner: caller, delegate });
+		}
+		Ok(())
+	}
+
+	/// Stop allowing `delegate` to create claims on behalf of the `caller`.
+	pub fn remove_delegate(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+	) -> DispatchResult {
+		let delegates = self.delegates.get_mut(&caller).ok_or("Not a delegate of the owner")?;
+		if !delegates.remove(&delegate) {
+			return Err("Not a delegate of the owner");
+		}
+		if delegates.is_empty() {
+			self.delegates.remove(&caller);
+		}
+		self.events.deposit_event(Event::DelegateRemoved { owner: caller, delegate });
+		Ok(())
+	}
+
+	/// Create many claims at once on behalf of the `caller`.
+	/// This function is all or nothing: it stops at the first claim which is already claimed (or
+	/// appears twice in `claims`) and returns an error, without creating any of the claims. It
+	/// also fails if the claims would not fit within `MAX_TOTAL_CLAIMS`.
+	pub fn create_claims(
+		&mut self,
+		caller: T::AccountId,
+		claims: Vec<T::Content>,
+	) -> DispatchResult {
+		T::validate_account(&caller)?;
+		let claims: Vec<_> = claims.into_iter().map(T::normalize).collect();
+		for (i, claim) in claims.iter().enumerate() {
+			if self.is_claimed(claim) || claims[..i].contains(claim) {
+				return Err("This content is already claimed.");
+			}
+		}
+		self.ensure_capacity(claims.len())?;
+		for claim in claims {
+			// All the claims expire in the same block, so only the first one can fail.
+			self.start_expiry(&claim)?;
+			self.set_owner(claim.clone(), Some(caller.clone()));
+			self.events.deposit_event(Event::Created { owner: caller.clone(), claim });
 		}
-		self.claims.insert(claim, caller);
 		Ok(())
 	}
 
@@ -47,41 +732,853 @@ impl<T: Config> Pallet<T> {
 	/// This function should only succeed if the caller is the owner of an existing claim.
 	/// It will return an error if the claim does not exist, or if the caller is not the owner.
 	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		let owner = self.get_claim(&claim).ok_or("Claim does not exist.")?;
+		let claim = T::normalize(claim);
+		self.ensure_owner(&caller, &claim)?;
+		self.ensure_unlocked(&claim)?;
+		self.set_owner(claim.clone(), None);
+		self.events.deposit_event(Event::Revoked { owner: caller, claim });
+		Ok(())
+	}
 
-		if caller != *owner {
+	/// Add `owner` to the owners of an existing claim, as its `caller` owner. Only unlocked claims
+	/// can get new owners.
+	pub fn add_owner(
+		&mut self,
+		caller: T::AccountId,
+		claim: T::Content,
+		owner: T::AccountId,
+	) -> DispatchResult {
+		let claim = T::normalize(claim);
+		self.ensure_owner(&caller, &claim)?;
+		self.ensure_unlocked(&claim)?;
+		T::validate_account(&owner)?;
+		if !self.owners.entry(claim.clone()).or_default().insert(owner.clone()) {
+			return Err("Already an owner");
+		}
+		self.events.deposit_event(Event::OwnerAdded { owner, claim });
+		Ok(())
+	}
+
+	/// Remove the `caller` from the owners of a claim. The claim is kept by its other owners, and
+	/// if the caller was its owner in `get_claim`, the first of them in account order takes over.
+	/// The claim is only removed, like with `revoke_claim`, when its last owner leaves. Locked
+	/// claims cannot be left.
+	pub fn leave_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+		let claim = T::normalize(claim);
+		let owners = self.owners.get(&claim).ok_or("Claim does not exist.")?;
+		if !owners.contains(&caller) {
 			return Err("This content is owned by someone else.");
 		}
-		self.claims.remove(&claim);
+		self.ensure_unlocked(&claim)?;
+
+		let next = owners.iter().find(|owner| **owner != caller).cloned();
+		match next {
+			None => {
+				self.set_owner(claim.clone(), None);
+				self.events.deposit_event(Event::Revoked { owner: caller, claim });
+				return Ok(());
+			},
+			Some(next) if self.get_claim(&claim) == Some(&caller) => {
+				self.set_owner(claim.clone(), Some(next));
+			},
+			Some(_) => {
+				self.owners.entry(claim.clone()).or_default().remove(&caller);
+			},
+		}
+		self.events.deposit_event(Event::OwnerLeft { owner: caller, claim });
+		Ok(())
+	}
+
+	/// Transfer an existing claim on some content from the `caller` to another account `to`.
+	/// If `REQUIRE_TRANSFER_ACCEPTANCE` is set, the transfer is only proposed, replacing any
+	/// previous proposal, and the owner only changes once `to` calls `accept_claim`.
+	/// It will return an error if the claim does not exist, or if the caller is not the owner.
+	pub fn transfer_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: T::Content,
+		to: T::AccountId,
+	) -> DispatchResult {
+		let claim = T::normalize(claim);
+		self.ensure_owner(&caller, &claim)?;
+		self.ensure_unlocked(&claim)?;
+		if T::REQUIRE_TRANSFER_ACCEPTANCE {
+			self.pending_transfers.insert(claim.clone(), to.clone());
+			self.events.deposit_event(Event::TransferProposed { from: caller, to, claim });
+		} else {
+			self.set_owner(claim.clone(), Some(to.clone()));
+			self.events.deposit_event(Event::Transferred { from: caller, to, claim });
+		}
+		Ok(())
+	}
+
+	/// Accept the transfer of a claim which was proposed to the `caller` with `transfer_claim`.
+	/// It will return an error if no transfer of the claim to the caller is pending.
+	pub fn accept_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+		let claim = T::normalize(claim);
+		if self.pending_transfers.get(&claim) != Some(&caller) {
+			return Err("No pending transfer to the caller");
+		}
+		self.ensure_unlocked(&claim)?;
+		let from = self.claims.get(&claim).cloned().ok_or("Claim does not exist.")?;
+		self.set_owner(claim.clone(), Some(caller.clone()));
+		self.events.deposit_event(Event::Transferred { from, to: caller, claim });
+		Ok(())
+	}
+
+	/// Lock an existing claim, making it permanent: it can no longer be revoked or transferred.
+	/// It will return an error if the claim does not exist, or if the caller is not the owner.
+	/// Locking a claim which is already locked does nothing.
+	pub fn lock_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+		let claim = T::normalize(claim);
+		self.ensure_owner(&caller, &claim)?;
+		if self.locked.insert(claim.clone()) {
+			self.events.deposit_event(Event::Locked { owner: caller, claim });
+		}
+		Ok(())
+	}
+
+	/// Update the content of the claim with the id `claim_id` to `new_content`, for example when
+	/// the underlying data changed. The claim keeps its id and owner.
+	/// It will return an error if the claim does not exist, if the caller is not the owner, if the
+	/// claim is locked, or if `new_content` is already claimed.
+	pub fn update_content(
+		&mut self,
+		caller: T::AccountId,
+		claim_id: u64,
+		new_content: T::Content,
+	) -> DispatchResult {
+		let new_content = T::normalize(new_content);
+		let claim = self.claim_by_id(claim_id).cloned().ok_or("Claim does not exist.")?;
+		self.ensure_owner(&caller, &claim)?;
+		self.ensure_unlocked(&claim)?;
+		if self.is_claimed(&new_content) {
+			return Err("This content is already claimed.");
+		}
+		self.move_claim(claim.clone(), new_content.clone());
+		self.events.deposit_event(Event::ContentUpdated {
+			owner: caller,
+			claim_id,
+			old: claim,
+			new: new_content,
+		});
+		Ok(())
+	}
+
+	/// Extend the expiry of an existing claim by `additional_blocks`.
+	/// It will return an error if the claim does not exist (for example because it already
+	/// expired), if the caller is not the owner, if the claim does not expire, or if the new
+	/// expiry would overflow.
+	pub fn renew_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: T::Content,
+		additional_blocks: T::BlockNumber,
+	) -> DispatchResult {
+		let claim = T::normalize(claim);
+		self.ensure_owner(&caller, &claim)?;
+		let expires_at = self.expiries.get_mut(&claim).ok_or("Claim does not expire")?;
+		if *expires_at < self.block_number {
+			return Err("Claim expired");
+		}
+		*expires_at = expires_at.checked_add(&additional_blocks).ok_or("Expiry overflow")?;
+		let expires_at = *expires_at;
+		self.events.deposit_event(Event::Renewed { owner: caller, claim, expires_at });
 		Ok(())
 	}
 }
 
+impl<T: Config> crate::support::GetWeight for Call<T> {
+	fn weight(&self) -> crate::support::Weight {
+		match self {
+			Call::create_claim { .. } => 10,
+			Call::create_claim_for { .. } => 10,
+			Call::add_delegate { .. } => 5,
+			Call::remove_delegate { .. } => 5,
+			Call::create_claims { claims } => 10 * claims.len() as crate::support::Weight,
+			Call::revoke_claim { .. } => 10,
+			Call::add_owner { .. } => 5,
+			Call::leave_claim { .. } => 10,
+			Call::transfer_claim { .. } => 10,
+			Call::accept_claim { .. } => 10,
+			Call::lock_claim { .. } => 10,
+			Call::renew_claim { .. } => 10,
+			Call::update_content { .. } => 10,
+		}
+	}
+}
+
 #[cfg(test)]
 mod test {
-	struct TestConfig;
+	/// Declare a test config called `$name` with the given proof of existence config items, on top
+	/// of the balances and system configs shared by every test config.
+	macro_rules! test_config {
+		($(#[$attr:meta])* $name:ident { $($item:item)* }) => {
+			$(#[$attr])*
+			struct $name;
+
+			impl super::Config for $name {
+				$($item)*
+			}
+
+			impl crate::balances::Config for $name {
+				type Balance = u32;
+				type OnTransfer = ();
+				type OnReap = ();
+				const DECIMALS: u32 = 2;
+				const MAX_BALANCE: u32 = u32::MAX;
+			}
 
-	impl super::Config for TestConfig {
-		type Content = &'static str;
+			impl crate::system::Config for $name {
+				type AccountId = &'static str;
+				type BlockNumber = u32;
+				type Nonce = u32;
+				type Hashing = crate::support::Sha256;
+				type RuntimeEvent = crate::system::Event<Self>;
+				const BLOCK_HASH_COUNT: u32 = 256;
+				const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+			}
+		};
 	}
 
-	impl crate::system::Config for TestConfig {
-		type AccountId = &'static str;
-		type BlockNumber = u32;
-		type Nonce = u32;
+	test_config! {
+		TestConfig {
+			type Content = &'static str;
+			const MAX_TOTAL_CLAIMS: u32 = 3;
+			const CLAIM_LIFETIME: Option<u32> = Some(10);
+			const CLAIM_CONFIRMATIONS: u32 = 3;
+			const MIN_BALANCE_TO_CLAIM: u32 = 0;
+		}
+	}
+
+	test_config! {
+		/// A config where claims ignore the case of their content, and transfers must be accepted.
+		CaseInsensitiveConfig {
+			type Content = String;
+			const MAX_TOTAL_CLAIMS: u32 = 3;
+			const REQUIRE_TRANSFER_ACCEPTANCE: bool = true;
+			const CLAIM_CONFIRMATIONS: u32 = 0;
+			const MIN_BALANCE_TO_CLAIM: u32 = 0;
+
+			fn normalize(content: String) -> String {
+				content.to_lowercase()
+			}
+		}
+	}
+
+	test_config! {
+		/// A config which allows many claims, which never expire, with a deposit of one per byte
+		/// and a minimum balance to claim.
+		ManyClaimsConfig {
+			type Content = &'static str;
+			const MAX_TOTAL_CLAIMS: u32 = 100;
+			const CLAIM_CONFIRMATIONS: u32 = 0;
+			const MIN_BALANCE_TO_CLAIM: u32 = 5;
+
+			fn deposit_for(len: usize) -> u32 {
+				len as u32
+			}
+
+			fn claim_deposit(content: &&'static str) -> u32 {
+				Self::deposit_for(content.len())
+			}
+		}
 	}
 
 	#[test]
 	fn basic_proof_of_existence() {
 		let mut poe = super::Pallet::<TestConfig>::new();
 		assert_eq!(poe.get_claim(&"Hello, world!"), None);
-		assert_eq!(poe.create_claim("alice", "Hello, world!"), Ok(()));
+		assert_eq!(poe.create_claim("alice", "Hello, world!", None), Ok(()));
 		assert_eq!(poe.get_claim(&"Hello, world!"), Some(&"alice"));
 		assert_eq!(
-			poe.create_claim("bob", "Hello, world!"),
+			poe.create_claim("bob", "Hello, world!", None),
 			Err("This content is already claimed.")
 		);
 		assert_eq!(poe.revoke_claim("alice", "Hello, world!"), Ok(()));
-		assert_eq!(poe.create_claim("bob", "Hello, world!"), Ok(()));
+		assert_eq!(poe.create_claim("bob", "Hello, world!", None), Ok(()));
+	}
+
+	#[test]
+	fn create_claims_is_all_or_nothing() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		assert_eq!(poe.create_claim("bob", "b", None), Ok(()));
+
+		assert_eq!(
+			poe.create_claims("alice", vec!["a", "b", "c"]),
+			Err("This content is already claimed.")
+		);
+		assert_eq!(poe.get_claim(&"a"), None);
+		assert_eq!(poe.get_claim(&"b"), Some(&"bob"));
+		assert_eq!(poe.get_claim(&"c"), None);
+
+		assert_eq!(
+			poe.create_claims("alice", vec!["a", "c", "a"]),
+			Err("This content is already claimed.")
+		);
+		assert_eq!(poe.get_claim(&"a"), None);
+
+		assert_eq!(poe.create_claims("alice", vec!["a", "c"]), Ok(()));
+		assert_eq!(poe.get_claim(&"a"), Some(&"alice"));
+		assert_eq!(poe.get_claim(&"c"), Some(&"alice"));
+	}
+
+	#[test]
+	fn claims_with_prefix() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "book:a", None).unwrap();
+		poe.create_claim("bob", "book:b", None).unwrap();
+		poe.create_claim("alice", "doc:c", None).unwrap();
+
+		assert_eq!(poe.claims_with_prefix("book:"), vec![("book:a", &"alice"), ("book:b", &"bob")]);
+		assert_eq!(poe.claims_with_prefix("doc:"), vec![("doc:c", &"alice")]);
+		assert_eq!(poe.claims_with_prefix("image:"), vec![]);
+	}
+
+	#[test]
+	fn transfer_claim() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "Hello, world!", None).unwrap();
+		assert_eq!(
+			poe.transfer_claim("bob", "Hello, world!", "bob"),
+			Err("This content is owned by someone else.")
+		);
+		assert_eq!(poe.transfer_claim("alice", "Goodbye!", "bob"), Err("Claim does not exist."));
+		assert_eq!(poe.transfer_claim("alice", "Hello, world!", "bob"), Ok(()));
+		assert_eq!(poe.get_claim(&"Hello, world!"), Some(&"bob"));
+	}
+
+	#[test]
+	fn transfer_claim_requires_acceptance() {
+		let mut poe = super::Pallet::<CaseInsensitiveConfig>::new();
+		poe.create_claim("alice", "a".to_string(), None).unwrap();
+		assert_eq!(poe.transfer_claim("alice", "A".to_string(), "bob"), Ok(()));
+		assert_eq!(poe.get_claim(&"a".to_string()), Some(&"alice"));
+		assert_eq!(poe.pending_transfer(&"a".to_string()), Some(&"bob"));
+
+		assert_eq!(
+			poe.accept_claim("charlie", "a".to_string()),
+			Err("No pending transfer to the caller")
+		);
+		assert_eq!(poe.accept_claim("bob", "A".to_string()), Ok(()));
+		assert_eq!(poe.get_claim(&"a".to_string()), Some(&"bob"));
+		assert_eq!(poe.pending_transfer(&"a".to_string()), None);
+		assert_eq!(
+			poe.accept_claim("bob", "a".to_string()),
+			Err("No pending transfer to the caller")
+		);
+	}
+
+	#[test]
+	fn unaccepted_transfer_keeps_owner() {
+		let mut poe = super::Pallet::<CaseInsensitiveConfig>::new();
+		poe.create_claim("alice", "a".to_string(), None).unwrap();
+		poe.transfer_claim("alice", "a".to_string(), "bob").unwrap();
+		// A new proposal replaces the previous one.
+		poe.transfer_claim("alice", "a".to_string(), "charlie").unwrap();
+		assert_eq!(
+			poe.accept_claim("bob", "a".to_string()),
+			Err("No pending transfer to the caller")
+		);
+		assert_eq!(poe.get_claim(&"a".to_string()), Some(&"alice"));
+
+		// Revoking the claim cancels the pending transfer.
+		poe.revoke_claim("alice", "a".to_string()).unwrap();
+		assert_eq!(poe.pending_transfer(&"a".to_string()), None);
+		assert_eq!(
+			poe.accept_claim("charlie", "a".to_string()),
+			Err("No pending transfer to the caller")
+		);
+	}
+
+	#[test]
+	fn owner_at() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.on_initialize(1);
+		poe.create_claim("alice", "Hello, world!", None).unwrap();
+		poe.on_initialize(3);
+		poe.transfer_claim("alice", "Hello, world!", "bob").unwrap();
+		poe.on_initialize(5);
+		poe.revoke_claim("bob", "Hello, world!").unwrap();
+
+		assert_eq!(poe.owner_at(&"Hello, world!", 0), None);
+		assert_eq!(poe.owner_at(&"Hello, world!", 2), Some(&"alice"));
+		assert_eq!(poe.owner_at(&"Hello, world!", 3), Some(&"bob"));
+		assert_eq!(poe.owner_at(&"Hello, world!", 4), Some(&"bob"));
+		assert_eq!(poe.owner_at(&"Hello, world!", 5), None);
+		assert_eq!(poe.owner_at(&"Goodbye!", 4), None);
+	}
+
+	#[test]
+	fn is_claimed() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		assert!(!poe.is_claimed(&"Hello, world!"));
+		poe.create_claim("alice", "Hello, world!", None).unwrap();
+		assert!(poe.is_claimed(&"Hello, world!"));
+		poe.revoke_claim("alice", "Hello, world!").unwrap();
+		assert!(!poe.is_claimed(&"Hello, world!"));
+	}
+
+	#[test]
+	fn claims_snapshot() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "a", None).unwrap();
+		poe.create_claim("bob", "b", None).unwrap();
+
+		let snapshot = poe.claims_snapshot();
+		assert_eq!(snapshot, poe.iter_claims().map(|(c, o)| (*c, *o)).collect());
+
+		poe.revoke_claim("alice", "a").unwrap();
+		poe.create_claim("alice", "c", None).unwrap();
+		assert_eq!(snapshot.get("a"), Some(&"alice"));
+		assert_eq!(snapshot.get("c"), None);
+		assert_eq!(snapshot.len(), 2);
+	}
+
+	#[test]
+	fn lock_claim() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "Hello, world!", None).unwrap();
+
+		assert_eq!(
+			poe.lock_claim("bob", "Hello, world!"),
+			Err("This content is owned by someone else.")
+		);
+		assert!(!poe.is_locked(&"Hello, world!"));
+		assert_eq!(poe.lock_claim("alice", "Goodbye!"), Err("Claim does not exist."));
+
+		assert_eq!(poe.lock_claim("alice", "Hello, world!"), Ok(()));
+		assert!(poe.is_locked(&"Hello, world!"));
+		assert_eq!(poe.revoke_claim("alice", "Hello, world!"), Err("Claim locked"));
+		assert_eq!(poe.transfer_claim("alice", "Hello, world!", "bob"), Err("Claim locked"));
+		assert_eq!(poe.get_claim(&"Hello, world!"), Some(&"alice"));
+	}
+
+	#[test]
+	fn max_total_claims() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		assert_eq!(poe.create_claims("alice", vec!["a", "b"]), Ok(()));
+		assert_eq!(poe.create_claims("bob", vec!["c", "d"]), Err("Claim storage full"));
+		assert_eq!(poe.create_claim("bob", "c", None), Ok(()));
+		assert_eq!(poe.create_claim("bob", "d", None), Err("Claim storage full"));
+
+		// Revoking a claim frees some capacity.
+		assert_eq!(poe.revoke_claim("alice", "a"), Ok(()));
+		assert_eq!(poe.create_claim("bob", "d", None), Ok(()));
+		assert_eq!(poe.get_claim(&"d"), Some(&"bob"));
+	}
+
+	#[test]
+	fn normalize() {
+		let mut poe = super::Pallet::<CaseInsensitiveConfig>::new();
+		assert_eq!(poe.create_claim("alice", "Book".to_string(), None), Ok(()));
+		assert_eq!(
+			poe.create_claim("bob", "book".to_string(), None),
+			Err("This content is already claimed.")
+		);
+		assert_eq!(poe.get_claim(&"BOOK".to_string()), Some(&"alice"));
+		assert_eq!(poe.iter_claims().next(), Some((&"book".to_string(), &"alice")));
+
+		assert_eq!(poe.revoke_claim("alice", "bOoK".to_string()), Ok(()));
+		assert!(!poe.is_claimed(&"Book".to_string()));
+	}
+
+	#[test]
+	fn clear() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "a", None).unwrap();
+		poe.lock_claim("alice", "a").unwrap();
+
+		poe.clear();
+		assert_eq!(poe.iter_claims().count(), 0);
+		assert!(!poe.is_locked(&"a"));
+		assert_eq!(poe.owner_at(&"a", 0), None);
+	}
+
+	#[test]
+	fn claims_between() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		for (block, claim) in [(2, "a"), (4, "b"), (6, "c")] {
+			poe.on_initialize(block);
+			poe.create_claim("alice", claim, None).unwrap();
+		}
+		// Transferring a claim does not change when it was created.
+		poe.transfer_claim("alice", "b", "bob").unwrap();
+
+		assert_eq!(poe.created_at(&"b"), Some(4));
+		assert_eq!(poe.claims_between(3, 5), vec![(&"b", &"bob")]);
+		assert_eq!(poe.claims_between(2, 6).len(), 3);
+		assert_eq!(poe.claims_between(5, 3), vec![]);
+
+		// A revoked claim which is claimed again is created at the new block.
+		poe.on_initialize(7);
+		poe.revoke_claim("alice", "a").unwrap();
+		assert_eq!(poe.created_at(&"a"), None);
+		poe.create_claim("bob", "a", None).unwrap();
+		assert_eq!(poe.created_at(&"a"), Some(7));
+	}
+
+	#[test]
+	fn claims_expire() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.on_initialize(1);
+		poe.create_claim("alice", "a", None).unwrap();
+		poe.create_claim("alice", "b", None).unwrap();
+		poe.lock_claim("alice", "b").unwrap();
+		assert_eq!(poe.expires_at(&"a"), Some(11));
+
+		poe.on_initialize(11);
+		assert!(poe.is_claimed(&"a"));
+		poe.on_initialize(12);
+		assert!(!poe.is_claimed(&"a"));
+		assert_eq!(poe.expires_at(&"a"), None);
+		// Locked claims never expire.
+		assert!(poe.is_claimed(&"b"));
+	}
+
+	#[test]
+	fn renew_claim() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.on_initialize(1);
+		poe.create_claim("alice", "a", None).unwrap();
+
+		assert_eq!(poe.renew_claim("bob", "a", 5), Err("This content is owned by someone else."));
+		poe.on_initialize(11);
+		assert_eq!(poe.renew_claim("alice", "a", 5), Ok(()));
+		assert_eq!(poe.expires_at(&"a"), Some(16));
+
+		assert_eq!(poe.renew_claim("alice", "a", u32::MAX), Err("Expiry overflow"));
+		assert_eq!(poe.expires_at(&"a"), Some(16));
+
+		poe.on_initialize(17);
+		assert_eq!(poe.renew_claim("alice", "a", 5), Err("Claim does not exist."));
+		assert!(!poe.is_claimed(&"a"));
+		let expired = matches!(
+			poe.take_events().last(),
+			Some(super::Event::Expired { owner: "alice", claim: "a" })
+		);
+		assert!(expired);
+
+		// A claim whose expiry would overflow cannot be created.
+		poe.on_initialize(u32::MAX - 5);
+		assert_eq!(poe.create_claim("alice", "b", None), Err("Expiry overflow"));
+		assert!(!poe.is_claimed(&"b"));
+	}
+
+	#[test]
+	fn create_claim_for() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		assert_eq!(
+			poe.create_claim_for("bob", "alice", "a", None),
+			Err("Not a delegate of the owner")
+		);
+		assert_eq!(poe.get_claim(&"a"), None);
+
+		assert_eq!(poe.add_delegate("alice", "bob"), Ok(()));
+		assert!(poe.is_delegate(&"alice", &"bob"));
+		assert!(!poe.is_delegate(&"bob", &"alice"));
+		assert_eq!(poe.create_claim_for("bob", "alice", "a", None), Ok(()));
+		assert_eq!(poe.get_claim(&"a"), Some(&"alice"));
+		assert_eq!(
+			poe.create_claim_for("bob", "alice", "a", None),
+			Err("This content is already claimed.")
+		);
+
+		assert_eq!(poe.remove_delegate("alice", "bob"), Ok(()));
+		assert_eq!(poe.remove_delegate("alice", "bob"), Err("Not a delegate of the owner"));
+		assert_eq!(
+			poe.create_claim_for("bob", "alice", "b", None),
+			Err("Not a delegate of the owner")
+		);
+	}
+
+	#[test]
+	fn claims_by_type() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "cat.png", Some("image".to_string())).unwrap();
+		poe.create_claim("bob", "notes.txt", Some("text".to_string())).unwrap();
+		poe.create_claim("alice", "dog.png", Some("image".to_string())).unwrap();
+		assert_eq!(poe.claims_by_type("image"), vec![&"cat.png", &"dog.png"]);
+		assert_eq!(poe.claims_by_type("text"), vec![&"notes.txt"]);
+		assert_eq!(poe.claims_by_type("video"), Vec::<&&str>::new());
+		assert_eq!(poe.content_type(&"notes.txt"), Some("text"));
+
+		// The content type goes away with the claim.
+		poe.revoke_claim("bob", "notes.txt").unwrap();
+		assert_eq!(poe.claims_by_type("text"), Vec::<&&str>::new());
+		assert_eq!(poe.content_type(&"notes.txt"), None);
+	}
+
+	#[test]
+	fn claims_by_creation() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "c", None).unwrap();
+		poe.create_claim("bob", "a", None).unwrap();
+		poe.create_claim("alice", "b", None).unwrap();
+		assert_eq!(poe.claims_by_creation(), vec![&"c", &"a", &"b"]);
+
+		// Transferring a claim keeps its position, claiming it again moves it to the end.
+		poe.transfer_claim("alice", "c", "bob").unwrap();
+		poe.revoke_claim("bob", "a").unwrap();
+		poe.create_claim("alice", "a", None).unwrap();
+		assert_eq!(poe.claims_by_creation(), vec![&"c", &"b", &"a"]);
+	}
+
+	#[test]
+	fn revoke_all() {
+		let mut poe = super::Pallet::<ManyClaimsConfig>::new();
+		for claim in ["a", "b", "c"] {
+			poe.create_claim("alice", claim, None).unwrap();
+		}
+		poe.create_claim("bob", "d", None).unwrap();
+		poe.take_events();
+
+		assert_eq!(poe.revoke_all("alice"), 3);
+		let claims: Vec<_> = poe.iter_claims().collect();
+		assert_eq!(claims, vec![(&"d", &"bob")]);
+		let events = poe.take_events();
+		assert_eq!(events.len(), 3);
+		for (event, claim) in events.iter().zip(["a", "b", "c"]) {
+			assert!(matches!(
+				event,
+				super::Event::Revoked { owner: "alice", claim: revoked } if *revoked == claim
+			));
+		}
+		assert_eq!(poe.revoke_all("alice"), 0);
+		assert!(poe.take_events().is_empty());
+
+		// Locked claims are kept.
+		poe.lock_claim("bob", "d").unwrap();
+		assert_eq!(poe.revoke_all("bob"), 0);
+		assert_eq!(poe.get_claim(&"d"), Some(&"bob"));
+	}
+
+	#[test]
+	fn is_final_claim() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.on_initialize(5);
+		poe.create_claim("alice", "claim", None).unwrap();
+
+		// The claim is pending for `CLAIM_CONFIRMATIONS` blocks after its creation.
+		assert!(!poe.is_final_claim(&"claim", 5));
+		assert!(!poe.is_final_claim(&"claim", 7));
+		assert!(poe.is_final_claim(&"claim", 8));
+		// Transfers do not change when a claim is final.
+		poe.on_initialize(8);
+		poe.transfer_claim("alice", "claim", "bob").unwrap();
+		assert!(poe.is_final_claim(&"claim", 8));
+		assert!(!poe.is_final_claim(&"unclaimed", 8));
+	}
+
+	#[test]
+	fn leave_claim() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "shared", None).unwrap();
+		assert_eq!(
+			poe.add_owner("bob", "shared", "charlie"),
+			Err("This content is owned by someone else.")
+		);
+		assert_eq!(poe.add_owner("alice", "shared", "bob"), Ok(()));
+		assert_eq!(poe.add_owner("alice", "shared", "bob"), Err("Already an owner"));
+		assert_eq!(poe.owners_of(&"shared"), vec![&"alice", &"bob"]);
+
+		assert_eq!(
+			poe.leave_claim("charlie", "shared"),
+			Err("This content is owned by someone else.")
+		);
+		// One of two owners leaving keeps the claim, which the other owner takes over.
+		assert_eq!(poe.leave_claim("alice", "shared"), Ok(()));
+		assert_eq!(poe.get_claim(&"shared"), Some(&"bob"));
+		assert_eq!(poe.owners_of(&"shared"), vec![&"bob"]);
+		assert_eq!(poe.claim_count_of(&"alice"), 0);
+		assert_eq!(poe.claim_count_of(&"bob"), 1);
+
+		// The last owner leaving removes the claim.
+		assert_eq!(poe.leave_claim("bob", "shared"), Ok(()));
+		assert_eq!(poe.get_claim(&"shared"), None);
+		assert!(poe.owners_of(&"shared").is_empty());
+		assert_eq!(poe.leave_claim("bob", "shared"), Err("Claim does not exist."));
+
+		// A co-owner who is not the owner of the claim leaves it without changing its owner.
+		poe.create_claim("alice", "other", None).unwrap();
+		poe.add_owner("alice", "other", "bob").unwrap();
+		assert_eq!(poe.leave_claim("bob", "other"), Ok(()));
+		assert_eq!(poe.owners_of(&"other"), vec![&"alice"]);
+		assert_eq!(poe.get_claim(&"other"), Some(&"alice"));
+	}
+
+	#[test]
+	fn claim_count_of() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		assert_eq!(poe.claim_count_of(&"alice"), 0);
+		poe.create_claim("alice", "a", None).unwrap();
+		poe.create_claim("alice", "b", None).unwrap();
+		poe.create_claim("bob", "c", None).unwrap();
+		assert_eq!(poe.claim_count_of(&"alice"), 2);
+		assert_eq!(poe.claim_count_of(&"bob"), 1);
+
+		poe.transfer_claim("alice", "a", "bob").unwrap();
+		assert_eq!(poe.claim_count_of(&"alice"), 1);
+		assert_eq!(poe.claim_count_of(&"bob"), 2);
+
+		// Updating the content of a claim keeps its owner.
+		let id = poe.claim_id(&"b").unwrap();
+		poe.update_content("alice", id, "d").unwrap();
+		assert_eq!(poe.claim_count_of(&"alice"), 1);
+
+		poe.revoke_claim("alice", "d").unwrap();
+		poe.revoke_claim("bob", "a").unwrap();
+		assert_eq!(poe.claim_count_of(&"alice"), 0);
+		assert_eq!(poe.claim_count_of(&"bob"), 1);
+
+		// A failed creation does not change any count.
+		assert!(poe.create_claim("alice", "c", None).is_err());
+		assert_eq!(poe.claim_count_of(&"alice"), 0);
+	}
+
+	#[test]
+	fn update_content() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "draft", Some("text".to_string())).unwrap();
+		poe.create_claim("bob", "other", None).unwrap();
+		let id = poe.claim_id(&"draft").unwrap();
+
+		poe.on_initialize(1);
+		assert_eq!(poe.update_content("alice", id, "final"), Ok(()));
+		assert_eq!(poe.claim_by_id(id), Some(&"final"));
+		assert_eq!(poe.claim_id(&"final"), Some(id));
+		assert_eq!(poe.get_claim(&"draft"), None);
+		assert_eq!(poe.get_claim(&"final"), Some(&"alice"));
+		assert_eq!(poe.content_type(&"final"), Some("text"));
+		assert_eq!(poe.expires_at(&"final"), Some(10));
+		assert_eq!(poe.owner_at(&"draft", 0), Some(&"alice"));
+		assert_eq!(poe.owner_at(&"draft", 1), None);
+		assert_eq!(poe.claims_by_creation(), vec![&"final", &"other"]);
+
+		// The old content can be claimed again, as a new claim.
+		assert_eq!(poe.create_claim("bob", "draft", None), Ok(()));
+		assert_ne!(poe.claim_id(&"draft"), Some(id));
+
+		// The id of a revoked claim no longer refers to any content.
+		poe.revoke_claim("alice", "final").unwrap();
+		assert_eq!(poe.claim_by_id(id), None);
+	}
+
+	#[test]
+	fn update_content_errors() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		poe.create_claim("alice", "a", None).unwrap();
+		poe.create_claim("bob", "b", None).unwrap();
+		let id = poe.claim_id(&"a").unwrap();
+
+		assert_eq!(
+			poe.update_content("bob", id, "c"),
+			Err("This content is owned by someone else.")
+		);
+		assert_eq!(poe.update_content("alice", 42, "c"), Err("Claim does not exist."));
+		assert_eq!(poe.update_content("alice", id, "b"), Err("This content is already claimed."));
+		poe.lock_claim("alice", "a").unwrap();
+		assert_eq!(poe.update_content("alice", id, "c"), Err("Claim locked"));
+		assert_eq!(poe.get_claim(&"a"), Some(&"alice"));
+	}
+
+	#[test]
+	fn claim_deposits() {
+		use super::DepositChange::{Release, Reserve};
+
+		let mut poe = super::Pallet::<ManyClaimsConfig>::new();
+		poe.create_claim("alice", "abc", None).unwrap();
+		assert_eq!(poe.deposit_of(&"abc"), Some((&"alice", 3)));
+		assert!(matches!(
+			poe.take_deposit_changes().as_slice(),
+			[Reserve { who: "alice", amount: 3, claim: "abc" }]
+		));
+
+		// The deposit moves along with the claim.
+		poe.transfer_claim("alice", "abc", "bob").unwrap();
+		assert_eq!(poe.deposit_of(&"abc"), Some((&"bob", 3)));
+		assert!(matches!(
+			poe.take_deposit_changes().as_slice(),
+			[Release { who: "alice", amount: 3 }, Reserve { who: "bob", amount: 3, claim: "abc" }]
+		));
+
+		// Only the difference is reserved or released when the content changes.
+		let id = poe.claim_id(&"abc").unwrap();
+		poe.update_content("bob", id, "abcde").unwrap();
+		assert_eq!(poe.deposit_of(&"abcde"), Some((&"bob", 5)));
+		assert!(matches!(
+			poe.take_deposit_changes().as_slice(),
+			[Reserve { who: "bob", amount: 2, claim: "abcde" }]
+		));
+		poe.update_content("bob", id, "a").unwrap();
+		assert_eq!(poe.deposit_of(&"a"), Some((&"bob", 1)));
+		assert!(matches!(
+			poe.take_deposit_changes().as_slice(),
+			[Release { who: "bob", amount: 4 }]
+		));
+
+		// Revoking the claim releases the deposit.
+		poe.revoke_claim("bob", "a").unwrap();
+		assert_eq!(poe.deposit_of(&"a"), None);
+		assert!(matches!(
+			poe.take_deposit_changes().as_slice(),
+			[Release { who: "bob", amount: 1 }]
+		));
+
+		poe.create_claims("carol", vec!["xy", "z"]).unwrap();
+		poe.take_deposit_changes();
+		assert_eq!(poe.revoke_all("carol"), 2);
+		assert!(matches!(
+			poe.take_deposit_changes().as_slice(),
+			[Release { who: "carol", amount: 2 }, Release { who: "carol", amount: 1 }]
+		));
+
+		// A deposit which could not be reserved is not released later.
+		poe.create_claim("dave", "dave", None).unwrap();
+		poe.take_deposit_changes();
+		poe.forget_deposit(&"dave", 4);
+		poe.revoke_claim("dave", "dave").unwrap();
+		assert!(poe.take_deposit_changes().is_empty());
+	}
+
+	#[test]
+	fn check_funds() {
+		let poe = super::Pallet::<ManyClaimsConfig>::new();
+		let balance = |who: &&str| if *who == "alice" { 5 } else { 4 };
+		let create = super::Call::create_claim { claim: "abc", content_type: None };
+		assert_eq!(poe.check_funds(&"alice", &create, balance), Ok(()));
+		assert_eq!(poe.check_funds(&"bob", &create, balance), Err("Insufficient balance to claim"));
+		// The owner of a claim created by a delegate needs the funds, not the delegate.
+		let create_for =
+			super::Call::create_claim_for { owner: "bob", claim: "abc", content_type: None };
+		assert_eq!(
+			poe.check_funds(&"alice", &create_for, balance),
+			Err("Insufficient balance to claim")
+		);
+		let large = super::Call::create_claim { claim: "abcdef", content_type: None };
+		assert_eq!(
+			poe.check_funds(&"alice", &large, balance),
+			Err("Insufficient balance for the claim deposit")
+		);
+	}
+
+	#[test]
+	fn deposit_needed() {
+		let mut poe = super::Pallet::<ManyClaimsConfig>::new();
+		let create = super::Call::create_claim { claim: "abc", content_type: None };
+		assert!(matches!(poe.deposit_needed(&"alice", &create), Some(("alice", 3))));
+		let claims = super::Call::create_claims { claims: vec!["ab", "c"] };
+		assert!(matches!(poe.deposit_needed(&"alice", &claims), Some(("alice", 3))));
+
+		poe.create_claim("alice", "abc", None).unwrap();
+		let transfer = super::Call::transfer_claim { claim: "abc", to: "bob" };
+		assert!(matches!(poe.deposit_needed(&"alice", &transfer), Some(("bob", 3))));
+		let id = poe.claim_id(&"abc").unwrap();
+		let grow = super::Call::update_content { claim_id: id, new_content: "abcd" };
+		assert!(matches!(poe.deposit_needed(&"alice", &grow), Some(("alice", 1))));
+		let shrink = super::Call::update_content { claim_id: id, new_content: "ab" };
+		assert!(poe.deposit_needed(&"alice", &shrink).is_none());
+		let revoke = super::Call::revoke_claim { claim: "abc" };
+		assert!(poe.deposit_needed(&"alice", &revoke).is_none());
 	}
 }
@@ -0,0 +1,303 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::support::{DepositEvent, DispatchResult, Encode, EventBuffer, GetWeight};
+
+pub trait Config: crate::system::Config {
+	/// The type of the calls which a rescuer can make on behalf of an account they recovered.
+	/// This is usually the `RuntimeCall` of the runtime, so that any call can be made.
+	type RuntimeCall: GetWeight;
+}
+
+/// The events emitted by the Recovery Module.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T: Config> {
+	/// `who` can now be recovered by `threshold` of their friends.
+	RecoveryCreated { who: T::AccountId, threshold: u32 },
+	/// `friend` vouched for `rescuer` to recover `lost`.
+	Vouched { friend: T::AccountId, lost: T::AccountId, rescuer: T::AccountId },
+	/// `rescuer` now controls `lost`.
+	AccountRecovered { lost: T::AccountId, rescuer: T::AccountId },
+}
+
+/// The friends who can recover an account, and how many of them are needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryConfig<AccountId> {
+	pub friends: BTreeSet<AccountId>,
+	pub threshold: u32,
+}
+
+impl<AccountId: Encode> Encode for RecoveryConfig<AccountId> {
+	fn encode(&self) -> Vec<u8> {
+		(&self.friends, self.threshold).encode()
+	}
+}
+
+/// This is the Recovery Module.
+/// It is a simple module which allows an account to designate friends, who can collectively give
+/// control of the account to a rescuer if it is lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pallet<T: Config> {
+	/// The recovery config of each account which can be recovered.
+	configs: BTreeMap<T::AccountId, RecoveryConfig<T::AccountId>>,
+	/// The friends who vouched for each `(lost, rescuer)` pair.
+	#[allow(clippy::type_complexity)]
+	vouches: BTreeMap<(T::AccountId, T::AccountId), BTreeSet<T::AccountId>>,
+	/// A map from each recovered account to the account which now controls it.
+	recovered: BTreeMap<T::AccountId, T::AccountId>,
+	/// The calls made by rescuers on behalf of the accounts they recovered, along with the
+	/// recovered account, which have not been dispatched by the runtime yet.
+	calls: Vec<(T::AccountId, T::RuntimeCall)>,
+	/// The events deposited by this pallet which have not been collected by the runtime yet.
+	events: EventBuffer<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Recovery Module.
+	pub fn new() -> Self {
+		Self {
+			configs: BTreeMap::new(),
+			vouches: BTreeMap::new(),
+			recovered: BTreeMap::new(),
+			calls: Vec::new(),
+			events: EventBuffer::new(),
+		}
+	}
+
+	/// Clear all the recovery configs, vouches and recovered accounts.
+	pub fn clear(&mut self) {
+		self.configs.clear();
+		self.vouches.clear();
+		self.recovered.clear();
+		self.calls.clear();
+	}
+
+	/// Take all the events deposited by this pallet since they were last taken.
+	pub fn take_events(&mut self) -> Vec<Event<T>> {
+		self.events.take()
+	}
+
+	/// Take all the calls made with `as_recovered` since they were last taken, along with the
+	/// account on behalf of which each of them must be dispatched. The runtime must dispatch them
+	/// right after the `as_recovered` call which made them.
+	pub fn take_calls(&mut self) -> Vec<(T::AccountId, T::RuntimeCall)> {
+		core::mem::take(&mut self.calls)
+	}
+
+	/// Get the recovery config of `who`, if they can be recovered.
+	pub fn recovery_config(&self, who: &T::AccountId) -> Option<&RecoveryConfig<T::AccountId>> {
+		self.configs.get(who)
+	}
+
+	/// Get the account which controls `lost`, if it was recovered.
+	pub fn recovered_by(&self, lost: &T::AccountId) -> Option<&T::AccountId> {
+		self.recovered.get(lost)
+	}
+}
+
+/// Functions which need the accounts to be encoded.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+{
+	/// Get the hash of the recovery configs, the vouches and the recovered accounts, so that they
+	/// can be covered by the state root.
+	pub fn state_hash(&self) -> [u8; 32] {
+		let mut bytes = self.configs.encode();
+		bytes.extend(self.vouches.encode());
+		bytes.extend(self.recovered.encode());
+		crate::system::Pallet::<T>::hash(&bytes)
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Allow the `caller` to be recovered by `threshold` of their `friends`.
+	/// This returns an error if the caller can already be recovered, or if the threshold is zero
+	/// or larger than the number of distinct friends.
+	pub fn create_recovery(
+		&mut self,
+		caller: T::AccountId,
+		friends: Vec<T::AccountId>,
+		threshold: u32,
+	) -> DispatchResult {
+		if self.configs.contains_key(&caller) {
+			return Err("Recovery already configured");
+		}
+		let friends: BTreeSet<_> = friends.into_iter().collect();
+		if threshold == 0 || threshold as usize > friends.len() {
+			return Err("Invalid recovery threshold");
+		}
+		self.configs.insert(caller.clone(), RecoveryConfig { friends, threshold });
+		self.events.deposit_event(Event::RecoveryCreated { who: caller, threshold });
+		Ok(())
+	}
+
+	/// Vouch, as the `caller`, for `rescuer` to take control of the `lost` account.
+	/// This returns an error if `lost` cannot be recovered, or if the caller is not one of its
+	/// friends. Vouching twice for the same rescuer does nothing.
+	pub fn vouch(
+		&mut self,
+		caller: T::AccountId,
+		lost: T::AccountId,
+		rescuer: T::AccountId,
+	) -> DispatchResult {
+		let config = self.configs.get(&lost).ok_or("Recovery not configured")?;
+		if !config.friends.contains(&caller) {
+			return Err("Not a friend of the lost account");
+		}
+		let vouches = self.vouches.entry((lost.clone(), rescuer.clone())).or_default();
+		if vouches.insert(caller.clone()) {
+			self.events.deposit_event(Event::Vouched { friend: caller, lost, rescuer });
+		}
+		Ok(())
+	}
+
+	/// Take control of the `lost` account as the `caller`, once enough of its friends vouched for
+	/// the caller. The vouches are then used up. This returns an error if `lost` was already
+	/// recovered.
+	pub fn claim_recovery(&mut self, caller: T::AccountId, lost: T::AccountId) -> DispatchResult {
+		let config = self.configs.get(&lost).ok_or("Recovery not configured")?;
+		if self.recovered.contains_key(&lost) {
+			return Err("Account already recovered");
+		}
+		let key = (lost.clone(), caller.clone());
+		let vouches = self.vouches.get(&key).map_or(0, |vouches| vouches.len());
+		if vouches < config.threshold as usize {
+			return Err("Not enough vouches");
+		}
+		self.vouches.remove(&key);
+		self.recovered.insert(lost.clone(), caller.clone());
+		self.events.deposit_event(Event::AccountRecovered { lost, rescuer: caller });
+		Ok(())
+	}
+
+	/// Make `call` on behalf of the `lost` account, as the `caller` which recovered it. The call is
+	/// dispatched by the runtime right after this one, see `take_calls`.
+	// The call must be boxed, since it is usually a `RuntimeCall` which contains this very call.
+	#[allow(clippy::boxed_local)]
+	pub fn as_recovered(
+		&mut self,
+		caller: T::AccountId,
+		lost: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		if self.recovered.get(&lost) != Some(&caller) {
+			return Err("Not the rescuer of the account");
+		}
+		self.calls.push((lost, *call));
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::GetWeight for Call<T> {
+	fn weight(&self) -> crate::support::Weight {
+		match self {
+			Call::create_recovery { friends, .. } => 5 + friends.len() as crate::support::Weight,
+			Call::vouch { .. } => 5,
+			Call::claim_recovery { .. } => 10,
+			// The call made on behalf of the lost account is dispatched along with this one.
+			Call::as_recovered { call, .. } => 5 + call.weight(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[derive(Debug, PartialEq)]
+	struct TestConfig;
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = crate::system::Call<Self>;
+	}
+
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Hashing = crate::support::Sha256;
+		type RuntimeEvent = crate::system::Event<Self>;
+		const BLOCK_HASH_COUNT: u32 = 256;
+		const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+	}
+
+	#[test]
+	fn create_recovery() {
+		let mut recovery = super::Pallet::<TestConfig>::new();
+		assert_eq!(
+			recovery.create_recovery("alice", vec!["bob", "bob"], 2),
+			Err("Invalid recovery threshold")
+		);
+		assert_eq!(
+			recovery.create_recovery("alice", vec!["bob"], 0),
+			Err("Invalid recovery threshold")
+		);
+		assert_eq!(recovery.create_recovery("alice", vec!["bob", "charlie"], 2), Ok(()));
+		assert_eq!(
+			recovery.create_recovery("alice", vec!["bob"], 1),
+			Err("Recovery already configured")
+		);
+		assert_eq!(recovery.recovery_config(&"alice").map(|config| config.threshold), Some(2));
+	}
+
+	#[test]
+	fn recovery_after_enough_vouches() {
+		let mut recovery = super::Pallet::<TestConfig>::new();
+		recovery.create_recovery("alice", vec!["bob", "charlie", "dave"], 2).unwrap();
+
+		assert_eq!(recovery.vouch("bob", "alice", "eve"), Ok(()));
+		// Vouching twice does not count twice.
+		assert_eq!(recovery.vouch("bob", "alice", "eve"), Ok(()));
+		assert_eq!(recovery.claim_recovery("eve", "alice"), Err("Not enough vouches"));
+		assert_eq!(recovery.recovered_by(&"alice"), None);
+
+		assert_eq!(recovery.vouch("charlie", "alice", "eve"), Ok(()));
+		assert_eq!(recovery.claim_recovery("eve", "alice"), Ok(()));
+		assert_eq!(recovery.recovered_by(&"alice"), Some(&"eve"));
+
+		// The account cannot be recovered again, even by another rescuer.
+		recovery.vouch("bob", "alice", "mallory").unwrap();
+		recovery.vouch("charlie", "alice", "mallory").unwrap();
+		assert_eq!(recovery.claim_recovery("mallory", "alice"), Err("Account already recovered"));
+		assert_eq!(recovery.recovered_by(&"alice"), Some(&"eve"));
+	}
+
+	#[test]
+	fn as_recovered() {
+		let mut recovery = super::Pallet::<TestConfig>::new();
+		recovery.create_recovery("alice", vec!["bob"], 1).unwrap();
+		let remark = || Box::new(crate::system::Call::remark { message: vec![1] });
+		assert_eq!(
+			recovery.as_recovered("eve", "alice", remark()),
+			Err("Not the rescuer of the account")
+		);
+
+		recovery.vouch("bob", "alice", "eve").unwrap();
+		recovery.claim_recovery("eve", "alice").unwrap();
+		assert_eq!(
+			recovery.as_recovered("mallory", "alice", remark()),
+			Err("Not the rescuer of the account")
+		);
+		assert_eq!(recovery.as_recovered("eve", "alice", remark()), Ok(()));
+		assert_eq!(recovery.take_calls(), vec![("alice", *remark())]);
+		assert!(recovery.take_calls().is_empty());
+	}
+
+	#[test]
+	fn claim_recovery_below_threshold() {
+		let mut recovery = super::Pallet::<TestConfig>::new();
+		assert_eq!(recovery.vouch("bob", "alice", "eve"), Err("Recovery not configured"));
+		assert_eq!(recovery.claim_recovery("eve", "alice"), Err("Recovery not configured"));
+
+		recovery.create_recovery("alice", vec!["bob", "charlie"], 2).unwrap();
+		assert_eq!(
+			recovery.vouch("mallory", "alice", "eve"),
+			Err("Not a friend of the lost account")
+		);
+		recovery.vouch("bob", "alice", "eve").unwrap();
+		// Vouches only count for the rescuer they were made for.
+		recovery.vouch("charlie", "alice", "mallory").unwrap();
+		assert_eq!(recovery.claim_recovery("eve", "alice"), Err("Not enough vouches"));
+		assert_eq!(recovery.claim_recovery("mallory", "alice"), Err("Not enough vouches"));
+		assert_eq!(recovery.recovered_by(&"alice"), None);
+	}
+}
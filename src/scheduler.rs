@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use crate::support::{DepositEvent, DispatchResult, Encode, EventBuffer, GetWeight};
+
+pub trait Config: crate::system::Config {
+	/// The type of the calls which can be scheduled. This is usually the `RuntimeCall` of the
+	/// runtime, so that any call can be scheduled.
+	type RuntimeCall: GetWeight;
+	/// The maximum number of scheduled calls dispatched in a single block. The calls which are
+	/// due beyond it are dispatched in the next blocks.
+	const MAX_SCHEDULED_PER_BLOCK: u32;
+}
+
+/// The events emitted by the Scheduler Module.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T: Config> {
+	/// `who` scheduled a call to be dispatched at block `when`.
+	Scheduled { who: T::AccountId, when: T::BlockNumber },
+}
+
+/// This is the Scheduler Module.
+/// It is a simple module which allows accounts to schedule a call to be dispatched on their behalf
+/// at a future block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pallet<T: Config> {
+	/// A map from a block number to the calls scheduled at that block, along with the account on
+	/// behalf of which each call is dispatched. Calls are dispatched in the order they were
+	/// scheduled.
+	#[allow(clippy::type_complexity)]
+	agenda: BTreeMap<T::BlockNumber, Vec<(T::AccountId, T::RuntimeCall)>>,
+	/// The events deposited by this pallet which have not been collected by the runtime yet.
+	events: EventBuffer<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Scheduler Module.
+	pub fn new() -> Self {
+		Self { agenda: BTreeMap::new(), events: EventBuffer::new() }
+	}
+
+	/// Clear all the scheduled calls.
+	pub fn clear(&mut self) {
+		self.agenda.clear();
+	}
+
+	/// Take all the events deposited by this pallet since they were last taken.
+	pub fn take_events(&mut self) -> Vec<Event<T>> {
+		self.events.take()
+	}
+
+	/// Remove and return the calls which are due at block `now`, up to `MAX_SCHEDULED_PER_BLOCK`
+	/// of them, in the order they are due. This includes calls scheduled at earlier blocks which
+	/// have not been dispatched yet, so that no call is left behind.
+	pub fn take_agenda(&mut self, now: T::BlockNumber) -> Vec<(T::AccountId, T::RuntimeCall)> {
+		let max = T::MAX_SCHEDULED_PER_BLOCK as usize;
+		let mut due = Vec::new();
+		while due.len() < max {
+			let Some(mut entry) = self.agenda.first_entry() else {
+				break;
+			};
+			if *entry.key() > now {
+				break;
+			}
+			let calls = entry.get_mut();
+			let count = calls.len().min(max - due.len());
+			due.extend(calls.drain(..count));
+			if calls.is_empty() {
+				entry.remove();
+			}
+		}
+		due
+	}
+
+	/// Cancel the call at `index` among the calls scheduled at block `when`, in the order they were
+	/// scheduled. The calls scheduled after it at the same block move down by one index.
+	pub fn cancel(&mut self, when: T::BlockNumber, index: usize) -> DispatchResult {
+		let calls = self.agenda.get_mut(&when).ok_or("No such scheduled call")?;
+		if index >= calls.len() {
+			return Err("No such scheduled call");
+		}
+		calls.remove(index);
+		if calls.is_empty() {
+			self.agenda.remove(&when);
+		}
+		Ok(())
+	}
+
+	/// Get the number of calls scheduled at each block which has not been executed yet, ordered
+	/// by block number.
+	pub fn pending(&self) -> Vec<(T::BlockNumber, usize)> {
+		self.agenda.iter().map(|(when, calls)| (*when, calls.len())).collect()
+	}
+}
+
+/// Functions which need the scheduled calls to be encoded.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::BlockNumber: Encode,
+	T::RuntimeCall: Encode,
+{
+	/// Get the hash of the agenda, so that the scheduled calls can be covered by the state root.
+	pub fn agenda_hash(&self) -> [u8; 32] {
+		crate::system::Pallet::<T>::hash(&self.agenda.encode())
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Schedule a `call` to be dispatched on behalf of the `caller` at block `when`.
+	/// If `when` is not in the future, the call is dispatched at the start of the next block.
+	// The call must be boxed, since it is usually a `RuntimeCall` which contains this very call.
+	#[allow(clippy::boxed_local)]
+	pub fn schedule(
+		&mut self,
+		caller: T::AccountId,
+		when: T::BlockNumber,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		self.agenda.entry(when).or_default().push((caller.clone(), *call));
+		self.events.deposit_event(Event::Scheduled { who: caller, when });
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::GetWeight for Call<T> {
+	fn weight(&self) -> crate::support::Weight {
+		// The scheduled call is dispatched outside of any extrinsic, so its weight is paid when it
+		// is scheduled.
+		match self {
+			Call::schedule { call, .. } => 5 + call.weight(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::system::Call;
+
+	#[derive(Debug, PartialEq)]
+	struct TestConfig;
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = Call<Self>;
+		const MAX_SCHEDULED_PER_BLOCK: u32 = 2;
+	}
+
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Hashing = crate::support::Sha256;
+		type RuntimeEvent = crate::system::Event<Self>;
+		const BLOCK_HASH_COUNT: u32 = 256;
+		const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+	}
+
+	/// A call which can be told apart from the others by its `message`.
+	fn remark(message: &str) -> Box<Call<TestConfig>> {
+		Box::new(Call::remark { message: message.as_bytes().to_vec() })
+	}
+
+	#[test]
+	fn pending_scheduled_calls() {
+		let mut scheduler = super::Pallet::<TestConfig>::new();
+		assert_eq!(scheduler.pending(), vec![]);
+
+		scheduler.schedule("alice", 5, remark("a")).unwrap();
+		scheduler.schedule("bob", 3, remark("b")).unwrap();
+		scheduler.schedule("alice", 5, remark("c")).unwrap();
+		assert_eq!(scheduler.pending(), vec![(3, 1), (5, 2)]);
+
+		assert_eq!(scheduler.take_agenda(2), vec![]);
+		assert_eq!(scheduler.take_agenda(3), vec![("bob", *remark("b"))]);
+		assert_eq!(scheduler.pending(), vec![(5, 2)]);
+
+		assert_eq!(
+			scheduler.take_agenda(6),
+			vec![("alice", *remark("a")), ("alice", *remark("c"))]
+		);
+		assert_eq!(scheduler.pending(), vec![]);
+	}
+
+	#[test]
+	fn take_agenda_is_capped() {
+		let mut scheduler = super::Pallet::<TestConfig>::new();
+		scheduler.schedule("alice", 5, remark("a")).unwrap();
+		scheduler.schedule("bob", 3, remark("b")).unwrap();
+		scheduler.schedule("alice", 5, remark("c")).unwrap();
+
+		// Only `MAX_SCHEDULED_PER_BLOCK` calls are taken, the rest stay due.
+		assert_eq!(scheduler.take_agenda(6), vec![("bob", *remark("b")), ("alice", *remark("a"))]);
+		assert_eq!(scheduler.pending(), vec![(5, 1)]);
+		assert_eq!(scheduler.take_agenda(7), vec![("alice", *remark("c"))]);
+		assert_eq!(scheduler.pending(), vec![]);
+	}
+
+	#[test]
+	fn schedule_weight() {
+		use crate::support::GetWeight;
+
+		let call = super::Call::<TestConfig>::schedule { when: 5, call: remark("a") };
+		assert_eq!(call.weight(), 5 + remark("a").weight());
+	}
+
+	#[test]
+	fn clear() {
+		let mut scheduler = super::Pallet::<TestConfig>::new();
+		scheduler.schedule("alice", 5, remark("a")).unwrap();
+		scheduler.clear();
+		assert_eq!(scheduler.pending(), vec![]);
+	}
+}
@@ -1,4 +1,18 @@
+use core::{
+	fmt, iter,
+	ops::{Add, Div, Mul, Rem, Sub},
+};
+use num::{
+	traits::SaturatingMul, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Saturating, Zero,
+};
+use std::{
+	cell::RefCell,
+	collections::{BTreeMap, BTreeSet},
+	rc::Rc,
+};
+
 /// The most primitive representation of a Blockchain block.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Block<Header, Extrinsic> {
 	/// The block header contains metadata about the block.
 	pub header: Header,
@@ -6,22 +20,375 @@ pub struct Block<Header, Extrinsic> {
 	pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
+impl<Header: Encode, Extrinsic: Encode> Encode for Block<Header, Extrinsic> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.header.encode();
+		bytes.extend(self.extrinsics.encode());
+		bytes
+	}
+}
+
+impl<Header: Decode, Extrinsic: Decode> Decode for Block<Header, Extrinsic> {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self { header: Decode::decode(input)?, extrinsics: Decode::decode(input)? })
+	}
+}
+
+/// We are using an extremely simplified header which only contains the current block number, the
+/// hash of the parent block, and the author of the block.
 /// On a real blockchain, you would expect to also find:
-/// - parent block hash
 /// - state root
 /// - extrinsics root
 /// - etc...
-pub struct Header<BlockNumber> {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header<BlockNumber, AccountId> {
 	pub block_number: BlockNumber,
+	/// The hash of the previous block. The first block after genesis uses a zero hash.
+	pub parent_hash: [u8; 32],
+	/// The account which produced this block, and receives the block reward.
+	pub author: AccountId,
+}
+
+impl<BlockNumber: Encode, AccountId: Encode> Encode for Header<BlockNumber, AccountId> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.block_number.encode();
+		bytes.extend(self.parent_hash.encode());
+		bytes.extend(self.author.encode());
+		bytes
+	}
+}
+
+impl<BlockNumber: Decode, AccountId: Decode> Decode for Header<BlockNumber, AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self {
+			block_number: Decode::decode(input)?,
+			parent_hash: Decode::decode(input)?,
+			author: Decode::decode(input)?,
+		})
+	}
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
 /// This simplified version of an extrinsic tells us who is making the call, and which call they are
 /// making.
-pub struct Extrinsic<Caller, Call> {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extrinsic<Caller, Call, Nonce, Tip, BlockNumber> {
 	pub caller: Caller,
 	pub call: Call,
+	/// The nonce of the caller when they made this extrinsic: the number of extrinsics they made
+	/// before it.
+	pub nonce: Nonce,
+	/// An extra amount the caller offers so that their extrinsic is prioritized. For now, the tip
+	/// is only used to order the extrinsics of a block, see `OrderingPolicy`.
+	pub tip: Tip,
+	/// The first block in which this extrinsic can be executed, if any.
+	pub valid_from: Option<BlockNumber>,
+	/// The last block in which this extrinsic can be executed, if any. This prevents an extrinsic
+	/// from being replayed indefinitely.
+	pub valid_until: Option<BlockNumber>,
+}
+
+impl<Caller, Call, Nonce, Tip: Zero, BlockNumber> Extrinsic<Caller, Call, Nonce, Tip, BlockNumber> {
+	/// Create an extrinsic without a tip, which can be executed in any block.
+	pub fn new(caller: Caller, call: Call, nonce: Nonce) -> Self {
+		Self { caller, call, nonce, tip: Tip::zero(), valid_from: None, valid_until: None }
+	}
+}
+
+impl<Caller: Encode, Call: Encode, Nonce: Encode, Tip: Encode, BlockNumber: Encode> Encode
+	for Extrinsic<Caller, Call, Nonce, Tip, BlockNumber>
+{
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.caller.encode();
+		bytes.extend(self.call.encode());
+		bytes.extend(self.nonce.encode());
+		bytes.extend(self.tip.encode());
+		bytes.extend(self.valid_from.encode());
+		bytes.extend(self.valid_until.encode());
+		bytes
+	}
+}
+
+impl<Caller: Decode, Call: Decode, Nonce: Decode, Tip: Decode, BlockNumber: Decode> Decode
+	for Extrinsic<Caller, Call, Nonce, Tip, BlockNumber>
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self {
+			caller: Decode::decode(input)?,
+			call: Decode::decode(input)?,
+			nonce: Decode::decode(input)?,
+			tip: Decode::decode(input)?,
+			valid_from: Decode::decode(input)?,
+			valid_until: Decode::decode(input)?,
+		})
+	}
+}
+
+impl<Caller, Call, Nonce, Tip, BlockNumber: PartialOrd>
+	Extrinsic<Caller, Call, Nonce, Tip, BlockNumber>
+{
+	/// Check whether this extrinsic can be executed in the block with number `now`.
+	pub fn is_valid_at(&self, now: &BlockNumber) -> bool {
+		self.valid_from.as_ref().is_none_or(|from| from <= now)
+			&& self.valid_until.as_ref().is_none_or(|until| now <= until)
+	}
+}
+
+/// How the extrinsics of a block are ordered before being executed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderingPolicy {
+	/// Execute the extrinsics in the order they appear in the block.
+	FifoAsSubmitted,
+	/// Execute the extrinsics with the lowest nonce first.
+	ByNonce,
+	/// Execute the extrinsics with the highest tip first.
+	ByTip,
+}
+
+impl OrderingPolicy {
+	/// Get the indices of `extrinsics` in the order they are executed following this policy. The
+	/// order is stable, so extrinsics which are equal according to the policy keep the order they
+	/// were submitted in.
+	///
+	/// When ordering by tip, the extrinsics of each caller keep their nonce order: a higher tip
+	/// cannot make an extrinsic run before another one of the same caller with a lower nonce.
+	pub fn order<Caller: Ord, Call, Nonce: Ord + Copy, Tip: Ord + Copy, BlockNumber>(
+		&self,
+		extrinsics: &[Extrinsic<Caller, Call, Nonce, Tip, BlockNumber>],
+	) -> Vec<usize> {
+		let mut order: Vec<usize> = (0..extrinsics.len()).collect();
+		match self {
+			OrderingPolicy::FifoAsSubmitted => {},
+			OrderingPolicy::ByNonce => order.sort_by_key(|&i| extrinsics[i].nonce),
+			OrderingPolicy::ByTip => {
+				order.sort_by_key(|&i| core::cmp::Reverse(extrinsics[i].tip));
+				// Each caller keeps the positions their tips earned, filled in nonce order.
+				let mut positions: BTreeMap<&Caller, Vec<usize>> = BTreeMap::new();
+				for (position, &i) in order.iter().enumerate() {
+					positions.entry(&extrinsics[i].caller).or_default().push(position);
+				}
+				let mut by_nonce = order.clone();
+				for positions in positions.values() {
+					let mut indices: Vec<usize> = positions.iter().map(|&p| order[p]).collect();
+					indices.sort_by_key(|&i| extrinsics[i].nonce);
+					for (&position, i) in positions.iter().zip(indices) {
+						by_nonce[position] = i;
+					}
+				}
+				order = by_nonce;
+			},
+		}
+		order
+	}
+
+	/// Sort the `extrinsics` following this policy, see `order`.
+	pub fn sort<Caller: Ord, Call, Nonce: Ord + Copy, Tip: Ord + Copy, BlockNumber>(
+		&self,
+		extrinsics: &mut Vec<Extrinsic<Caller, Call, Nonce, Tip, BlockNumber>>,
+	) {
+		let order = self.order(extrinsics);
+		let mut unsorted: Vec<_> = extrinsics.drain(..).map(Some).collect();
+		extrinsics.extend(order.into_iter().filter_map(|i| unsorted[i].take()));
+	}
+}
+
+/// How the nonces of extrinsics are counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonceStrategy {
+	/// Each account has its own nonce, counting the extrinsics it made.
+	PerAccount,
+	/// All the accounts share a single nonce, counting all the extrinsics made on the chain.
+	Global,
+}
+
+/// How the nonces of the extrinsics in a block are checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonceMode {
+	/// Each extrinsic must use the next nonce of its caller, which protects against replays.
+	Strict,
+	/// The nonce of each extrinsic is ignored, and it is given the next nonce of its caller
+	/// instead. This offers no replay protection, so it is only meant for tests and tools.
+	Auto,
+}
+
+/// An amount of tokens, which can be used as the `Balance` of the balances pallet. Unlike a bare
+/// `u128`, it cannot be mixed up with other integers, like block numbers or nonces, without an
+/// explicit conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Balance(pub u128);
+
+impl Balance {
+	/// The largest amount of tokens.
+	pub const MAX: Self = Self(u128::MAX);
+}
+
+impl From<u128> for Balance {
+	fn from(amount: u128) -> Self {
+		Self(amount)
+	}
+}
+
+impl From<Balance> for u128 {
+	fn from(amount: Balance) -> Self {
+		amount.0
+	}
+}
+
+impl fmt::Display for Balance {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl Add for Balance {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Self(self.0 + other.0)
+	}
+}
+
+impl Sub for Balance {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self(self.0 - other.0)
+	}
+}
+
+impl Mul for Balance {
+	type Output = Self;
+
+	fn mul(self, other: Self) -> Self {
+		Self(self.0 * other.0)
+	}
+}
+
+impl Div for Balance {
+	type Output = Self;
+
+	fn div(self, other: Self) -> Self {
+		Self(self.0 / other.0)
+	}
+}
+
+impl Rem for Balance {
+	type Output = Self;
+
+	fn rem(self, other: Self) -> Self {
+		Self(self.0 % other.0)
+	}
+}
+
+impl Zero for Balance {
+	fn zero() -> Self {
+		Self(0)
+	}
+
+	fn is_zero(&self) -> bool {
+		self.0 == 0
+	}
+}
+
+impl CheckedAdd for Balance {
+	fn checked_add(&self, other: &Self) -> Option<Self> {
+		self.0.checked_add(other.0).map(Self)
+	}
+}
+
+impl CheckedSub for Balance {
+	fn checked_sub(&self, other: &Self) -> Option<Self> {
+		self.0.checked_sub(other.0).map(Self)
+	}
+}
+
+impl CheckedMul for Balance {
+	fn checked_mul(&self, other: &Self) -> Option<Self> {
+		self.0.checked_mul(other.0).map(Self)
+	}
+}
+
+impl CheckedDiv for Balance {
+	fn checked_div(&self, other: &Self) -> Option<Self> {
+		self.0.checked_div(other.0).map(Self)
+	}
+}
+
+impl Saturating for Balance {
+	fn saturating_add(self, other: Self) -> Self {
+		Self(self.0.saturating_add(other.0))
+	}
+
+	fn saturating_sub(self, other: Self) -> Self {
+		Self(self.0.saturating_sub(other.0))
+	}
+}
+
+impl SaturatingMul for Balance {
+	fn saturating_mul(&self, other: &Self) -> Self {
+		Self(self.0.saturating_mul(other.0))
+	}
+}
+
+impl iter::Sum for Balance {
+	fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+		Self(iter.map(|amount| amount.0).sum())
+	}
+}
+
+impl<'a> iter::Sum<&'a Balance> for Balance {
+	fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+		iter.copied().sum()
+	}
+}
+
+impl Encode for Balance {
+	fn encode(&self) -> Vec<u8> {
+		self.0.encode()
+	}
+}
+
+impl Decode for Balance {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		u128::decode(input).map(Self)
+	}
+}
+
+/// Format `value` as a JSON string, with its quotes, escaping the characters JSON requires.
+#[cfg(feature = "logging")]
+pub fn json_string(value: &str) -> String {
+	let mut json = String::from('"');
+	for c in value.chars() {
+		match c {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			'\n' => json.push_str("\\n"),
+			'\r' => json.push_str("\\r"),
+			'\t' => json.push_str("\\t"),
+			c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+			c => json.push(c),
+		}
+	}
+	json.push('"');
+	json
+}
+
+/// Remove the `extrinsics` which are exact duplicates of an earlier one, keeping the first of
+/// each. Extrinsics are compared by their encoding. Returns the indices of the removed
+/// extrinsics, in the original list.
+pub fn deduplicate<E: Encode>(extrinsics: &mut Vec<E>) -> Vec<usize> {
+	let mut seen = std::collections::BTreeSet::new();
+	let mut removed = Vec::new();
+	let mut index = 0;
+	extrinsics.retain(|extrinsic| {
+		let keep = seen.insert(extrinsic.encode());
+		if !keep {
+			removed.push(index);
+		}
+		index += 1;
+		keep
+	});
+	removed
 }
 
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
@@ -40,3 +407,693 @@ pub trait Dispatch {
 	/// based on the outcome of that function call.
 	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// The weight of a call is an abstract measure of how much it costs to execute it.
+pub type Weight = u64;
+
+/// A trait for calls which know their own weight.
+pub trait GetWeight {
+	/// Get the weight of executing this call.
+	fn weight(&self) -> Weight;
+}
+
+/// What `execute_block` does with a block whose extrinsics weigh more than the maximum block
+/// weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverweightMode {
+	/// Reject the whole block, without executing any of its extrinsics.
+	Reject,
+	/// Execute the extrinsics in order until the next one would exceed the maximum block weight,
+	/// and skip the rest.
+	Partial,
+}
+
+/// A trait which allows the runtime to run custom logic at specific points while executing a
+/// block. All hooks do nothing by default.
+pub trait Hooks<Header> {
+	/// Called at the start of every block, after the block number has been incremented, but
+	/// before any extrinsic is executed.
+	fn on_initialize(&mut self, _header: &Header) {}
+
+	/// Called at the end of every block, after all its extrinsics were executed.
+	fn on_finalize(&mut self, _header: &Header) {}
+
+	/// Called after each call which was dispatched successfully, once its events were collected.
+	/// If this fails, the call fails with the returned error, though its effects are kept.
+	fn on_dispatched(&mut self) -> DispatchResult {
+		Ok(())
+	}
+}
+
+/// A trait which allows the runtime to charge a fee for each extrinsic of a block.
+pub trait Fees<Header, Caller, Call> {
+	/// The type of the fees, usually a balance.
+	type Fee;
+
+	/// Charge the fee of `call` from `caller`, before it is dispatched. If this fails, the call is
+	/// not dispatched, and the extrinsic fails with the returned error.
+	fn charge_fee(&mut self, caller: &Caller, call: &Call) -> Result<Self::Fee, &'static str>;
+
+	/// Settle the `fee` which was charged from `caller` once their call was dispatched with
+	/// `result`, in the block with `header`. For example, by paying it to the block author. If
+	/// this fails, a successful extrinsic fails with the returned error, though the effects of its
+	/// call are kept.
+	fn settle_fee(
+		&mut self,
+		header: &Header,
+		caller: &Caller,
+		fee: Self::Fee,
+		result: &DispatchResult,
+	) -> DispatchResult;
+}
+
+/// A trait which allows the runtime to reject calls before they reach their pallet, for checks
+/// which need state from other pallets.
+pub trait CallFilter<Caller, Call> {
+	/// Check whether `caller` is allowed to dispatch `call`. If not, the call fails with the
+	/// returned error without being dispatched.
+	fn filter_call(&self, caller: &Caller, call: &Call) -> DispatchResult;
+}
+
+/// A trait for anything which events of type `E` can be deposited in: the event buffer of each
+/// pallet, and the system pallet which collects the events of the whole runtime.
+pub trait DepositEvent<E> {
+	fn deposit_event(&mut self, event: E);
+}
+
+/// A buffer of events, in the order they were deposited. Pallets use one to hold their events
+/// until the runtime collects them after a successful dispatch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventBuffer<E> {
+	events: Vec<E>,
+}
+
+impl<E> EventBuffer<E> {
+	/// Create an empty buffer.
+	pub fn new() -> Self {
+		Self { events: Vec::new() }
+	}
+
+	/// Get the events in the buffer.
+	pub fn as_slice(&self) -> &[E] {
+		&self.events
+	}
+
+	/// Take all the events out of the buffer, leaving it empty.
+	pub fn take(&mut self) -> Vec<E> {
+		core::mem::take(&mut self.events)
+	}
+}
+
+impl<E> Default for EventBuffer<E> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<E> DepositEvent<E> for EventBuffer<E> {
+	fn deposit_event(&mut self, event: E) {
+		self.events.push(event);
+	}
+}
+
+/// An optional callback which is invoked with a value of type `A` each time something happens, for
+/// example with each event as it is deposited, to log events live instead of reading them at the
+/// end of a block. The callback is not part of the state: clones share it, and it is ignored when
+/// comparing states.
+pub struct Callback<A: ?Sized> {
+	callback: Option<Rc<RefCell<BoxedCallback<A>>>>,
+}
+
+/// The function of a `Callback`.
+pub type BoxedCallback<A> = Box<dyn FnMut(&A)>;
+
+/// A callback invoked with each event as it is deposited.
+pub type EventHandler<E> = Callback<E>;
+
+/// The function of an `EventHandler`.
+pub type BoxedEventHandler<E> = BoxedCallback<E>;
+
+/// A callback invoked with each line which is logged.
+#[cfg(feature = "logging")]
+pub type LogHandler = Callback<str>;
+
+/// The function of a `LogHandler`.
+#[cfg(feature = "logging")]
+pub type BoxedLogHandler = BoxedCallback<str>;
+
+impl<A: ?Sized> Callback<A> {
+	pub fn new() -> Self {
+		Self { callback: None }
+	}
+
+	/// Set the callback, replacing any previous one.
+	pub fn set(&mut self, callback: BoxedCallback<A>) {
+		self.callback = Some(Rc::new(RefCell::new(callback)));
+	}
+
+	/// Remove the callback, if any.
+	pub fn clear(&mut self) {
+		self.callback = None;
+	}
+
+	/// Check whether a callback is set.
+	pub fn is_set(&self) -> bool {
+		self.callback.is_some()
+	}
+
+	/// Invoke the callback, if any, with `value`.
+	pub fn notify(&self, value: &A) {
+		if let Some(callback) = &self.callback {
+			(callback.borrow_mut())(value);
+		}
+	}
+}
+
+impl<A: ?Sized> Clone for Callback<A> {
+	fn clone(&self) -> Self {
+		Self { callback: self.callback.clone() }
+	}
+}
+
+impl<A: ?Sized> PartialEq for Callback<A> {
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+impl<A: ?Sized> core::fmt::Debug for Callback<A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		f.debug_struct("Callback").field("set", &self.callback.is_some()).finish()
+	}
+}
+
+/// A trait for types which can be encoded into bytes, for example so that they can be hashed.
+/// We use a very simple format: integers are encoded as little endian bytes, fixed size arrays
+/// are encoded as is, strings and lists are encoded as their length (as a `u32`) followed by
+/// their bytes or items, and tuples are encoded as their items one after the other. Options are
+/// encoded as a `0` byte for `None`, or a `1` byte followed by the value.
+pub trait Encode {
+	fn encode(&self) -> Vec<u8>;
+}
+
+impl Encode for u8 {
+	fn encode(&self) -> Vec<u8> {
+		vec![*self]
+	}
+}
+
+impl Encode for u16 {
+	fn encode(&self) -> Vec<u8> {
+		self.to_le_bytes().to_vec()
+	}
+}
+
+impl Encode for u32 {
+	fn encode(&self) -> Vec<u8> {
+		self.to_le_bytes().to_vec()
+	}
+}
+
+impl Encode for u64 {
+	fn encode(&self) -> Vec<u8> {
+		self.to_le_bytes().to_vec()
+	}
+}
+
+impl Encode for u128 {
+	fn encode(&self) -> Vec<u8> {
+		self.to_le_bytes().to_vec()
+	}
+}
+
+impl Encode for [u8; 32] {
+	fn encode(&self) -> Vec<u8> {
+		self.to_vec()
+	}
+}
+
+impl Encode for str {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = (self.len() as u32).encode();
+		bytes.extend_from_slice(self.as_bytes());
+		bytes
+	}
+}
+
+impl Encode for String {
+	fn encode(&self) -> Vec<u8> {
+		self.as_str().encode()
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = (self.len() as u32).encode();
+		for item in self {
+			bytes.extend(item.encode());
+		}
+		bytes
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode(&self) -> Vec<u8> {
+		self.as_slice().encode()
+	}
+}
+
+impl<T: Encode> Encode for BTreeSet<T> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = (self.len() as u32).encode();
+		for item in self {
+			bytes.extend(item.encode());
+		}
+		bytes
+	}
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = (self.len() as u32).encode();
+		for entry in self {
+			bytes.extend(entry.encode());
+		}
+		bytes
+	}
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.0.encode();
+		bytes.extend(self.1.encode());
+		bytes
+	}
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+	fn encode(&self) -> Vec<u8> {
+		let mut bytes = self.0.encode();
+		bytes.extend(self.1.encode());
+		bytes.extend(self.2.encode());
+		bytes
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode(&self) -> Vec<u8> {
+		match self {
+			None => vec![0],
+			Some(value) => {
+				let mut bytes = vec![1];
+				bytes.extend(value.encode());
+				bytes
+			},
+		}
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode(&self) -> Vec<u8> {
+		(**self).encode()
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode(&self) -> Vec<u8> {
+		(**self).encode()
+	}
+}
+
+/// A trait for types which can be decoded from the bytes produced by their `Encode`
+/// implementation. Decoding reads from the start of `input`, and advances it past the bytes which
+/// were read.
+pub trait Decode: Sized {
+	fn decode(input: &mut &[u8]) -> Result<Self, String>;
+}
+
+/// Read the next `len` bytes of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+	if input.len() < len {
+		return Err(format!("Expected {} more bytes, found {}", len, input.len()));
+	}
+	let (bytes, rest) = input.split_at(len);
+	*input = rest;
+	Ok(bytes)
+}
+
+impl Decode for u8 {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(take(input, 1)?[0])
+	}
+}
+
+impl Decode for u16 {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self::from_le_bytes(take(input, 2)?.try_into().unwrap()))
+	}
+}
+
+impl Decode for u32 {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self::from_le_bytes(take(input, 4)?.try_into().unwrap()))
+	}
+}
+
+impl Decode for u64 {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self::from_le_bytes(take(input, 8)?.try_into().unwrap()))
+	}
+}
+
+impl Decode for u128 {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Self::from_le_bytes(take(input, 16)?.try_into().unwrap()))
+	}
+}
+
+impl Decode for [u8; 32] {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(take(input, 32)?.try_into().unwrap())
+	}
+}
+
+impl Decode for String {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		let len = u32::decode(input)? as usize;
+		let bytes = take(input, len)?;
+		String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		let len = u32::decode(input)?;
+		(0..len).map(|_| T::decode(input)).collect()
+	}
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok((A::decode(input)?, B::decode(input)?))
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		match u8::decode(input)? {
+			0 => Ok(None),
+			1 => Ok(Some(T::decode(input)?)),
+			tag => Err(format!("Invalid option tag {}", tag)),
+		}
+	}
+}
+
+impl<T: Decode> Decode for Box<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, String> {
+		Ok(Box::new(T::decode(input)?))
+	}
+}
+
+/// A trait which abstracts over the hashing algorithm used by our state machine.
+/// Anything which needs a hash, like block hashes or the hash of some claimed content, should go
+/// through this trait so the runtime developer can choose the algorithm in one place.
+pub trait Hasher {
+	/// Hash some arbitrary `bytes` into a fixed size 32 byte output.
+	fn hash(bytes: &[u8]) -> [u8; 32];
+}
+
+/// The default hasher for our state machine, implementing SHA-256.
+/// This is a simple, dependency free implementation, and is not optimized for performance.
+#[derive(Debug)]
+pub struct Sha256;
+
+impl Sha256 {
+	/// The round constants: the first 32 bits of the fractional parts of the cube roots of the
+	/// first 64 primes.
+	const K: [u32; 64] = [
+		0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+		0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+		0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+		0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+		0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+		0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+		0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+		0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+		0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+		0xc67178f2,
+	];
+
+	/// The initial hash value: the first 32 bits of the fractional parts of the square roots of
+	/// the first 8 primes.
+	const H: [u32; 8] = [
+		0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+		0x5be0cd19,
+	];
+}
+
+impl Hasher for Sha256 {
+	fn hash(bytes: &[u8]) -> [u8; 32] {
+		// Pad the message with a single `1` bit, then zeros, then the message length in bits, so
+		// that the total length is a multiple of 64 bytes.
+		let mut message = bytes.to_vec();
+		message.push(0x80);
+		while message.len() % 64 != 56 {
+			message.push(0);
+		}
+		message.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+
+		let mut state = Self::H;
+		for chunk in message.chunks(64) {
+			let mut w = [0u32; 64];
+			for (i, word) in chunk.chunks(4).enumerate() {
+				w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+			}
+			for i in 16..64 {
+				let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+				let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+				w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+			}
+
+			let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+			for (k, w) in Self::K.iter().zip(w) {
+				let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+				let ch = (e & f) ^ (!e & g);
+				let temp1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(*k).wrapping_add(w);
+				let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+				let maj = (a & b) ^ (a & c) ^ (b & c);
+				let temp2 = s0.wrapping_add(maj);
+
+				h = g;
+				g = f;
+				f = e;
+				e = d.wrapping_add(temp1);
+				d = c;
+				c = b;
+				b = a;
+				a = temp1.wrapping_add(temp2);
+			}
+
+			for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+				*s = s.wrapping_add(v);
+			}
+		}
+
+		let mut output = [0u8; 32];
+		for (i, s) in state.iter().enumerate() {
+			output[i * 4..i * 4 + 4].copy_from_slice(&s.to_be_bytes());
+		}
+		output
+	}
+}
+
+/// Compute the root of the Merkle tree over `leaves` using the hasher `H`. Each node is the hash of
+/// its two children one after the other, and a node without a sibling is paired with itself. The
+/// root of an empty tree is the zero hash.
+pub fn merkle_root<H: Hasher>(leaves: &[[u8; 32]]) -> [u8; 32] {
+	let mut level = leaves.to_vec();
+	if level.is_empty() {
+		return [0; 32];
+	}
+	while level.len() > 1 {
+		level = level
+			.chunks(2)
+			.map(|pair| merkle_parent::<H>(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+			.collect();
+	}
+	level[0]
+}
+
+/// Get the siblings of the leaf at `index` in the Merkle tree over `leaves`, from the bottom of
+/// the tree to the top, see `merkle_root`. Returns `None` if there is no such leaf.
+pub fn merkle_proof<H: Hasher>(leaves: &[[u8; 32]], mut index: usize) -> Option<Vec<[u8; 32]>> {
+	if index >= leaves.len() {
+		return None;
+	}
+	let mut level = leaves.to_vec();
+	let mut siblings = Vec::new();
+	while level.len() > 1 {
+		siblings.push(*level.get(index ^ 1).unwrap_or(&level[index]));
+		level = level
+			.chunks(2)
+			.map(|pair| merkle_parent::<H>(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+			.collect();
+		index /= 2;
+	}
+	Some(siblings)
+}
+
+/// Check that `leaf` is at `index` in the Merkle tree with `root`, given its `siblings` from
+/// `merkle_proof`.
+pub fn verify_merkle_proof<H: Hasher>(
+	leaf: [u8; 32],
+	mut index: usize,
+	siblings: &[[u8; 32]],
+	root: [u8; 32],
+) -> bool {
+	let mut node = leaf;
+	for sibling in siblings {
+		node = if index.is_multiple_of(2) {
+			merkle_parent::<H>(&node, sibling)
+		} else {
+			merkle_parent::<H>(sibling, &node)
+		};
+		index /= 2;
+	}
+	index == 0 && node == root
+}
+
+/// Hash two Merkle tree nodes into their parent.
+fn merkle_parent<H: Hasher>(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+	H::hash(&[left.as_slice(), right.as_slice()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+	use num::{CheckedAdd, CheckedSub};
+
+	use super::{Balance, Decode, Encode, Extrinsic, Hasher, OrderingPolicy, Sha256};
+
+	#[test]
+	fn ordering_policies() {
+		let extrinsic = |caller, nonce, tip| Extrinsic {
+			caller,
+			call: (),
+			nonce,
+			tip,
+			valid_from: None::<u32>,
+			valid_until: None,
+		};
+		let extrinsics = vec![extrinsic("a", 2, 5), extrinsic("b", 0, 1), extrinsic("c", 1, 9)];
+		let order = |policy: OrderingPolicy| {
+			let mut extrinsics = extrinsics.clone();
+			policy.sort(&mut extrinsics);
+			extrinsics.into_iter().map(|extrinsic| extrinsic.caller).collect::<Vec<_>>()
+		};
+
+		assert_eq!(order(OrderingPolicy::FifoAsSubmitted), vec!["a", "b", "c"]);
+		assert_eq!(order(OrderingPolicy::ByNonce), vec!["b", "c", "a"]);
+		assert_eq!(order(OrderingPolicy::ByTip), vec!["c", "a", "b"]);
+
+		// The extrinsics of a caller stay in nonce order, whatever their tips.
+		let extrinsics = [extrinsic("a", 0, 1), extrinsic("b", 0, 5), extrinsic("a", 1, 9)];
+		assert_eq!(OrderingPolicy::ByTip.order(&extrinsics), vec![0, 1, 2]);
+	}
+
+	#[test]
+	fn merkle_proofs() {
+		use super::{Hasher, Sha256};
+
+		let leaves: Vec<_> = (0u8..5).map(|i| Sha256::hash(&[i])).collect();
+		let root = super::merkle_root::<Sha256>(&leaves);
+		for (i, leaf) in leaves.iter().enumerate() {
+			let siblings = super::merkle_proof::<Sha256>(&leaves, i).unwrap();
+			assert!(super::verify_merkle_proof::<Sha256>(*leaf, i, &siblings, root));
+			let other_leaf = leaves[(i + 1) % leaves.len()];
+			assert!(!super::verify_merkle_proof::<Sha256>(other_leaf, i, &siblings, root));
+		}
+		assert_eq!(super::merkle_proof::<Sha256>(&leaves, 5), None);
+		assert_eq!(super::merkle_root::<Sha256>(&leaves[..1]), leaves[0]);
+		assert_eq!(super::merkle_root::<Sha256>(&[]), [0; 32]);
+	}
+
+	#[test]
+	fn sha256_test_vectors() {
+		assert_eq!(
+			Sha256::hash(b""),
+			[
+				0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+				0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+				0x78, 0x52, 0xb8, 0x55,
+			]
+		);
+		assert_eq!(
+			Sha256::hash(b"abc"),
+			[
+				0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+				0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+				0xf2, 0x00, 0x15, 0xad,
+			]
+		);
+		// This input pads out to two chunks.
+		assert_eq!(
+			Sha256::hash(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+			[
+				0x24, 0x8d, 0x6a, 0x61, 0xd2, 0x06, 0x38, 0xb8, 0xe5, 0xc0, 0x26, 0x93, 0x0c, 0x3e,
+				0x60, 0x39, 0xa3, 0x3c, 0xe4, 0x59, 0x64, 0xff, 0x21, 0x67, 0xf6, 0xec, 0xed, 0xd4,
+				0x19, 0xdb, 0x06, 0xc1,
+			]
+		);
+	}
+
+	#[test]
+	fn deduplicate() {
+		let mut extrinsics = vec![1u32, 2, 1, 3, 2];
+		assert_eq!(super::deduplicate(&mut extrinsics), vec![2, 4]);
+		assert_eq!(extrinsics, vec![1, 2, 3]);
+	}
+
+	#[test]
+	fn validity_window() {
+		let extrinsic = |valid_from, valid_until| Extrinsic {
+			caller: (),
+			call: (),
+			nonce: 0,
+			tip: 0,
+			valid_from,
+			valid_until,
+		};
+		assert!(extrinsic(None, None).is_valid_at(&1));
+		assert!(!extrinsic(Some(2), None).is_valid_at(&1));
+		assert!(extrinsic(Some(2), Some(4)).is_valid_at(&2));
+		assert!(extrinsic(Some(2), Some(4)).is_valid_at(&4));
+		assert!(!extrinsic(Some(2), Some(4)).is_valid_at(&5));
+	}
+
+	#[test]
+	fn balance_wrapper() {
+		// The checked operations behave like the ones of the raw integers.
+		let (a, b) = (Balance(30), Balance(20));
+		assert_eq!(a.checked_add(&b), 30u128.checked_add(20).map(Balance));
+		assert_eq!(a.checked_sub(&b), Some(Balance(10)));
+		assert_eq!(b.checked_sub(&a), None);
+		assert_eq!(Balance(u128::MAX).checked_add(&Balance(1)), None);
+
+		// Mixing a balance with a raw integer, like `a + 1u128`, does not compile, so amounts
+		// must be converted explicitly.
+		assert_eq!(a + Balance::from(1), Balance(31));
+		assert_eq!(u128::from(a), 30);
+
+		assert_eq!(Balance::decode(&mut a.encode().as_slice()), Ok(a));
+		assert_eq!(a.to_string(), "30");
+	}
+
+	#[test]
+	#[cfg(feature = "logging")]
+	fn json_strings() {
+		use super::json_string;
+		assert_eq!(json_string("alice"), "\"alice\"");
+		assert_eq!(json_string("a \"b\" \\ c\n"), "\"a \\\"b\\\" \\\\ c\\n\"");
+		assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+	}
+}
@@ -1,27 +1,221 @@
-use std::{collections::BTreeMap, ops::AddAssign};
+#[cfg(feature = "logging")]
+use std::fmt::Display;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	ops::AddAssign,
+};
 
-use num::{One, Zero};
+use num::{CheckedAdd, One, Zero};
 
-pub trait Config {
-	type BlockNumber: Zero + One + AddAssign + Copy;
-	type AccountId: Ord;
-	type Nonce: Zero + One + Copy;
+#[cfg(feature = "logging")]
+use crate::support::{json_string, BoxedLogHandler, LogHandler};
+use crate::support::{
+	BoxedEventHandler, DepositEvent, DispatchResult, Encode, EventBuffer, EventHandler, Extrinsic,
+	Hasher, NonceMode, NonceStrategy,
+};
+
+pub trait Config: Sized {
+	type BlockNumber: Zero + One + AddAssign + CheckedAdd + Copy + Ord + Encode;
+	type AccountId: Ord + Clone;
+	type Nonce: Zero + One + Copy + Ord;
+	/// The hashing algorithm used by the state machine, for things like block and content hashes.
+	type Hashing: Hasher;
+	/// The overarching event type of the runtime, which the events of this pallet are converted
+	/// into.
+	type RuntimeEvent: From<Event<Self>>;
+	/// The number of recent block hashes we keep in storage. This should be at least 1, so that
+	/// the next block can always be checked against its parent hash.
+	const BLOCK_HASH_COUNT: u32;
+	/// The maximum total weight of the extrinsics in a block.
+	const MAX_BLOCK_WEIGHT: crate::support::Weight;
+	/// How the extrinsics of a block are ordered before being executed. By default, they are
+	/// executed in the order they were submitted.
+	const ORDERING_POLICY: crate::support::OrderingPolicy =
+		crate::support::OrderingPolicy::FifoAsSubmitted;
+	/// The number of recent dispatch outcomes we keep in the dispatch log.
+	const DISPATCH_LOG_CAPACITY: u32 = 16;
+	/// Whether blocks without any extrinsic can be executed.
+	const ALLOW_EMPTY_BLOCKS: bool = true;
+	/// Whether exact duplicates of an extrinsic earlier in the same block are skipped, rather
+	/// than failing the block because of their nonce.
+	const DEDUPLICATE_EXTRINSICS: bool = false;
+	/// Whether each account has its own nonce, or all accounts share a single one. With a single
+	/// nonce, the nonces are checked in the order set by `ORDERING_POLICY`, so ordering by tip
+	/// rejects blocks whose tips do not follow their nonces.
+	const NONCE_STRATEGY: crate::support::NonceStrategy = crate::support::NonceStrategy::PerAccount;
+	/// The maximum total size of the encoded extrinsics in a block, in bytes. By default, the size
+	/// of blocks is not limited.
+	const MAX_BLOCK_BYTES: u32 = u32::MAX;
+	/// Whether the nonces of extrinsics are checked, or assigned automatically, when the chain
+	/// starts. This can then be switched with `Pallet::set_nonce_mode`.
+	const NONCE_MODE: crate::support::NonceMode = crate::support::NonceMode::Strict;
+
+	/// Check that an account id is well formed, before it is given a balance or owns a claim.
+	/// By default, all account ids are valid.
+	fn validate_account(_who: &Self::AccountId) -> DispatchResult {
+		Ok(())
+	}
+
+	/// Whether `who` is the sudo account, which can dispatch privileged calls like
+	/// `add_to_blacklist`. By default, there is no sudo account.
+	fn is_sudo(_who: &Self::AccountId) -> bool {
+		false
+	}
+}
+
+/// The events emitted by the System Pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T: Config> {
+	/// The extrinsic at `index` in the current block was dispatched successfully.
+	ExtrinsicSuccess { index: u32 },
+	/// The extrinsic at `index` in the current block failed with `error`.
+	ExtrinsicFailed { index: u32, error: &'static str },
+	/// `who` made a remark with some arbitrary `message`.
+	Remarked { who: T::AccountId, message: Vec<u8> },
+}
+
+/// The outcome of dispatching one extrinsic, as recorded in the dispatch log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispatchRecord<T: Config> {
+	/// The block in which the extrinsic was dispatched.
+	pub block: T::BlockNumber,
+	/// The index of the extrinsic in its block.
+	pub extrinsic_index: u32,
+	/// The account which made the extrinsic.
+	pub caller: T::AccountId,
+	/// The result of dispatching the extrinsic.
+	pub result: DispatchResult,
 }
 
 /// This is the System Pallet.
 /// It handles low level state needed for your blockchain.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pallet<T: Config> {
 	/// The current block number.
 	pub block_number: T::BlockNumber,
 	/// A map from an account to their nonce.
-	pub nonce: BTreeMap<T::AccountId, T::Nonce>,
+	nonce: BTreeMap<T::AccountId, T::Nonce>,
+	/// The nonce shared by all accounts, only used with the `Global` nonce strategy.
+	global_nonce: T::Nonce,
+	/// Whether the nonces of extrinsics are checked, or assigned automatically.
+	nonce_mode: NonceMode,
+	/// A map from a block number to the hash of that block.
+	block_hashes: BTreeMap<T::BlockNumber, [u8; 32]>,
+	/// The events deposited during the current block.
+	events: EventBuffer<T::RuntimeEvent>,
+	/// The callback invoked with each event as it is deposited.
+	event_handler: EventHandler<T::RuntimeEvent>,
+	/// The callback invoked with a JSON line describing each dispatched extrinsic.
+	#[cfg(feature = "logging")]
+	log_handler: LogHandler,
+	/// The version of the runtime which last modified the state, used to detect runtime upgrades.
+	spec_version: u32,
+	/// The hash of the runtime code which last executed a block, used to detect whether the state
+	/// was produced by a different binary.
+	code_hash: [u8; 32],
+	/// The accounts which take turns authoring blocks, set at genesis.
+	validators: Vec<T::AccountId>,
+	/// Whether the chain is halted, in which case no block can be executed until it is resumed.
+	halted: bool,
+	/// The hash of the genesis state, which identifies the chain.
+	genesis_hash: [u8; 32],
+	/// The outcomes of the most recent dispatches, oldest first. Unlike events, these persist
+	/// across blocks, up to `DISPATCH_LOG_CAPACITY` of them.
+	dispatch_log: Vec<DispatchRecord<T>>,
+	/// For each account which made extrinsics, the last block it made one in, and the number of
+	/// distinct blocks it made any in.
+	active_blocks: BTreeMap<T::AccountId, (T::BlockNumber, u32)>,
+	/// The number of extrinsics dispatched in each block.
+	extrinsic_counts: BTreeMap<T::BlockNumber, u32>,
+	/// The accounts which are not allowed to dispatch any call.
+	blacklist: BTreeSet<T::AccountId>,
 }
 
 impl<T: Config> Pallet<T> {
 	/// Create a new instance of the System Pallet.
 	pub fn new() -> Self {
-		Self { block_number: T::BlockNumber::zero(), nonce: BTreeMap::new() }
+		Self {
+			block_number: T::BlockNumber::zero(),
+			nonce: BTreeMap::new(),
+			global_nonce: T::Nonce::zero(),
+			nonce_mode: T::NONCE_MODE,
+			block_hashes: BTreeMap::new(),
+			events: EventBuffer::new(),
+			event_handler: EventHandler::new(),
+			#[cfg(feature = "logging")]
+			log_handler: LogHandler::new(),
+			spec_version: 0,
+			code_hash: [0; 32],
+			validators: Vec::new(),
+			halted: false,
+			genesis_hash: [0; 32],
+			dispatch_log: Vec::new(),
+			active_blocks: BTreeMap::new(),
+			extrinsic_counts: BTreeMap::new(),
+			blacklist: BTreeSet::new(),
+		}
+	}
+
+	/// Clear the nonces and activity of all accounts, the events and the dispatch log.
+	/// The block number, block hashes, extrinsic counts, blacklist and spec version are kept, so
+	/// that the chain can keep executing blocks on top of the cleared state.
+	pub fn clear(&mut self) {
+		self.nonce.clear();
+		self.global_nonce = T::Nonce::zero();
+		self.active_blocks.clear();
+		self.events.take();
+		self.dispatch_log.clear();
+	}
+
+	/// Hash some `bytes` using the hashing algorithm configured for this state machine.
+	pub fn hash(bytes: &[u8]) -> [u8; 32] {
+		T::Hashing::hash(bytes)
+	}
+
+	/// Check that a block with `extrinsic_count` extrinsics can be executed, following
+	/// `ALLOW_EMPTY_BLOCKS`.
+	pub fn ensure_block_not_empty(extrinsic_count: usize) -> DispatchResult {
+		if extrinsic_count == 0 && !T::ALLOW_EMPTY_BLOCKS {
+			return Err("Empty block");
+		}
+		Ok(())
+	}
+
+	/// Check that extrinsics whose encodings take `bytes` bytes in total fit within
+	/// `MAX_BLOCK_BYTES`.
+	pub fn ensure_block_size(bytes: usize) -> DispatchResult {
+		if bytes > T::MAX_BLOCK_BYTES as usize {
+			return Err("Block size limit exceeded");
+		}
+		Ok(())
+	}
+
+	/// Get the accounts which take turns authoring blocks.
+	pub fn validators(&self) -> &[T::AccountId] {
+		&self.validators
+	}
+
+	/// Set the accounts which take turns authoring blocks.
+	pub fn set_validators(&mut self, validators: Vec<T::AccountId>) {
+		self.validators = validators;
+	}
+
+	/// Check whether the chain is halted.
+	pub fn is_halted(&self) -> bool {
+		self.halted
+	}
+
+	/// Halt the chain, or resume it if `halted` is false.
+	pub fn set_halted(&mut self, halted: bool) {
+		self.halted = halted;
+	}
+
+	/// Check that the chain is not halted, so that blocks can be executed.
+	pub fn ensure_not_halted(&self) -> DispatchResult {
+		if self.halted {
+			return Err("Chain halted");
+		}
+		Ok(())
 	}
 
 	/// Get the current block number.
@@ -29,32 +223,434 @@ impl<T: Config> Pallet<T> {
 		self.block_number
 	}
 
+	/// Get the version of the runtime which last modified the state.
+	pub fn spec_version(&self) -> u32 {
+		self.spec_version
+	}
+
+	/// Set the version of the runtime which last modified the state.
+	pub fn set_spec_version(&mut self, version: u32) {
+		self.spec_version = version;
+	}
+
+	/// Get the hash of the runtime code which last executed a block. This is the zero hash if no
+	/// block was executed yet.
+	pub fn code_hash(&self) -> [u8; 32] {
+		self.code_hash
+	}
+
+	/// Set the hash of the runtime code which last executed a block.
+	pub fn set_code_hash(&mut self, hash: [u8; 32]) {
+		self.code_hash = hash;
+	}
+
+	/// Get the hash of the genesis state. This is the zero hash if it was never set.
+	pub fn genesis_hash(&self) -> [u8; 32] {
+		self.genesis_hash
+	}
+
+	/// Set the hash of the genesis state.
+	pub fn set_genesis_hash(&mut self, hash: [u8; 32]) {
+		self.genesis_hash = hash;
+	}
+
+	/// Get the events deposited during the current block.
+	pub fn events(&self) -> &[T::RuntimeEvent] {
+		self.events.as_slice()
+	}
+
+	/// Set a callback which is invoked with each event as it is deposited.
+	pub fn set_event_handler(&mut self, handler: BoxedEventHandler<T::RuntimeEvent>) {
+		self.event_handler.set(handler);
+	}
+
+	/// Remove the callback invoked with each deposited event, if any.
+	pub fn clear_event_handler(&mut self) {
+		self.event_handler.clear();
+	}
+
+	/// Set a callback which is invoked with a JSON line describing each dispatched extrinsic, see
+	/// `log_dispatch`.
+	#[cfg(feature = "logging")]
+	pub fn set_log_handler(&mut self, handler: BoxedLogHandler) {
+		self.log_handler.set(handler);
+	}
+
+	/// Remove the callback invoked with each dispatch log line, if any.
+	#[cfg(feature = "logging")]
+	pub fn clear_log_handler(&mut self) {
+		self.log_handler.clear();
+	}
+
+	/// Remove all the callbacks of this pallet, for events and log lines, for example from a copy
+	/// of the state whose events must not reach them.
+	pub fn clear_handlers(&mut self) {
+		self.event_handler.clear();
+		#[cfg(feature = "logging")]
+		self.log_handler.clear();
+	}
+
+	/// Use the same callbacks as `other`, for events and log lines, see `clear_handlers`.
+	pub fn share_handlers(&mut self, other: &Self) {
+		self.event_handler = other.event_handler.clone();
+		#[cfg(feature = "logging")]
+		{
+			self.log_handler = other.log_handler.clone();
+		}
+	}
+
+	/// Get the outcomes of the most recent dispatches, oldest first.
+	pub fn recent_dispatches(&self) -> &[DispatchRecord<T>] {
+		&self.dispatch_log
+	}
+
+	/// Get the number of extrinsics dispatched in each block which had any.
+	pub fn extrinsic_counts(&self) -> impl Iterator<Item = (&T::BlockNumber, &u32)> {
+		self.extrinsic_counts.iter()
+	}
+
+	/// Check that `who` is the sudo account, which can dispatch privileged calls.
+	pub fn ensure_sudo(who: &T::AccountId) -> DispatchResult {
+		if !T::is_sudo(who) {
+			return Err("Not the sudo account");
+		}
+		Ok(())
+	}
+
+	/// Check whether `who` is forbidden from dispatching any call.
+	pub fn is_blacklisted(&self, who: &T::AccountId) -> bool {
+		self.blacklist.contains(who)
+	}
+
+	/// Check that `who` is allowed to dispatch calls.
+	pub fn ensure_not_blacklisted(&self, who: &T::AccountId) -> DispatchResult {
+		if self.is_blacklisted(who) {
+			return Err("Account blacklisted");
+		}
+		Ok(())
+	}
+
+	/// Get the number of distinct blocks in which `who` made at least one extrinsic.
+	pub fn active_block_count(&self, who: &T::AccountId) -> u32 {
+		self.active_blocks.get(who).map_or(0, |(_, count)| *count)
+	}
+
+	/// Record the outcome of dispatching an extrinsic in the dispatch log, dropping the oldest
+	/// records beyond `DISPATCH_LOG_CAPACITY`.
+	pub fn record_dispatch(&mut self, record: DispatchRecord<T>) {
+		*self.extrinsic_counts.entry(record.block).or_default() += 1;
+		let (last_block, count) =
+			self.active_blocks.entry(record.caller.clone()).or_insert((record.block, 0));
+		if *count == 0 || *last_block != record.block {
+			*last_block = record.block;
+			*count += 1;
+		}
+		self.dispatch_log.push(record);
+		let excess = self.dispatch_log.len().saturating_sub(T::DISPATCH_LOG_CAPACITY as usize);
+		self.dispatch_log.drain(..excess);
+	}
+
 	/// Increases the block number by one.
+	/// Since events only live for the block in which they are deposited, this also clears them.
 	pub fn inc_block_number(&mut self) {
+		self.events.take();
 		self.block_number += T::BlockNumber::one();
 	}
 
-	/// Increment the nonce of an account. This helps us keep track of how many transactions each
-	/// account has made.
+	/// Get the hash of the block with number `number`, if we know it.
+	pub fn block_hash(&self, number: T::BlockNumber) -> Option<[u8; 32]> {
+		self.block_hashes.get(&number).copied()
+	}
+
+	/// Store the `hash` of the block with number `number`.
+	pub fn set_block_hash(&mut self, number: T::BlockNumber, hash: [u8; 32]) {
+		self.block_hashes.insert(number, hash);
+	}
+
+	/// Remove all but the `keep_last` most recent block hashes from storage, so that the block hash
+	/// history does not grow forever.
+	pub fn prune_block_hashes(&mut self, keep_last: u32) {
+		let to_remove = self.block_hashes.len().saturating_sub(keep_last as usize);
+		match self.block_hashes.keys().nth(to_remove).copied() {
+			Some(first_kept) => self.block_hashes = self.block_hashes.split_off(&first_kept),
+			None => self.block_hashes.clear(),
+		}
+	}
+
+	/// Called at the end of every block, once all extrinsics have been executed.
+	pub fn on_finalize(&mut self) {
+		self.prune_block_hashes(T::BLOCK_HASH_COUNT);
+	}
+
+	/// Get the hash which the next block must use as its parent hash: the hash of the block at
+	/// the current block number. At genesis there is no such block, so this is the zero hash.
+	pub fn parent_hash(&self) -> [u8; 32] {
+		self.block_hash(self.block_number).unwrap_or([0; 32])
+	}
+
+	/// Get the nonce the next extrinsic of `who` should use: the number of extrinsics they made so
+	/// far, or the number of extrinsics made by anyone with the `Global` nonce strategy.
+	pub fn next_nonce(&self, who: &T::AccountId) -> T::Nonce {
+		match T::NONCE_STRATEGY {
+			NonceStrategy::PerAccount => *self.nonce.get(who).unwrap_or(&T::Nonce::zero()),
+			NonceStrategy::Global => self.global_nonce,
+		}
+	}
+
+	/// Iterate over the accounts which have a stored nonce, along with that nonce, ordered by
+	/// account. With the `Global` nonce strategy, these are only the nonces set at genesis.
+	pub fn nonces(&self) -> impl Iterator<Item = (&T::AccountId, &T::Nonce)> {
+		self.nonce.iter()
+	}
+
+	/// Get whether the nonces of extrinsics are checked, or assigned automatically.
+	pub fn nonce_mode(&self) -> NonceMode {
+		self.nonce_mode
+	}
+
+	/// Switch between checking the nonces of extrinsics and assigning them automatically, for
+	/// example to make tests and tools easier to write.
+	pub fn set_nonce_mode(&mut self, mode: NonceMode) {
+		self.nonce_mode = mode;
+	}
+
+	/// Check that `extrinsics`, given as their caller and nonce in the order they are executed, use
+	/// consecutive nonces starting from the next nonce of their caller. With the `Global` nonce
+	/// strategy, the extrinsics of all the callers share a single sequence. Nonces are not checked
+	/// at all in the `Auto` nonce mode.
+	pub fn ensure_sequential_nonces<'a>(
+		&self,
+		extrinsics: impl IntoIterator<Item = (&'a T::AccountId, T::Nonce)>,
+	) -> DispatchResult
+	where
+		T::AccountId: 'a,
+	{
+		if self.nonce_mode == NonceMode::Auto {
+			return Ok(());
+		}
+		let mut nonces = BTreeMap::new();
+		for (caller, nonce) in extrinsics {
+			let key = match T::NONCE_STRATEGY {
+				NonceStrategy::PerAccount => Some(caller),
+				NonceStrategy::Global => None,
+			};
+			let next = nonces.entry(key).or_insert_with(|| self.next_nonce(caller));
+			if nonce != *next {
+				return Err("Invalid nonce");
+			}
+			*next = *next + T::Nonce::one();
+		}
+		Ok(())
+	}
+
+	/// Check the nonces of `extrinsics` as they are submitted in a block, see
+	/// `ensure_sequential_nonces`, once they are ordered following `ORDERING_POLICY`.
+	#[allow(clippy::type_complexity)]
+	pub fn ensure_valid_nonces<Call, Tip: Ord + Copy>(
+		&self,
+		extrinsics: &[Extrinsic<T::AccountId, Call, T::Nonce, Tip, T::BlockNumber>],
+	) -> DispatchResult {
+		if self.nonce_mode == NonceMode::Auto {
+			return Ok(());
+		}
+		let order = T::ORDERING_POLICY.order(extrinsics);
+		self.ensure_sequential_nonces(
+			order.into_iter().map(|i| (&extrinsics[i].caller, extrinsics[i].nonce)),
+		)
+	}
+
+	/// Set the nonce of `who`, for example when building the genesis state of a resumed chain.
+	/// This only affects the next nonce of `who` with the `PerAccount` nonce strategy.
+	pub fn set_nonce(&mut self, who: T::AccountId, nonce: T::Nonce) {
+		self.nonce.insert(who, nonce);
+	}
+
+	/// Increment the nonce of an account, or the global nonce with the `Global` nonce strategy.
+	/// This helps us keep track of how many transactions each account has made.
 	pub fn inc_nonce(&mut self, who: T::AccountId) {
-		self.nonce
-			.entry(who)
-			.and_modify(|curr| *curr = *curr + T::Nonce::one())
-			.or_insert(T::Nonce::one());
+		match T::NONCE_STRATEGY {
+			NonceStrategy::PerAccount => {
+				self.nonce
+					.entry(who)
+					.and_modify(|curr| *curr = *curr + T::Nonce::one())
+					.or_insert(T::Nonce::one());
+			},
+			NonceStrategy::Global => self.global_nonce = self.global_nonce + T::Nonce::one(),
+		}
+	}
+}
+
+/// Functions which need the accounts and nonces to be encoded.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Nonce: Encode,
+{
+	/// Get the root of the Merkle tree over the nonces, so that they can be covered by state
+	/// proofs. Each leaf is the hash of an account and its nonce, ordered by account, or the hash
+	/// of the shared nonce with a global `NONCE_STRATEGY`.
+	pub fn nonce_root(&self) -> [u8; 32] {
+		let leaves: Vec<_> = match T::NONCE_STRATEGY {
+			NonceStrategy::PerAccount => {
+				self.nonce.iter().map(|entry| Self::hash(&entry.encode())).collect()
+			},
+			NonceStrategy::Global => vec![Self::hash(&self.global_nonce.encode())],
+		};
+		crate::support::merkle_root::<T::Hashing>(&leaves)
+	}
+
+	/// Get the hash of the blacklist and the spec version, so that they can be covered by the
+	/// state root.
+	pub fn config_hash(&self) -> [u8; 32] {
+		Self::hash(&(&self.blacklist, self.spec_version).encode())
+	}
+}
+
+/// Functions which need the accounts and block numbers to be displayed.
+#[cfg(feature = "logging")]
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Display,
+	T::BlockNumber: Display,
+{
+	/// Log the dispatch of the extrinsic `index` of the current block, made by `caller` with the
+	/// `(pallet, call)` named `call`, as a JSON line passed to the log handler, if one is set.
+	pub fn log_dispatch(
+		&self,
+		index: u32,
+		caller: &T::AccountId,
+		call: (&str, &str),
+		result: &DispatchResult,
+	) {
+		if !self.log_handler.is_set() {
+			return;
+		}
+		let result = match result {
+			Ok(()) => String::from("\"result\":\"ok\""),
+			Err(error) => format!("\"result\":\"error\",\"error\":{}", json_string(error)),
+		};
+		let line = format!(
+			"{{\"block\":{},\"index\":{},\"caller\":{},\"call\":{},{}}}",
+			self.block_number,
+			index,
+			json_string(&caller.to_string()),
+			json_string(&format!("{}.{}", call.0, call.1)),
+			result
+		);
+		self.log_handler.notify(&line);
+	}
+}
+
+/// The system pallet collects the events of the whole runtime, for the current block.
+impl<T: Config, E: Into<T::RuntimeEvent>> DepositEvent<E> for Pallet<T> {
+	fn deposit_event(&mut self, event: E) {
+		let event = event.into();
+		self.event_handler.notify(&event);
+		self.events.deposit_event(event);
+	}
+}
+
+/// Functions which only make sense when accounts can be built from a string.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode + From<String>,
+{
+	/// Derive the sub-account number `index` of `parent`, for example to give a pallet several
+	/// accounts it controls. The same inputs always derive the same account: the hex encoded hash
+	/// of the parent and the index.
+	pub fn derive_sub_account(parent: &T::AccountId, index: u16) -> T::AccountId {
+		let mut bytes = b"sub".to_vec();
+		bytes.extend(parent.encode());
+		bytes.extend(index.encode());
+		let hash = Self::hash(&bytes);
+		hash.iter().map(|byte| format!("{byte:02x}")).collect::<String>().into()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Make a remark on chain. This does not change any state, it only deposits an event with the
+	/// `message`, which can be useful to anchor some data.
+	pub fn remark(&mut self, caller: T::AccountId, message: Vec<u8>) -> DispatchResult {
+		self.deposit_event(Event::Remarked { who: caller, message });
+		Ok(())
+	}
+
+	/// Forbid `who` from dispatching any call, as the `caller` which must be the sudo account.
+	pub fn add_to_blacklist(&mut self, caller: T::AccountId, who: T::AccountId) -> DispatchResult {
+		Self::ensure_sudo(&caller)?;
+		self.blacklist.insert(who);
+		Ok(())
+	}
+
+	/// Allow `who` to dispatch calls again, as the `caller` which must be the sudo account, see
+	/// `add_to_blacklist`.
+	pub fn remove_from_blacklist(
+		&mut self,
+		caller: T::AccountId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		Self::ensure_sudo(&caller)?;
+		self.blacklist.remove(&who);
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::GetWeight for Call<T> {
+	fn weight(&self) -> crate::support::Weight {
+		match self {
+			Call::remark { .. } => 1,
+			Call::add_to_blacklist { .. } => 1,
+			Call::remove_from_blacklist { .. } => 1,
+		}
 	}
 }
 
 #[cfg(test)]
 mod tests {
 
+	#[derive(Debug, PartialEq)]
 	struct TestConfig;
 
+	/// A hasher which returns a predictable output: every byte is the length of the input.
+	struct MockHasher;
+
+	impl crate::support::Hasher for MockHasher {
+		fn hash(bytes: &[u8]) -> [u8; 32] {
+			[bytes.len() as u8; 32]
+		}
+	}
+
 	impl super::Config for TestConfig {
 		type BlockNumber = u32;
 
 		type AccountId = String;
 
 		type Nonce = u32;
+
+		type Hashing = MockHasher;
+
+		type RuntimeEvent = super::Event<Self>;
+
+		const BLOCK_HASH_COUNT: u32 = 2;
+
+		const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+
+		const DISPATCH_LOG_CAPACITY: u32 = 2;
+	}
+
+	/// A config using the real hasher, since the mock one only looks at the length of its input.
+	struct Sha256Config;
+
+	impl super::Config for Sha256Config {
+		type BlockNumber = u32;
+		type AccountId = String;
+		type Nonce = u32;
+		type Hashing = crate::support::Sha256;
+		type RuntimeEvent = super::Event<Self>;
+		const BLOCK_HASH_COUNT: u32 = 2;
+		const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+		const DISPATCH_LOG_CAPACITY: u32 = 2;
 	}
 
 	#[test]
@@ -69,4 +665,212 @@ mod tests {
 		pallet.inc_nonce("Wassim".to_string());
 		assert_eq!(pallet.nonce.get("Wassim").unwrap(), &1);
 	}
+
+	#[test]
+	fn hashing_uses_configured_hasher() {
+		use super::*;
+
+		assert_eq!(Pallet::<TestConfig>::hash(b""), [0; 32]);
+		assert_eq!(Pallet::<TestConfig>::hash(b"Wassim"), [6; 32]);
+	}
+
+	#[test]
+	fn block_hashes() {
+		use super::*;
+
+		let mut pallet = Pallet::<TestConfig>::new();
+		assert_eq!(pallet.parent_hash(), [0; 32]);
+		pallet.inc_block_number();
+		pallet.set_block_hash(1, [1; 32]);
+		assert_eq!(pallet.block_hash(1), Some([1; 32]));
+		assert_eq!(pallet.block_hash(2), None);
+		assert_eq!(pallet.parent_hash(), [1; 32]);
+	}
+
+	#[test]
+	fn events_are_cleared_each_block() {
+		use super::*;
+
+		let mut pallet = Pallet::<TestConfig>::new();
+		pallet.deposit_event(Event::<TestConfig>::ExtrinsicSuccess { index: 0 });
+		assert_eq!(pallet.events(), &[Event::ExtrinsicSuccess { index: 0 }]);
+		pallet.inc_block_number();
+		assert_eq!(pallet.events(), &[]);
+	}
+
+	#[test]
+	fn prune_block_hashes() {
+		use super::*;
+
+		let mut pallet = Pallet::<TestConfig>::new();
+		for i in 1..=5 {
+			pallet.inc_block_number();
+			pallet.set_block_hash(i, [i as u8; 32]);
+		}
+		pallet.prune_block_hashes(2);
+		assert_eq!(pallet.block_hash(1), None);
+		assert_eq!(pallet.block_hash(3), None);
+		assert_eq!(pallet.block_hash(4), Some([4; 32]));
+		assert_eq!(pallet.block_hash(5), Some([5; 32]));
+		assert_eq!(pallet.parent_hash(), [5; 32]);
+
+		// Keeping more hashes than we have leaves them untouched.
+		pallet.prune_block_hashes(10);
+		assert_eq!(pallet.block_hash(4), Some([4; 32]));
+
+		// The finalize hook prunes to `BLOCK_HASH_COUNT`.
+		pallet.inc_block_number();
+		pallet.set_block_hash(6, [6; 32]);
+		pallet.on_finalize();
+		assert_eq!(pallet.block_hash(4), None);
+		assert_eq!(pallet.block_hash(5), Some([5; 32]));
+		assert_eq!(pallet.block_hash(6), Some([6; 32]));
+
+		pallet.prune_block_hashes(0);
+		assert_eq!(pallet.block_hash(6), None);
+	}
+
+	#[test]
+	fn dispatch_log_is_bounded() {
+		use super::*;
+
+		let mut pallet = Pallet::<TestConfig>::new();
+		for (block, result) in [(1, Ok(())), (1, Err("error")), (2, Ok(()))] {
+			pallet.record_dispatch(DispatchRecord {
+				block,
+				extrinsic_index: 0,
+				caller: "Wassim".to_string(),
+				result,
+			});
+		}
+
+		let log: Vec<_> = pallet.recent_dispatches().iter().map(|r| (r.block, r.result)).collect();
+		assert_eq!(log, vec![(1, Err("error")), (2, Ok(()))]);
+	}
+
+	#[test]
+	fn remark() {
+		use super::*;
+
+		let mut pallet = Pallet::<TestConfig>::new();
+		assert_eq!(pallet.remark("Wassim".to_string(), b"Hello, world!".to_vec()), Ok(()));
+		assert_eq!(
+			pallet.events(),
+			&[Event::Remarked { who: "Wassim".to_string(), message: b"Hello, world!".to_vec() }]
+		);
+	}
+
+	#[test]
+	fn derive_sub_account() {
+		use super::*;
+
+		let derive = Pallet::<Sha256Config>::derive_sub_account;
+		let wassim = "Wassim".to_string();
+		assert_eq!(derive(&wassim, 0), derive(&wassim, 0));
+		assert_ne!(derive(&wassim, 0), derive(&wassim, 1));
+		assert_ne!(derive(&wassim, 0), derive(&"Alice".to_string(), 0));
+		assert_ne!(derive(&wassim, 0), wassim);
+		assert_eq!(derive(&wassim, 0).len(), 64);
+	}
+
+	#[test]
+	fn clear() {
+		use super::*;
+
+		let mut pallet = Pallet::<TestConfig>::new();
+		pallet.inc_block_number();
+		pallet.set_block_hash(1, [1; 32]);
+		pallet.inc_nonce("Wassim".to_string());
+		pallet.deposit_event(Event::<TestConfig>::ExtrinsicSuccess { index: 0 });
+
+		pallet.clear();
+		assert_eq!(pallet.next_nonce(&"Wassim".to_string()), 0);
+		assert_eq!(pallet.events(), &[]);
+		assert_eq!(pallet.block_number(), 1);
+		assert_eq!(pallet.parent_hash(), [1; 32]);
+	}
+
+	#[test]
+	fn empty_blocks() {
+		use super::*;
+
+		struct NoEmptyBlocksConfig;
+
+		impl Config for NoEmptyBlocksConfig {
+			type BlockNumber = u32;
+			type AccountId = String;
+			type Nonce = u32;
+			type Hashing = MockHasher;
+			type RuntimeEvent = Event<Self>;
+			const BLOCK_HASH_COUNT: u32 = 2;
+			const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+			const DISPATCH_LOG_CAPACITY: u32 = 2;
+			const ALLOW_EMPTY_BLOCKS: bool = false;
+		}
+
+		assert_eq!(Pallet::<TestConfig>::ensure_block_not_empty(0), Ok(()));
+		assert_eq!(Pallet::<NoEmptyBlocksConfig>::ensure_block_not_empty(0), Err("Empty block"));
+		assert_eq!(Pallet::<NoEmptyBlocksConfig>::ensure_block_not_empty(1), Ok(()));
+	}
+
+	#[test]
+	fn nonce_strategies() {
+		use super::*;
+
+		struct GlobalNonceConfig;
+
+		impl Config for GlobalNonceConfig {
+			type BlockNumber = u32;
+			type AccountId = String;
+			type Nonce = u32;
+			type Hashing = MockHasher;
+			type RuntimeEvent = Event<Self>;
+			const BLOCK_HASH_COUNT: u32 = 2;
+			const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+			const DISPATCH_LOG_CAPACITY: u32 = 2;
+			const NONCE_STRATEGY: NonceStrategy = NonceStrategy::Global;
+		}
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+
+		let mut per_account = Pallet::<TestConfig>::new();
+		per_account.inc_nonce(alice.clone());
+		per_account.inc_nonce(alice.clone());
+		per_account.inc_nonce(bob.clone());
+		assert_eq!(per_account.next_nonce(&alice), 2);
+		assert_eq!(per_account.next_nonce(&bob), 1);
+
+		let mut global = Pallet::<GlobalNonceConfig>::new();
+		global.inc_nonce(alice.clone());
+		global.inc_nonce(alice.clone());
+		global.inc_nonce(bob.clone());
+		assert_eq!(global.next_nonce(&alice), 3);
+		assert_eq!(global.next_nonce(&bob), 3);
+		assert_eq!(global.next_nonce(&"charlie".to_string()), 3);
+	}
+
+	#[test]
+	fn nonce_root() {
+		use super::*;
+
+		let alice = "alice".to_string();
+		let mut pallet = Pallet::<Sha256Config>::new();
+		let empty_root = pallet.nonce_root();
+
+		pallet.inc_nonce(alice.clone());
+		let root = pallet.nonce_root();
+		assert_ne!(root, empty_root);
+
+		// Changes which do not touch any nonce keep the root.
+		pallet.inc_block_number();
+		pallet.deposit_event(Event::<Sha256Config>::ExtrinsicSuccess { index: 0 });
+		assert_eq!(pallet.nonce_root(), root);
+
+		pallet.inc_nonce(alice.clone());
+		assert_ne!(pallet.nonce_root(), root);
+		let root = pallet.nonce_root();
+		pallet.inc_nonce("bob".to_string());
+		assert_ne!(pallet.nonce_root(), root);
+	}
 }
@@ -0,0 +1,268 @@
+use num::{Saturating, Zero};
+use std::collections::BTreeMap;
+
+use crate::support::{DepositEvent, DispatchResult, Encode, EventBuffer};
+
+pub trait Config: crate::balances::Config {
+	/// Whether `who` is allowed to approve spend proposals, for example a sudo account.
+	fn is_approver(who: &Self::AccountId) -> bool;
+	/// The number of times the payment of an approved spend is retried after it failed, before
+	/// the spend is dropped, so that a spend which can never be paid does not hold its amount.
+	const MAX_SPEND_RETRIES: u32;
+}
+
+/// The events emitted by the Treasury Module.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Event<T: Config> {
+	/// `proposer` proposed to spend `amount` from the treasury for `beneficiary`.
+	SpendProposed {
+		/// The id which the proposal is approved with.
+		id: u32,
+		proposer: T::AccountId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+	},
+	/// The spend proposal `id` was approved, and will be paid at the end of the block.
+	SpendApproved { id: u32 },
+	/// The approved spend `id` could not be paid because of `error`. It stays approved, and its
+	/// payment is retried at the end of the next block.
+	SpendFailed { id: u32, error: &'static str },
+	/// The approved spend `id` could not be paid because of `error`, after `MAX_SPEND_RETRIES`
+	/// retries. It is dropped, and its amount stays in the treasury.
+	SpendDropped { id: u32, error: &'static str },
+}
+
+/// A proposal to spend some of the treasury funds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendProposal<AccountId, Balance> {
+	pub proposer: AccountId,
+	pub beneficiary: AccountId,
+	pub amount: Balance,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for SpendProposal<AccountId, Balance> {
+	fn encode(&self) -> Vec<u8> {
+		(&self.proposer, &self.beneficiary, &self.amount).encode()
+	}
+}
+
+/// This is the Treasury Module.
+/// It is a simple module which allows accounts to propose spending the funds collected by the
+/// treasury, and an approver to accept those proposals. The treasury funds are held by the
+/// balances pallet, so the runtime pays the approved spends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pallet<T: Config> {
+	/// The proposals which have not been approved yet, by id.
+	proposals: BTreeMap<u32, SpendProposal<T::AccountId, T::Balance>>,
+	/// The id of the next proposal.
+	next_proposal_id: u32,
+	/// The approved spends which have not been paid yet, as `(id, beneficiary, amount)` entries in
+	/// the order they were approved.
+	approved: Vec<(u32, T::AccountId, T::Balance)>,
+	/// The number of times the payment of each approved spend failed, for the spends which failed
+	/// at least once.
+	failures: BTreeMap<u32, u32>,
+	/// The events deposited by this pallet which have not been collected by the runtime yet.
+	events: EventBuffer<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Treasury Module.
+	pub fn new() -> Self {
+		Self {
+			proposals: BTreeMap::new(),
+			next_proposal_id: 0,
+			approved: Vec::new(),
+			failures: BTreeMap::new(),
+			events: EventBuffer::new(),
+		}
+	}
+
+	/// Clear all the proposals and approved spends.
+	pub fn clear(&mut self) {
+		self.proposals.clear();
+		self.next_proposal_id = 0;
+		self.approved.clear();
+		self.failures.clear();
+	}
+
+	/// Take all the events deposited by this pallet since they were last taken.
+	pub fn take_events(&mut self) -> Vec<Event<T>> {
+		self.events.take()
+	}
+
+	/// Get the spend proposal `id`, if it exists and has not been approved yet.
+	pub fn proposal(&self, id: u32) -> Option<&SpendProposal<T::AccountId, T::Balance>> {
+		self.proposals.get(&id)
+	}
+
+	/// Get the total amount of the approved spends which have not been paid yet.
+	pub fn approved_total(&self) -> T::Balance {
+		self.approved
+			.iter()
+			.fold(T::Balance::zero(), |total, (_, _, amount)| total.saturating_add(*amount))
+	}
+
+	/// Remove and return all the approved spends, in the order they were approved, so that the
+	/// runtime can pay them.
+	pub fn take_approved(&mut self) -> Vec<(u32, T::AccountId, T::Balance)> {
+		let approved = core::mem::take(&mut self.approved);
+		// Forget the failures of the spends which were paid since.
+		self.failures
+			.retain(|id, _| approved.iter().any(|(approved, ..)| approved == id));
+		approved
+	}
+
+	/// Record that the runtime could not pay the approved spend `id` of `amount` to `beneficiary`
+	/// because of `error`. The spend is approved again, so that it is retried later, unless it
+	/// already failed `MAX_SPEND_RETRIES` times after its first attempt, in which case it is
+	/// dropped.
+	pub fn spend_failed(
+		&mut self,
+		id: u32,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+		error: &'static str,
+	) {
+		let failures = self.failures.get(&id).map_or(1, |failures| failures.saturating_add(1));
+		if failures > T::MAX_SPEND_RETRIES {
+			self.failures.remove(&id);
+			self.events.deposit_event(Event::SpendDropped { id, error });
+			return;
+		}
+		self.failures.insert(id, failures);
+		self.approved.push((id, beneficiary, amount));
+		self.events.deposit_event(Event::SpendFailed { id, error });
+	}
+}
+
+/// Functions which need the accounts and balances to be encoded.
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+{
+	/// Get the hash of the proposals, the approved spends and their failures, so that they can be
+	/// covered by the state root.
+	pub fn state_hash(&self) -> [u8; 32] {
+		let mut bytes = self.proposals.encode();
+		bytes.extend(self.next_proposal_id.encode());
+		bytes.extend(self.approved.encode());
+		bytes.extend(self.failures.encode());
+		crate::system::Pallet::<T>::hash(&bytes)
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose, as the `caller`, to spend `amount` from the treasury for `beneficiary`.
+	pub fn propose_spend(
+		&mut self,
+		caller: T::AccountId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let id = self.next_proposal_id;
+		self.next_proposal_id = id.checked_add(1).ok_or("Too many spend proposals")?;
+		self.proposals.insert(
+			id,
+			SpendProposal { proposer: caller.clone(), beneficiary: beneficiary.clone(), amount },
+		);
+		self.events.deposit_event(Event::SpendProposed {
+			id,
+			proposer: caller,
+			beneficiary,
+			amount,
+		});
+		Ok(())
+	}
+
+	/// Approve the spend proposal `id`, so that it is paid at the end of the block.
+	/// This returns an error if the caller is not an approver, or if there is no such proposal.
+	pub fn approve_spend(&mut self, caller: T::AccountId, id: u32) -> DispatchResult {
+		if !T::is_approver(&caller) {
+			return Err("Not a treasury approver");
+		}
+		let proposal = self.proposals.remove(&id).ok_or("No such spend proposal")?;
+		self.approved.push((id, proposal.beneficiary, proposal.amount));
+		self.events.deposit_event(Event::SpendApproved { id });
+		Ok(())
+	}
+}
+
+impl<T: Config> crate::support::GetWeight for Call<T> {
+	fn weight(&self) -> crate::support::Weight {
+		match self {
+			Call::propose_spend { .. } => 5,
+			Call::approve_spend { .. } => 5,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	struct TestConfig;
+
+	impl super::Config for TestConfig {
+		fn is_approver(who: &&'static str) -> bool {
+			*who == "sudo"
+		}
+		const MAX_SPEND_RETRIES: u32 = 1;
+	}
+
+	impl crate::balances::Config for TestConfig {
+		type Balance = u32;
+		type OnTransfer = ();
+		type OnReap = ();
+		const DECIMALS: u32 = 2;
+		const MAX_BALANCE: u32 = 200;
+	}
+
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Hashing = crate::support::Sha256;
+		type RuntimeEvent = crate::system::Event<Self>;
+		const BLOCK_HASH_COUNT: u32 = 256;
+		const MAX_BLOCK_WEIGHT: crate::support::Weight = 1_000;
+	}
+
+	#[test]
+	fn approve_spend() {
+		let mut treasury = super::Pallet::<TestConfig>::new();
+		assert_eq!(treasury.propose_spend("alice", "bob", 30), Ok(()));
+		assert_eq!(treasury.propose_spend("alice", "charlie", 20), Ok(()));
+		assert_eq!(treasury.proposal(0).map(|proposal| proposal.amount), Some(30));
+
+		assert_eq!(treasury.approve_spend("alice", 0), Err("Not a treasury approver"));
+		assert_eq!(treasury.approve_spend("sudo", 2), Err("No such spend proposal"));
+		assert_eq!(treasury.approve_spend("sudo", 1), Ok(()));
+		assert_eq!(treasury.approve_spend("sudo", 0), Ok(()));
+		// A proposal can only be approved once.
+		assert_eq!(treasury.approve_spend("sudo", 0), Err("No such spend proposal"));
+
+		assert_eq!(treasury.approved_total(), 50);
+		assert_eq!(treasury.take_approved(), vec![(1, "charlie", 20), (0, "bob", 30)]);
+		assert_eq!(treasury.approved_total(), 0);
+
+		// A spend which could not be paid is approved again.
+		treasury.take_events();
+		treasury.spend_failed(0, "bob", 30, "Not enough funds!");
+		assert_eq!(treasury.approved_total(), 30);
+		assert!(matches!(
+			treasury.take_events().as_slice(),
+			[super::Event::SpendFailed { id: 0, error: "Not enough funds!" }]
+		));
+		assert_eq!(treasury.take_approved(), vec![(0, "bob", 30)]);
+
+		// Once its `MAX_SPEND_RETRIES` retries failed too, it is dropped.
+		treasury.spend_failed(0, "bob", 30, "Not enough funds!");
+		assert_eq!(treasury.approved_total(), 0);
+		assert!(matches!(
+			treasury.take_events().as_slice(),
+			[super::Event::SpendDropped { id: 0, error: "Not enough funds!" }]
+		));
+	}
+}